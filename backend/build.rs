@@ -0,0 +1,6 @@
+fn main() {
+    // 迁移通过 embed_migrations! 编译进程序，目录变化时需要重新编译
+    println!("cargo:rerun-if-changed=migrations");
+    // 前端构建产物通过 RustEmbed 打包，重新构建前端后需要重新编译
+    println!("cargo:rerun-if-changed=../web/dist");
+}
@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS idx_images_root_uploaded_at;
+DROP TABLE IF EXISTS images;
@@ -0,0 +1,22 @@
+CREATE TABLE IF NOT EXISTS images (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    root TEXT NOT NULL,
+    name TEXT NOT NULL,
+    original_name TEXT NOT NULL,
+    size BIGINT NOT NULL,
+    width INTEGER NOT NULL,
+    height INTEGER NOT NULL,
+    mime TEXT NOT NULL,
+    sha256 TEXT NOT NULL,
+    uploaded_at TIMESTAMP NOT NULL,
+    uploader TEXT,
+    camera_make TEXT,
+    camera_model TEXT,
+    taken_at TIMESTAMP,
+    orientation INTEGER,
+    gps_latitude DOUBLE,
+    gps_longitude DOUBLE,
+    UNIQUE (root, name)
+);
+
+CREATE INDEX IF NOT EXISTS idx_images_root_uploaded_at ON images (root, uploaded_at);
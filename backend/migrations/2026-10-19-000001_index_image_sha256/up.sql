@@ -0,0 +1 @@
+CREATE UNIQUE INDEX IF NOT EXISTS idx_images_root_sha256 ON images (root, sha256);
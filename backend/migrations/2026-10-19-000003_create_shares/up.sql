@@ -0,0 +1,10 @@
+CREATE TABLE IF NOT EXISTS shares (
+    id TEXT PRIMARY KEY NOT NULL,
+    root TEXT NOT NULL,
+    path TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL,
+    expires_at TIMESTAMP NOT NULL,
+    max_downloads INTEGER,
+    downloads INTEGER NOT NULL DEFAULT 0,
+    revoked_at TIMESTAMP
+);
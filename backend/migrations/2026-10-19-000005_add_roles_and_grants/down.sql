@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS root_grants;
+ALTER TABLE users DROP COLUMN role;
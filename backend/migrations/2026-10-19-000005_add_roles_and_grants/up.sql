@@ -0,0 +1,11 @@
+ALTER TABLE users ADD COLUMN role TEXT NOT NULL DEFAULT 'viewer';
+
+-- 升级前创建的第一个用户即初始管理员
+UPDATE users SET role = 'admin' WHERE id = (SELECT MIN(id) FROM users);
+
+CREATE TABLE IF NOT EXISTS root_grants (
+    user_id INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    root TEXT NOT NULL,
+    access TEXT NOT NULL,
+    PRIMARY KEY (user_id, root)
+);
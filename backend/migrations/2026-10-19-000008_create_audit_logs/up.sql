@@ -0,0 +1,18 @@
+-- 不关联 users 外键，删除用户后仍保留其操作记录
+CREATE TABLE IF NOT EXISTS audit_logs (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    created_at TIMESTAMP NOT NULL,
+    user_id INTEGER,
+    username TEXT,
+    ip TEXT,
+    method TEXT NOT NULL,
+    action TEXT NOT NULL,
+    target TEXT NOT NULL,
+    params TEXT,
+    status INTEGER NOT NULL,
+    success BOOLEAN NOT NULL,
+    error TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_audit_logs_created_at ON audit_logs (created_at);
+CREATE INDEX IF NOT EXISTS idx_audit_logs_username ON audit_logs (username, created_at);
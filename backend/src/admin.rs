@@ -0,0 +1,226 @@
+// 供命令行使用的管理操作，不经过 HTTP 接口，直接读写数据库
+use chrono::{NaiveDateTime, Utc};
+use diesel::prelude::*;
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
+use serde::Serialize;
+use std::path::Path;
+
+use crate::ServerError;
+use crate::auth_api::model::{NewUser, User};
+use crate::auth_api::password::{check_password_policy, hash_password, random_password};
+use crate::auth_api::role::Role;
+use crate::config::{Config, DatabaseConfig};
+use crate::db::Database;
+use crate::error::AppError;
+use crate::schema::{sessions, users};
+use crate::storage::Storage;
+use crate::user_api::check_username;
+
+/// 命令行中显示的用户信息
+#[derive(Debug, Serialize)]
+pub struct UserSummary {
+    pub id: i32,
+    pub username: String,
+    pub role: String,
+    pub disabled: bool,
+    pub totp_enabled: bool,
+    pub created_at: NaiveDateTime,
+}
+
+impl From<User> for UserSummary {
+    fn from(user: User) -> Self {
+        UserSummary {
+            id: user.id,
+            username: user.username,
+            role: user.role,
+            disabled: user.disabled,
+            totp_enabled: user.totp_enabled,
+            created_at: user.created_at,
+        }
+    }
+}
+
+/// 某一时刻的 CPU、内存和交换分区使用情况
+#[derive(Debug, Serialize)]
+pub struct SystemSnapshot {
+    pub version: &'static str,
+    pub cpu_cores: usize,
+    /// 0.0~1.0
+    pub cpu_usage_ratio: f32,
+    pub mem_total_kb: u64,
+    pub mem_used_kb: u64,
+    pub mem_usage_ratio: f32,
+    pub swap_total_kb: u64,
+    pub swap_used_kb: u64,
+    pub swap_usage_ratio: f32,
+}
+
+fn command_err(e: AppError) -> ServerError {
+    ServerError::Command(e.to_string())
+}
+
+/// 把默认配置写入 path，文件已存在且 force 为 false 时返回错误
+pub fn write_default_config(path: &Path, force: bool) -> Result<(), ServerError> {
+    if path.exists() && !force {
+        return Err(ServerError::Command(format!(
+            "{} already exists, use --force to overwrite",
+            path.display()
+        )));
+    }
+    let text = Config::default().to_toml()?;
+    if let Some(parent) = path.parent()
+        && !parent.as_os_str().is_empty()
+    {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| ServerError::Command(format!("{}: {}", parent.display(), e)))?;
+    }
+    std::fs::write(path, text)
+        .map_err(|e| ServerError::Command(format!("{}: {}", path.display(), e)))
+}
+
+/// 执行尚未执行的迁移，返回本次执行的版本
+pub fn migrate(config: &DatabaseConfig) -> Result<Vec<String>, ServerError> {
+    Database::migrate(config).map(|(_, applied)| applied)
+}
+
+/// 创建存储根目录和数据库，没有用户时创建 admin，返回其随机密码
+pub fn init(config: &Config) -> Result<Option<String>, ServerError> {
+    config.validate()?;
+    Storage::from_config(&config.storage)?;
+    let db = Database::open(&config.database)?;
+    crate::auth_api::ensure_admin(&db)
+}
+
+/// 校验配置，并检查存储根是否可用，与服务启动时的检查相同
+pub fn check_config(config: &Config) -> Result<(), ServerError> {
+    config.validate()?;
+    Storage::from_config(&config.storage)?;
+    Ok(())
+}
+
+pub fn list_users(config: &DatabaseConfig) -> Result<Vec<UserSummary>, ServerError> {
+    let db = Database::open(config)?;
+    let mut conn = db.connect().map_err(command_err)?;
+    let list = users::table
+        .order(users::id.asc())
+        .select(User::as_select())
+        .load(&mut conn)
+        .map_err(|e| command_err(e.into()))?;
+    Ok(list.into_iter().map(UserSummary::from).collect())
+}
+
+/// 创建用户；password 为 None 时生成随机密码，和用户一起返回
+pub fn add_user(
+    config: &DatabaseConfig,
+    username: &str,
+    role: &str,
+    password: Option<&str>,
+) -> Result<(UserSummary, Option<String>), ServerError> {
+    let username = check_username(username).map_err(command_err)?;
+    let role: Role = role.parse().map_err(command_err)?;
+    let generated = password.is_none().then(random_password);
+    let password = password.or(generated.as_deref()).unwrap_or_default();
+    check_password_policy("password", password).map_err(command_err)?;
+    let hash = hash_password(password).map_err(command_err)?;
+    let db = Database::open(config)?;
+    let mut conn = db.connect().map_err(command_err)?;
+    let now = Utc::now().naive_utc();
+    diesel::insert_into(users::table)
+        .values(&NewUser {
+            username,
+            password_hash: &hash,
+            role: role.as_str(),
+            created_at: now,
+            updated_at: now,
+        })
+        .execute(&mut conn)
+        .map_err(|e| match e {
+            DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, _) => {
+                ServerError::Command(format!("user already exists: {}", username))
+            }
+            e => command_err(e.into()),
+        })?;
+    let user = users::table
+        .filter(users::username.eq(username))
+        .select(User::as_select())
+        .first(&mut conn)
+        .map_err(|e| command_err(e.into()))?;
+    Ok((user.into(), generated))
+}
+
+/// 重置密码并注销该用户的全部会话；password 为 None 时生成随机密码并返回
+pub fn set_password(
+    config: &DatabaseConfig,
+    username: &str,
+    password: Option<&str>,
+) -> Result<Option<String>, ServerError> {
+    let generated = password.is_none().then(random_password);
+    let password = password.or(generated.as_deref()).unwrap_or_default();
+    check_password_policy("password", password).map_err(command_err)?;
+    let hash = hash_password(password).map_err(command_err)?;
+    let db = Database::open(config)?;
+    let mut conn = db.connect().map_err(command_err)?;
+    conn.transaction(|conn| {
+        let id: i32 = users::table
+            .filter(users::username.eq(username))
+            .select(users::id)
+            .first(conn)
+            .optional()?
+            .ok_or_else(|| AppError::NotFound(username.to_string()))?;
+        diesel::update(users::table.find(id))
+            .set((
+                users::password_hash.eq(&hash),
+                users::updated_at.eq(Utc::now().naive_utc()),
+            ))
+            .execute(conn)?;
+        diesel::delete(sessions::table.filter(sessions::user_id.eq(id))).execute(conn)?;
+        Ok::<_, AppError>(())
+    })
+    .map_err(|e| match e {
+        AppError::NotFound(name) => ServerError::Command(format!("user not found: {}", name)),
+        e => command_err(e),
+    })?;
+    Ok(generated)
+}
+
+/// 采样 CPU 使用率需要约 100ms
+pub fn system_snapshot() -> SystemSnapshot {
+    let mem = system_info::mem::get_mem_info();
+    let swap = system_info::swap::get_swap_info();
+    SystemSnapshot {
+        version: crate::VERSION,
+        cpu_cores: system_info::cpu::count(),
+        cpu_usage_ratio: system_info::cpu::usage() as f32 / 1000.0,
+        mem_total_kb: mem.total_kb,
+        mem_used_kb: mem.used_kb,
+        mem_usage_ratio: mem.usage_ratio as f32,
+        swap_total_kb: swap.total_kb,
+        swap_used_kb: swap.used_kb,
+        swap_usage_ratio: swap.usage_ratio as f32,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_users() {
+        let test_db = crate::db::TestDb::new();
+        let config = &test_db.config;
+        let (user, generated) = add_user(config, "alice", "viewer", None).unwrap();
+        assert_eq!(user.role, "viewer");
+        assert_eq!(generated.unwrap().len(), 16);
+        assert!(add_user(config, "alice", "viewer", Some("password1")).is_err());
+        assert!(add_user(config, "bob", "root", Some("password1")).is_err());
+        assert!(add_user(config, "bob", "admin", Some("short")).is_err());
+        assert_eq!(
+            set_password(config, "alice", Some("password2")).unwrap(),
+            None
+        );
+        assert!(set_password(config, "carol", Some("password2")).is_err());
+        let users = list_users(config).unwrap();
+        assert_eq!(users.len(), 1);
+        assert_eq!(users[0].username, "alice");
+    }
+}
@@ -0,0 +1,262 @@
+use actix_web::http::header::{ContentDisposition, DispositionParam, DispositionType};
+use actix_web::{HttpResponse, Responder, get, web};
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use diesel::sqlite::Sqlite;
+use serde::Deserialize;
+use utoipa::{IntoParams, ToSchema};
+
+use super::model::AuditLog;
+use crate::base::{Page, Response};
+use crate::db::Database;
+use crate::error::{AppError, Validator};
+use crate::openapi::tags;
+use crate::schema::audit_logs;
+
+const DEFAULT_PAGE_SIZE: i64 = 50;
+const MAX_PAGE_SIZE: i64 = 500;
+/// 单次导出的最大条数
+const MAX_EXPORT_ROWS: i64 = 100_000;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+enum ExportFormat {
+    #[default]
+    Json,
+    Csv,
+}
+
+/// 列表和导出共用的查询参数，时间为 UTC，如 2026-10-19T08:00:00
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct AuditQuery {
+    username: Option<String>,
+    /// 路由模板或请求路径中包含的文本
+    action: Option<String>,
+    method: Option<String>,
+    success: Option<bool>,
+    from: Option<NaiveDateTime>,
+    to: Option<NaiveDateTime>,
+    page: Option<i64>,
+    page_size: Option<i64>,
+    #[serde(default)]
+    format: ExportFormat,
+}
+
+fn filtered(query: &AuditQuery) -> audit_logs::BoxedQuery<'_, Sqlite> {
+    let mut q = audit_logs::table.into_boxed();
+    if let Some(username) = &query.username {
+        q = q.filter(audit_logs::username.eq(username));
+    }
+    if let Some(action) = &query.action {
+        let pattern = format!(
+            "%{}%",
+            action
+                .replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_")
+        );
+        q = q.filter(
+            audit_logs::action
+                .like(pattern.clone())
+                .escape('\\')
+                .or(audit_logs::target.like(pattern).escape('\\')),
+        );
+    }
+    if let Some(method) = &query.method {
+        q = q.filter(audit_logs::method.eq(method.to_uppercase()));
+    }
+    if let Some(success) = query.success {
+        q = q.filter(audit_logs::success.eq(success));
+    }
+    if let Some(from) = query.from {
+        q = q.filter(audit_logs::created_at.ge(from));
+    }
+    if let Some(to) = query.to {
+        q = q.filter(audit_logs::created_at.lt(to));
+    }
+    q
+}
+
+/// 按时间倒序分页查询审计记录
+#[utoipa::path(
+    tag = tags::AUDIT,
+    params(AuditQuery),
+    responses((status = 200, body = Response<Page<AuditLog>>))
+)]
+#[get("")]
+async fn list_logs(
+    query: web::Query<AuditQuery>,
+    db: web::Data<Database>,
+) -> Result<impl Responder, AppError> {
+    let query = query.into_inner();
+    let page = query.page.unwrap_or(1);
+    let page_size = query.page_size.unwrap_or(DEFAULT_PAGE_SIZE);
+    Validator::new()
+        .check(page >= 1, "page", "必须大于 0")
+        .check(
+            (1..=MAX_PAGE_SIZE).contains(&page_size),
+            "page_size",
+            format!("必须在 1~{} 之间", MAX_PAGE_SIZE),
+        )
+        .finish()?;
+    let result = web::block(move || -> Result<Page<AuditLog>, AppError> {
+        let mut conn = db.connect()?;
+        let total = filtered(&query).count().get_result(&mut conn)?;
+        let items = filtered(&query)
+            .order(audit_logs::id.desc())
+            .limit(page_size)
+            .offset((page - 1) * page_size)
+            .select(AuditLog::as_select())
+            .load(&mut conn)?;
+        Ok(Page {
+            items,
+            total,
+            page,
+            page_size,
+        })
+    })
+    .await??;
+    Ok(Response::ok(result))
+}
+
+/// 以公式字符开头的单元格前加 `'`，避免表格软件把用户输入的内容当作公式执行
+fn escape_formula(cell: String) -> String {
+    if cell.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        format!("'{}", cell)
+    } else {
+        cell
+    }
+}
+
+fn to_csv(logs: &[AuditLog]) -> Result<Vec<u8>, AppError> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    let csv_err = |e: csv::Error| AppError::Unknown(format!("生成 CSV 失败: {}", e));
+    writer
+        .write_record([
+            "id",
+            "created_at",
+            "user_id",
+            "username",
+            "ip",
+            "method",
+            "action",
+            "target",
+            "params",
+            "status",
+            "success",
+            "error",
+        ])
+        .map_err(csv_err)?;
+    for log in logs {
+        writer
+            .write_record(
+                [
+                    log.id.to_string(),
+                    log.created_at.format("%Y-%m-%dT%H:%M:%S%.3f").to_string(),
+                    log.user_id.map(|id| id.to_string()).unwrap_or_default(),
+                    log.username.clone().unwrap_or_default(),
+                    log.ip.clone().unwrap_or_default(),
+                    log.method.clone(),
+                    log.action.clone(),
+                    log.target.clone(),
+                    log.params.clone().unwrap_or_default(),
+                    log.status.to_string(),
+                    log.success.to_string(),
+                    log.error.clone().unwrap_or_default(),
+                ]
+                .map(escape_formula),
+            )
+            .map_err(csv_err)?;
+    }
+    writer
+        .into_inner()
+        .map_err(|e| AppError::Unknown(format!("生成 CSV 失败: {}", e)))
+}
+
+/// 按筛选条件导出为 JSON 或 CSV 附件，按时间正序，最多 MAX_EXPORT_ROWS 条
+#[utoipa::path(
+    tag = tags::AUDIT,
+    params(AuditQuery),
+    responses((status = 200, description = "JSON 数组或 CSV 附件", content(
+        (Vec<AuditLog> = "application/json"),
+        (String = "text/csv"),
+    )))
+)]
+#[get("/export")]
+async fn export_logs(
+    query: web::Query<AuditQuery>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let query = query.into_inner();
+    let format = query.format;
+    let logs = web::block(move || -> Result<Vec<AuditLog>, AppError> {
+        Ok(filtered(&query)
+            .order(audit_logs::id.asc())
+            .limit(MAX_EXPORT_ROWS)
+            .select(AuditLog::as_select())
+            .load(&mut db.connect()?)?)
+    })
+    .await??;
+    let (body, content_type, file_name) = match format {
+        ExportFormat::Json => (
+            serde_json::to_vec(&logs).map_err(|e| AppError::Unknown(e.to_string()))?,
+            "application/json",
+            "audit.json",
+        ),
+        ExportFormat::Csv => (to_csv(&logs)?, "text/csv; charset=utf-8", "audit.csv"),
+    };
+    Ok(HttpResponse::Ok()
+        .content_type(content_type)
+        .insert_header(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename(file_name.into())],
+        })
+        .body(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    #[test]
+    fn test_to_csv() {
+        let log = AuditLog {
+            id: 1,
+            created_at: Utc::now().naive_utc(),
+            user_id: Some(1),
+            username: Some("admin".into()),
+            ip: Some("127.0.0.1".into()),
+            method: "POST".into(),
+            action: "/v1/img/delete".into(),
+            target: "/v1/img/delete".into(),
+            params: Some(r#"{"body":{"names":["a.png","b,c.png"]}}"#.into()),
+            status: 200,
+            success: true,
+            error: None,
+        };
+        let injected = AuditLog {
+            username: Some("=HYPERLINK(\"http://x\")".into()),
+            target: "@SUM(A1)".into(),
+            error: Some("-1+1".into()),
+            ..log.clone()
+        };
+        let csv = String::from_utf8(to_csv(&[log, injected]).unwrap()).unwrap();
+        let mut lines = csv.lines();
+        assert!(lines.next().unwrap().starts_with("id,created_at,"));
+        // 含逗号和引号的字段需要加引号转义
+        assert!(
+            lines
+                .next()
+                .unwrap()
+                .contains(r#""{""body"":{""names"":[""a.png"",""b,c.png""]}}""#)
+        );
+        // 可能被当作公式的单元格加 ' 前缀
+        let line = lines.next().unwrap();
+        assert!(line.contains(r#","'=HYPERLINK(""http://x"")","#));
+        assert!(line.contains(",'@SUM(A1),"));
+        assert!(line.ends_with(",'-1+1"));
+        assert!(line.contains(",POST,"));
+    }
+}
@@ -0,0 +1,107 @@
+mod api;
+pub mod model;
+use chrono::{Duration, NaiveDateTime};
+use diesel::prelude::*;
+use log::{info, warn};
+use tokio::task::JoinHandle;
+use utoipa_actix_web::service_config::ServiceConfig;
+
+use crate::db::Database;
+use crate::error::AppError;
+use crate::lifecycle::StopSignal;
+use crate::schema::audit_logs;
+use model::NewAuditLog;
+
+/// 保留期清理的间隔
+const PRUNE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(3600);
+
+/// /v1/audit 下的查询和导出接口
+pub fn handle(cfg: &mut ServiceConfig) {
+    cfg.service(api::list_logs);
+    cfg.service(api::export_logs);
+}
+
+pub fn record(conn: &mut SqliteConnection, entry: &NewAuditLog) -> Result<(), AppError> {
+    diesel::insert_into(audit_logs::table)
+        .values(entry)
+        .execute(conn)?;
+    Ok(())
+}
+
+/// 删除超过保留天数的记录，返回删除的条数；retention_days 为 0 时不删除
+pub fn prune(
+    conn: &mut SqliteConnection,
+    now: NaiveDateTime,
+    retention_days: u32,
+) -> Result<usize, AppError> {
+    if retention_days == 0 {
+        return Ok(0);
+    }
+    let before = now - Duration::days(retention_days as i64);
+    Ok(
+        diesel::delete(audit_logs::table.filter(audit_logs::created_at.lt(before)))
+            .execute(conn)?,
+    )
+}
+
+/// 启动后台任务，每小时按保留期清理一次，收到停止通知后退出
+pub fn spawn_pruner(
+    db: Database,
+    retention_days: u32,
+    mut stop: StopSignal,
+) -> Option<JoinHandle<()>> {
+    if retention_days == 0 {
+        return None;
+    }
+    Some(tokio::spawn(async move {
+        let mut interval = tokio::time::interval(PRUNE_INTERVAL);
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = stop.changed() => break,
+            }
+            let now = chrono::Utc::now().naive_utc();
+            match db.run(move |conn| prune(conn, now, retention_days)).await {
+                Ok(0) => {}
+                Ok(n) => info!("Pruned {} audit log entries", n),
+                Err(e) => warn!("Failed to prune audit log: {}", e),
+            }
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::TestDb;
+    use chrono::Utc;
+
+    fn entry(created_at: NaiveDateTime) -> NewAuditLog {
+        NewAuditLog {
+            created_at,
+            user_id: Some(1),
+            username: Some("admin".into()),
+            ip: None,
+            method: "DELETE".into(),
+            action: "/v1/img/{name}".into(),
+            target: "/v1/img/a.png".into(),
+            params: None,
+            status: 200,
+            success: true,
+            error: None,
+        }
+    }
+
+    #[test]
+    fn test_prune() {
+        let test_db = TestDb::new();
+        let mut conn = test_db.conn();
+        let now = Utc::now().naive_utc();
+        record(&mut conn, &entry(now - Duration::days(40))).unwrap();
+        record(&mut conn, &entry(now - Duration::days(10))).unwrap();
+        assert_eq!(prune(&mut conn, now, 0).unwrap(), 0);
+        assert_eq!(prune(&mut conn, now, 30).unwrap(), 1);
+        let left: i64 = audit_logs::table.count().get_result(&mut conn).unwrap();
+        assert_eq!(left, 1);
+    }
+}
@@ -0,0 +1,44 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::schema::audit_logs;
+
+/// 一条审计记录
+#[derive(Debug, Clone, Queryable, Selectable, Serialize, Deserialize, ToSchema)]
+#[diesel(table_name = audit_logs, check_for_backend(diesel::sqlite::Sqlite))]
+pub struct AuditLog {
+    pub id: i32,
+    pub created_at: NaiveDateTime,
+    /// 未登录的请求（如登录失败）为空
+    pub user_id: Option<i32>,
+    pub username: Option<String>,
+    pub ip: Option<String>,
+    pub method: String,
+    /// 路由模板，如 /v1/img/{name}
+    pub action: String,
+    /// 实际请求路径
+    pub target: String,
+    /// 查询参数和请求体的 JSON，敏感字段已脱敏
+    pub params: Option<String>,
+    pub status: i32,
+    pub success: bool,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Insertable)]
+#[diesel(table_name = audit_logs)]
+pub struct NewAuditLog {
+    pub created_at: NaiveDateTime,
+    pub user_id: Option<i32>,
+    pub username: Option<String>,
+    pub ip: Option<String>,
+    pub method: String,
+    pub action: String,
+    pub target: String,
+    pub params: Option<String>,
+    pub status: i32,
+    pub success: bool,
+    pub error: Option<String>,
+}
@@ -0,0 +1,264 @@
+use actix_web::cookie::{Cookie, SameSite, time};
+use actix_web::{HttpRequest, HttpResponse, Responder, get, post, web};
+use chrono::{Duration, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::model::{LoginChallenge, User};
+use super::password::{check_password_policy, hash_password, verify_password};
+use super::session::{create_session, delete_session, new_token, token_hash};
+use super::totp::check_second_factor;
+use crate::base::Response;
+use crate::config::{AuthConfig, Config};
+use crate::db::Database;
+use crate::error::AppError;
+use crate::middleware::auth::CurrentUser;
+use crate::openapi::tags;
+use crate::schema::{login_challenges, sessions, users};
+
+#[derive(Deserialize, ToSchema)]
+struct LoginRequest {
+    username: String,
+    password: String,
+}
+
+fn session_cookie(config: &AuthConfig, token: String) -> Cookie<'static> {
+    Cookie::build(config.cookie_name.clone(), token)
+        .path("/")
+        .http_only(true)
+        .same_site(SameSite::Strict)
+        .secure(config.cookie_secure)
+        .max_age(time::Duration::seconds(config.session_ttl_secs as i64))
+        .finish()
+}
+
+/// 第二步验证的有效期和最多尝试次数
+const CHALLENGE_TTL_SECS: i64 = 300;
+const MAX_CHALLENGE_ATTEMPTS: i32 = 5;
+
+/// 登录结果：未启用两步验证时直接返回用户，否则返回第二步使用的 challenge
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(untagged)]
+enum LoginResult {
+    User(User),
+    Challenge {
+        totp_required: bool,
+        challenge: String,
+    },
+}
+
+fn client_info(req: &HttpRequest) -> (Option<String>, Option<String>) {
+    let ip = req.peer_addr().map(|a| a.ip().to_string());
+    let user_agent = req
+        .headers()
+        .get("User-Agent")
+        .and_then(|v| v.to_str().ok())
+        .map(String::from);
+    (ip, user_agent)
+}
+
+/// 用户名或密码错误统一返回 401，不区分用户是否存在
+#[utoipa::path(
+    tag = tags::AUTH,
+    security(()),
+    responses((status = 200, body = Response<LoginResult>))
+)]
+#[post("/login")]
+async fn login(
+    req: HttpRequest,
+    body: web::Json<LoginRequest>,
+    config: web::Data<Config>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let body = body.into_inner();
+    let (ip, user_agent) = client_info(&req);
+    let ttl = config.auth.session_ttl_secs;
+    let (token, result) = web::block(move || -> Result<(Option<String>, LoginResult), AppError> {
+        let mut conn = db.connect()?;
+        let user: Option<User> = users::table
+            .filter(users::username.eq(&body.username))
+            .filter(users::disabled.eq(false))
+            .select(User::as_select())
+            .first(&mut conn)
+            .optional()?;
+        if !verify_password(
+            &body.password,
+            user.as_ref().map(|u| u.password_hash.as_str()),
+        ) {
+            return Err(AppError::Unauthorized);
+        }
+        let user = user.ok_or(AppError::Unauthorized)?;
+        if user.totp_enabled {
+            let (challenge, hash) = new_token();
+            let now = Utc::now().naive_utc();
+            diesel::delete(login_challenges::table.filter(login_challenges::expires_at.le(now)))
+                .execute(&mut conn)?;
+            diesel::insert_into(login_challenges::table)
+                .values(&LoginChallenge {
+                    token_hash: hash,
+                    user_id: user.id,
+                    created_at: now,
+                    expires_at: now + Duration::seconds(CHALLENGE_TTL_SECS),
+                    attempts: 0,
+                })
+                .execute(&mut conn)?;
+            return Ok((
+                None,
+                LoginResult::Challenge {
+                    totp_required: true,
+                    challenge,
+                },
+            ));
+        }
+        let (token, _) = create_session(&mut conn, user.id, ttl, ip, user_agent)?;
+        Ok((Some(token), LoginResult::User(user)))
+    })
+    .await??;
+    let mut response = HttpResponse::Ok();
+    if let Some(token) = token {
+        response.cookie(session_cookie(&config.auth, token));
+    }
+    Ok(response.json(Response::ok(result)))
+}
+
+#[derive(Deserialize, ToSchema)]
+struct TotpLoginRequest {
+    challenge: String,
+    /// TOTP 验证码或恢复码
+    code: String,
+}
+
+/// 登录第二步：校验验证码后创建会话；challenge 过期或错误次数过多时需重新输入密码
+#[utoipa::path(tag = tags::AUTH, security(()), responses((status = 200, body = Response<User>)))]
+#[post("/login/totp")]
+async fn login_totp(
+    req: HttpRequest,
+    body: web::Json<TotpLoginRequest>,
+    config: web::Data<Config>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let body = body.into_inner();
+    let (ip, user_agent) = client_info(&req);
+    let ttl = config.auth.session_ttl_secs;
+    let (token, user) = web::block(move || -> Result<(String, User), AppError> {
+        let mut conn = db.connect()?;
+        let hash = token_hash(&body.challenge);
+        let now = Utc::now().naive_utc();
+        let verified = conn.immediate_transaction(|conn| {
+            let found: Option<(LoginChallenge, User)> = login_challenges::table
+                .inner_join(users::table)
+                .filter(login_challenges::token_hash.eq(&hash))
+                .filter(login_challenges::expires_at.gt(now))
+                .filter(users::disabled.eq(false))
+                .select((LoginChallenge::as_select(), User::as_select()))
+                .first(conn)
+                .optional()?;
+            let Some((challenge, user)) = found else {
+                return Ok::<_, AppError>(None);
+            };
+            let target = login_challenges::table.filter(login_challenges::token_hash.eq(&hash));
+            if check_second_factor(conn, &user, &body.code, now)? {
+                diesel::delete(target).execute(conn)?;
+                return Ok(Some(user));
+            }
+            if challenge.attempts + 1 >= MAX_CHALLENGE_ATTEMPTS {
+                diesel::delete(target).execute(conn)?;
+            } else {
+                diesel::update(target)
+                    .set(login_challenges::attempts.eq(challenge.attempts + 1))
+                    .execute(conn)?;
+            }
+            Ok(None)
+        })?;
+        let user = verified.ok_or(AppError::Unauthorized)?;
+        let (token, _) = create_session(&mut conn, user.id, ttl, ip, user_agent)?;
+        Ok((token, user))
+    })
+    .await??;
+    Ok(HttpResponse::Ok()
+        .cookie(session_cookie(&config.auth, token))
+        .json(Response::ok(user)))
+}
+
+#[utoipa::path(tag = tags::AUTH, responses((status = 200, body = Response<utoipa::TupleUnit>)))]
+#[post("/logout")]
+async fn logout(
+    req: HttpRequest,
+    config: web::Data<Config>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    if let Some(cookie) = req.cookie(&config.auth.cookie_name) {
+        let token = cookie.value().to_string();
+        db.run(move |conn| delete_session(conn, &token)).await?;
+    }
+    let mut removal = session_cookie(&config.auth, String::new());
+    removal.make_removal();
+    Ok(HttpResponse::Ok().cookie(removal).json(Response::ok(())))
+}
+
+#[utoipa::path(tag = tags::AUTH, responses((status = 200, body = Response<User>)))]
+#[get("/me")]
+async fn me(user: CurrentUser, db: web::Data<Database>) -> Result<impl Responder, AppError> {
+    let user = web::block(move || -> Result<User, AppError> {
+        Ok(users::table
+            .find(user.id)
+            .select(User::as_select())
+            .first(&mut db.connect()?)?)
+    })
+    .await??;
+    Ok(Response::ok(user))
+}
+
+#[derive(Deserialize, ToSchema)]
+struct ChangePasswordRequest {
+    old_password: String,
+    new_password: String,
+}
+
+/// 修改密码后注销该用户的其它会话
+#[utoipa::path(tag = tags::AUTH, responses((status = 200, body = Response<utoipa::TupleUnit>)))]
+#[post("/password")]
+async fn change_password(
+    req: HttpRequest,
+    user: CurrentUser,
+    body: web::Json<ChangePasswordRequest>,
+    config: web::Data<Config>,
+    db: web::Data<Database>,
+) -> Result<impl Responder, AppError> {
+    user.require_session()?;
+    let body = body.into_inner();
+    check_password_policy("new_password", &body.new_password)?;
+    let current = req
+        .cookie(&config.auth.cookie_name)
+        .map(|c| token_hash(c.value()))
+        .unwrap_or_default();
+    web::block(move || -> Result<(), AppError> {
+        let mut conn = db.connect()?;
+        let hash: String = users::table
+            .find(user.id)
+            .select(users::password_hash)
+            .first(&mut conn)?;
+        if !verify_password(&body.old_password, Some(&hash)) {
+            return Err(AppError::InvalidParam("原密码错误".into()));
+        }
+        let new_hash = hash_password(&body.new_password)?;
+        conn.transaction(|conn| {
+            diesel::update(users::table.find(user.id))
+                .set((
+                    users::password_hash.eq(new_hash),
+                    users::updated_at.eq(Utc::now().naive_utc()),
+                ))
+                .execute(conn)?;
+            diesel::delete(
+                sessions::table
+                    .filter(sessions::user_id.eq(user.id))
+                    .filter(sessions::token_hash.ne(current)),
+            )
+            .execute(conn)?;
+            Ok(())
+        })
+    })
+    .await??;
+    Ok(Response::ok(()))
+}
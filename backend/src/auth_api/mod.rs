@@ -0,0 +1,110 @@
+mod api;
+pub mod model;
+pub mod password;
+pub mod role;
+pub mod session;
+pub mod token;
+pub mod totp;
+use chrono::Utc;
+use diesel::prelude::*;
+use std::fs;
+use std::io::Write;
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use utoipa_actix_web::service_config::ServiceConfig;
+
+use crate::ServerError;
+use crate::config::DatabaseConfig;
+use crate::db::Database;
+use crate::schema::users;
+use model::NewUser;
+
+pub fn handle(cfg: &mut ServiceConfig) {
+    cfg.service(api::login);
+    cfg.service(api::login_totp);
+    cfg.service(api::logout);
+    cfg.service(api::me);
+    cfg.service(api::change_password);
+    cfg.service(token::list_tokens);
+    cfg.service(token::create_token);
+    cfg.service(token::revoke_token);
+    cfg.service(totp::status);
+    cfg.service(totp::setup);
+    cfg.service(totp::enable);
+    cfg.service(totp::disable);
+    cfg.service(totp::regenerate_recovery_codes);
+}
+
+/// 服务启动时生成的初始密码写入数据库所在目录下的该文件
+const INITIAL_PASSWORD_FILE: &str = "initial-admin-password";
+
+/// 没有任何用户时创建 admin 账号，返回随机密码；密码不写入日志，由调用方交给管理员
+pub fn ensure_admin(db: &Database) -> Result<Option<String>, ServerError> {
+    let err = |e: crate::error::AppError| ServerError::Config(format!("create admin: {}", e));
+    let mut conn = db.connect().map_err(err)?;
+    let count: i64 = users::table
+        .count()
+        .get_result(&mut conn)
+        .map_err(|e| err(e.into()))?;
+    if count > 0 {
+        return Ok(None);
+    }
+    let password = password::random_password();
+    let hash = password::hash_password(&password).map_err(err)?;
+    let now = Utc::now().naive_utc();
+    diesel::insert_into(users::table)
+        .values(&NewUser {
+            username: "admin",
+            password_hash: &hash,
+            role: role::Role::Admin.as_str(),
+            created_at: now,
+            updated_at: now,
+        })
+        .execute(&mut conn)
+        .map_err(|e| err(e.into()))?;
+    Ok(Some(password))
+}
+
+/// 把初始密码写入只有服务用户可读的文件，返回文件路径
+pub fn write_initial_password(
+    config: &DatabaseConfig,
+    password: &str,
+) -> Result<PathBuf, ServerError> {
+    let dir = Path::new(&config.url)
+        .parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let path = dir.join(INITIAL_PASSWORD_FILE);
+    let err = |e: std::io::Error| ServerError::Config(format!("{}: {}", path.display(), e));
+    let mut file = fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(&path)
+        .map_err(err)?;
+    // 文件已存在时 mode 不生效，需要单独收紧权限
+    file.set_permissions(fs::Permissions::from_mode(0o600))
+        .map_err(err)?;
+    writeln!(file, "{}", password).map_err(err)?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::TestDb;
+
+    #[test]
+    fn test_initial_password() {
+        let test_db = TestDb::new();
+        let password = ensure_admin(&test_db.db).unwrap().unwrap();
+        assert!(ensure_admin(&test_db.db).unwrap().is_none());
+
+        let path = write_initial_password(&test_db.config, &password).unwrap();
+        assert_eq!(path.parent().unwrap(), test_db.dir);
+        assert_eq!(fs::read_to_string(&path).unwrap().trim(), password);
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+}
@@ -0,0 +1,99 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::schema::{api_tokens, login_challenges, root_grants, sessions, users};
+
+#[derive(Debug, Clone, Queryable, Selectable, Serialize, Deserialize, ToSchema)]
+#[diesel(table_name = users, check_for_backend(diesel::sqlite::Sqlite))]
+pub struct User {
+    pub id: i32,
+    pub username: String,
+    /// argon2 PHC 字符串，不返回给客户端
+    #[serde(skip)]
+    pub password_hash: String,
+    pub disabled: bool,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+    /// viewer / operator / admin
+    pub role: String,
+    /// base32 编码的 TOTP 密钥，启用前为待确认的密钥
+    #[serde(skip)]
+    pub totp_secret: Option<String>,
+    pub totp_enabled: bool,
+    /// 由管理员设置，为 true 时必须启用两步验证才能使用面板
+    pub totp_required: bool,
+    /// 最近一次通过验证的时间步，防止验证码被重放
+    #[serde(skip)]
+    pub totp_last_step: Option<i64>,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = users)]
+pub struct NewUser<'a> {
+    pub username: &'a str,
+    pub password_hash: &'a str,
+    pub role: &'a str,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+/// 登录会话，只保存令牌的 SHA-256
+#[derive(Debug, Clone, Queryable, Selectable, Insertable)]
+#[diesel(table_name = sessions, check_for_backend(diesel::sqlite::Sqlite))]
+pub struct Session {
+    pub token_hash: String,
+    pub user_id: i32,
+    pub created_at: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
+    pub last_seen_at: NaiveDateTime,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+/// 非管理员用户对存储根的授权
+#[derive(Debug, Clone, Queryable, Selectable, Insertable, Serialize, Deserialize, ToSchema)]
+#[diesel(table_name = root_grants, check_for_backend(diesel::sqlite::Sqlite))]
+pub struct RootGrant {
+    #[serde(skip)]
+    pub user_id: i32,
+    pub root: String,
+    /// read / write
+    pub access: String,
+}
+
+/// API 令牌，库中只保存令牌的 SHA-256；scopes 为逗号分隔的权限名
+#[derive(Debug, Clone, Queryable, Selectable)]
+#[diesel(table_name = api_tokens, check_for_backend(diesel::sqlite::Sqlite))]
+pub struct ApiToken {
+    pub id: i32,
+    pub name: String,
+    pub scopes: String,
+    pub created_at: NaiveDateTime,
+    pub expires_at: Option<NaiveDateTime>,
+    pub last_used_at: Option<NaiveDateTime>,
+    pub revoked_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = api_tokens)]
+pub struct NewApiToken<'a> {
+    pub user_id: i32,
+    pub name: &'a str,
+    pub token_hash: &'a str,
+    pub scopes: &'a str,
+    pub created_at: NaiveDateTime,
+    pub expires_at: Option<NaiveDateTime>,
+}
+
+/// 密码验证通过、等待输入两步验证码的登录请求
+#[derive(Debug, Clone, Queryable, Selectable, Insertable)]
+#[diesel(table_name = login_challenges, check_for_backend(diesel::sqlite::Sqlite))]
+pub struct LoginChallenge {
+    pub token_hash: String,
+    pub user_id: i32,
+    pub created_at: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
+    pub attempts: i32,
+}
@@ -0,0 +1,74 @@
+use argon2::Argon2;
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use rand::Rng;
+use rand::distributions::Alphanumeric;
+use std::sync::LazyLock;
+
+use crate::error::AppError;
+
+pub const MIN_PASSWORD_LEN: usize = 8;
+
+/// 用户不存在时也做一次校验，避免通过响应时间判断用户名是否存在
+static DUMMY_HASH: LazyLock<String> =
+    LazyLock::new(|| hash_password("rpanel-dummy-password").expect("hash dummy password"));
+
+pub fn hash_password(password: &str) -> Result<String, AppError> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|h| h.to_string())
+        .map_err(|e| AppError::Unknown(format!("密码哈希失败: {}", e)))
+}
+
+/// hash 为 None 表示用户不存在，此时总是返回 false
+pub fn verify_password(password: &str, hash: Option<&str>) -> bool {
+    let Ok(parsed) = PasswordHash::new(hash.unwrap_or(&DUMMY_HASH)) else {
+        return false;
+    };
+    Argon2::default()
+        .verify_password(password.as_bytes(), &parsed)
+        .is_ok()
+        && hash.is_some()
+}
+
+/// field 为请求中密码字段的名称，校验失败时用于指出是哪个字段
+pub fn check_password_policy(field: &str, password: &str) -> Result<(), AppError> {
+    if password.chars().count() < MIN_PASSWORD_LEN {
+        return Err(AppError::field(
+            field,
+            format!("密码至少 {} 个字符", MIN_PASSWORD_LEN),
+        ));
+    }
+    Ok(())
+}
+
+/// 生成 16 位字母数字随机密码，用于初始管理员和命令行创建的用户
+pub fn random_password() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(16)
+        .map(char::from)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash_verify() {
+        let hash = hash_password("correct horse").unwrap();
+        assert!(hash.starts_with("$argon2id$"));
+        assert!(verify_password("correct horse", Some(&hash)));
+        assert!(!verify_password("wrong horse", Some(&hash)));
+        assert!(!verify_password("rpanel-dummy-password", None));
+        assert!(!verify_password("x", Some("not a hash")));
+    }
+
+    #[test]
+    fn test_policy() {
+        assert!(check_password_policy("password", "1234567").is_err());
+        assert!(check_password_policy("password", "12345678").is_ok());
+    }
+}
@@ -0,0 +1,171 @@
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use utoipa::ToSchema;
+
+use crate::error::AppError;
+use crate::storage::Operation;
+
+/// 用户角色，权限由角色决定
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// 只读：查看系统信息、浏览已授权的文件和图片
+    Viewer,
+    /// 在 viewer 基础上可以修改文件、上传删除图片、管理分享链接
+    Operator,
+    /// 全部权限，不受存储根授权限制
+    Admin,
+}
+
+impl Role {
+    pub const ALL: [Role; 3] = [Role::Viewer, Role::Operator, Role::Admin];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Role::Viewer => "viewer",
+            Role::Operator => "operator",
+            Role::Admin => "admin",
+        }
+    }
+
+    pub fn permissions(self) -> &'static [Permission] {
+        use Permission::*;
+        match self {
+            Role::Viewer => &[SystemRead, FilesRead, ImagesRead],
+            Role::Operator => &[
+                SystemRead,
+                FilesRead,
+                FilesWrite,
+                ImagesRead,
+                ImagesWrite,
+                SharesManage,
+            ],
+            Role::Admin => &Permission::ALL,
+        }
+    }
+
+    pub fn has(self, permission: Permission) -> bool {
+        self.permissions().contains(&permission)
+    }
+}
+
+impl FromStr for Role {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Role::ALL
+            .into_iter()
+            .find(|r| r.as_str() == s)
+            .ok_or_else(|| AppError::InvalidParam(format!("未知角色: {}", s)))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Permission {
+    SystemRead,
+    FilesRead,
+    FilesWrite,
+    ImagesRead,
+    ImagesWrite,
+    SharesManage,
+    UsersManage,
+    AuditRead,
+}
+
+impl Permission {
+    pub const ALL: [Permission; 8] = [
+        Permission::SystemRead,
+        Permission::FilesRead,
+        Permission::FilesWrite,
+        Permission::ImagesRead,
+        Permission::ImagesWrite,
+        Permission::SharesManage,
+        Permission::UsersManage,
+        Permission::AuditRead,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Permission::SystemRead => "system_read",
+            Permission::FilesRead => "files_read",
+            Permission::FilesWrite => "files_write",
+            Permission::ImagesRead => "images_read",
+            Permission::ImagesWrite => "images_write",
+            Permission::SharesManage => "shares_manage",
+            Permission::UsersManage => "users_manage",
+            Permission::AuditRead => "audit_read",
+        }
+    }
+}
+
+impl FromStr for Permission {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Permission::ALL
+            .into_iter()
+            .find(|p| p.as_str() == s)
+            .ok_or_else(|| AppError::InvalidParam(format!("未知权限: {}", s)))
+    }
+}
+
+/// 非管理员对某个存储根的授权
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Access {
+    /// 只允许读取和搜索
+    Read,
+    /// 允许存储根配置中开放的全部操作
+    Write,
+}
+
+impl Access {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Access::Read => "read",
+            Access::Write => "write",
+        }
+    }
+
+    pub fn allows(self, op: Operation) -> bool {
+        self == Access::Write || op.is_read()
+    }
+}
+
+impl FromStr for Access {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "read" => Ok(Access::Read),
+            "write" => Ok(Access::Write),
+            _ => Err(AppError::InvalidParam(format!("未知授权: {}", s))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_role_permissions() {
+        assert!(Role::Admin.has(Permission::UsersManage));
+        assert!(!Role::Operator.has(Permission::UsersManage));
+        assert!(Role::Operator.has(Permission::FilesWrite));
+        assert!(!Role::Viewer.has(Permission::FilesWrite));
+        assert_eq!("operator".parse::<Role>().unwrap(), Role::Operator);
+        assert!("root".parse::<Role>().is_err());
+        for p in Permission::ALL {
+            assert_eq!(p.as_str().parse::<Permission>().unwrap(), p);
+        }
+    }
+
+    #[test]
+    fn test_access() {
+        assert!(Access::Read.allows(Operation::Search));
+        assert!(!Access::Read.allows(Operation::Delete));
+        assert!(Access::Write.allows(Operation::Delete));
+    }
+}
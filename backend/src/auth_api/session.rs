@@ -0,0 +1,82 @@
+use chrono::{Duration, NaiveDateTime, Utc};
+use diesel::prelude::*;
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+
+use super::model::{Session, User};
+use crate::error::AppError;
+use crate::schema::{sessions, users};
+
+/// last_seen_at 的最小更新间隔，避免每个请求都写库
+pub(super) const TOUCH_INTERVAL_SECS: i64 = 60;
+
+/// 生成随机令牌，返回 (令牌, 令牌哈希)
+pub fn new_token() -> (String, String) {
+    let mut bytes = [0u8; 32];
+    rand::rngs::OsRng.fill_bytes(&mut bytes);
+    let token = hex::encode(bytes);
+    let hash = token_hash(&token);
+    (token, hash)
+}
+
+pub fn token_hash(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+pub fn create_session(
+    conn: &mut SqliteConnection,
+    user_id: i32,
+    ttl_secs: u64,
+    ip: Option<String>,
+    user_agent: Option<String>,
+) -> Result<(String, Session), AppError> {
+    let (token, token_hash) = new_token();
+    let now = Utc::now().naive_utc();
+    let session = Session {
+        token_hash,
+        user_id,
+        created_at: now,
+        expires_at: now + Duration::seconds(ttl_secs as i64),
+        last_seen_at: now,
+        ip,
+        user_agent,
+    };
+    // 顺带清理过期会话
+    diesel::delete(sessions::table.filter(sessions::expires_at.le(now))).execute(conn)?;
+    diesel::insert_into(sessions::table)
+        .values(&session)
+        .execute(conn)?;
+    Ok((token, session))
+}
+
+/// 按令牌查找未过期会话对应的用户，已禁用的用户视为未登录
+pub fn session_user(
+    conn: &mut SqliteConnection,
+    token: &str,
+    now: NaiveDateTime,
+) -> Result<Option<User>, AppError> {
+    let hash = token_hash(token);
+    let found: Option<(Session, User)> = sessions::table
+        .inner_join(users::table)
+        .filter(sessions::token_hash.eq(&hash))
+        .filter(sessions::expires_at.gt(now))
+        .filter(users::disabled.eq(false))
+        .select((Session::as_select(), User::as_select()))
+        .first(conn)
+        .optional()?;
+    let Some((session, user)) = found else {
+        return Ok(None);
+    };
+    if (now - session.last_seen_at).num_seconds() >= TOUCH_INTERVAL_SECS {
+        diesel::update(sessions::table.filter(sessions::token_hash.eq(&hash)))
+            .set(sessions::last_seen_at.eq(now))
+            .execute(conn)?;
+    }
+    Ok(Some(user))
+}
+
+pub fn delete_session(conn: &mut SqliteConnection, token: &str) -> Result<(), AppError> {
+    diesel::delete(sessions::table.filter(sessions::token_hash.eq(token_hash(token))))
+        .execute(conn)?;
+    Ok(())
+}
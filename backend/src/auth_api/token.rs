@@ -0,0 +1,270 @@
+use actix_web::{Responder, delete, get, post, web};
+use chrono::{Duration, NaiveDateTime, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::model::{ApiToken, NewApiToken, User};
+use super::role::Permission;
+use super::session::{TOUCH_INTERVAL_SECS, new_token, token_hash};
+use crate::base::Response;
+use crate::db::Database;
+use crate::error::{AppError, Validator};
+use crate::middleware::auth::CurrentUser;
+use crate::openapi::tags;
+use crate::schema::{api_tokens, users};
+
+/// 令牌前缀，便于在日志和代码仓库中识别泄露的令牌
+const TOKEN_PREFIX: &str = "rpt_";
+const MAX_NAME_LEN: usize = 64;
+
+/// 返回给客户端的令牌信息，不含令牌本身
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+struct TokenInfo {
+    id: i32,
+    name: String,
+    scopes: Vec<Permission>,
+    created_at: NaiveDateTime,
+    expires_at: Option<NaiveDateTime>,
+    last_used_at: Option<NaiveDateTime>,
+    revoked_at: Option<NaiveDateTime>,
+}
+
+/// 创建结果，token 只在创建时返回一次
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+struct CreatedToken {
+    #[serde(flatten)]
+    info: TokenInfo,
+    token: String,
+}
+
+#[derive(Deserialize, ToSchema)]
+#[schema(as = CreateTokenRequest)]
+struct CreateRequest {
+    name: String,
+    scopes: Vec<Permission>,
+    /// 有效期（秒），不填表示永不过期
+    expires_in: Option<u64>,
+}
+
+fn parse_scopes(scopes: &str) -> Vec<Permission> {
+    scopes.split(',').filter_map(|s| s.parse().ok()).collect()
+}
+
+fn join_scopes(scopes: &[Permission]) -> String {
+    scopes
+        .iter()
+        .map(|p| p.as_str())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+impl From<ApiToken> for TokenInfo {
+    fn from(t: ApiToken) -> Self {
+        TokenInfo {
+            id: t.id,
+            scopes: parse_scopes(&t.scopes),
+            name: t.name,
+            created_at: t.created_at,
+            expires_at: t.expires_at,
+            last_used_at: t.last_used_at,
+            revoked_at: t.revoked_at,
+        }
+    }
+}
+
+/// 按令牌查找用户及令牌的权限范围；令牌已撤销、过期或用户被禁用时返回 None
+pub fn token_user(
+    conn: &mut SqliteConnection,
+    token: &str,
+    now: NaiveDateTime,
+) -> Result<Option<(User, Vec<Permission>)>, AppError> {
+    if !token.starts_with(TOKEN_PREFIX) {
+        return Ok(None);
+    }
+    let hash = token_hash(token);
+    let found: Option<(ApiToken, User)> = api_tokens::table
+        .inner_join(users::table)
+        .filter(api_tokens::token_hash.eq(&hash))
+        .filter(api_tokens::revoked_at.is_null())
+        .filter(
+            api_tokens::expires_at
+                .is_null()
+                .or(api_tokens::expires_at.gt(now)),
+        )
+        .filter(users::disabled.eq(false))
+        .select((ApiToken::as_select(), User::as_select()))
+        .first(conn)
+        .optional()?;
+    let Some((api_token, user)) = found else {
+        return Ok(None);
+    };
+    if api_token
+        .last_used_at
+        .is_none_or(|t| (now - t).num_seconds() >= TOUCH_INTERVAL_SECS)
+    {
+        diesel::update(api_tokens::table.find(api_token.id))
+            .set(api_tokens::last_used_at.eq(now))
+            .execute(conn)?;
+    }
+    Ok(Some((user, parse_scopes(&api_token.scopes))))
+}
+
+#[utoipa::path(tag = tags::AUTH, responses((status = 200, body = Response<Vec<TokenInfo>>)))]
+#[get("/tokens")]
+async fn list_tokens(
+    user: CurrentUser,
+    db: web::Data<Database>,
+) -> Result<impl Responder, AppError> {
+    let tokens = web::block(move || -> Result<Vec<TokenInfo>, AppError> {
+        Ok(api_tokens::table
+            .filter(api_tokens::user_id.eq(user.id))
+            .order(api_tokens::created_at.desc())
+            .select(ApiToken::as_select())
+            .load(&mut db.connect()?)?
+            .into_iter()
+            .map(TokenInfo::from)
+            .collect())
+    })
+    .await??;
+    Ok(Response::ok(tokens))
+}
+
+/// 只能用登录会话创建令牌，scopes 不能超出当前角色的权限
+#[utoipa::path(tag = tags::AUTH, responses((status = 200, body = Response<CreatedToken>)))]
+#[post("/tokens")]
+async fn create_token(
+    user: CurrentUser,
+    req: web::Json<CreateRequest>,
+    db: web::Data<Database>,
+) -> Result<impl Responder, AppError> {
+    user.require_session()?;
+    let req = req.into_inner();
+    let name = req.name.trim().to_string();
+    Validator::new()
+        .check(
+            !name.is_empty()
+                && name.chars().count() <= MAX_NAME_LEN
+                && !name.chars().any(char::is_control),
+            "name",
+            format!("不能为空且不超过 {} 个字符", MAX_NAME_LEN),
+        )
+        .check(!req.scopes.is_empty(), "scopes", "不能为空")
+        .check(req.expires_in != Some(0), "expires_in", "必须大于 0")
+        .finish()?;
+    for scope in &req.scopes {
+        user.require(*scope)?;
+    }
+    let now = Utc::now().naive_utc();
+    let expires_at = match req.expires_in {
+        Some(secs) => Some(
+            i64::try_from(secs)
+                .ok()
+                .and_then(Duration::try_seconds)
+                .and_then(|d| now.checked_add_signed(d))
+                .ok_or_else(|| AppError::field("expires_in", "超出范围"))?,
+        ),
+        None => None,
+    };
+    let (raw, _) = new_token();
+    let token = format!("{}{}", TOKEN_PREFIX, raw);
+    let hash = token_hash(&token);
+    let scopes = join_scopes(&req.scopes);
+    let created = web::block(move || -> Result<ApiToken, AppError> {
+        let mut conn = db.connect()?;
+        diesel::insert_into(api_tokens::table)
+            .values(&NewApiToken {
+                user_id: user.id,
+                name: &name,
+                token_hash: &hash,
+                scopes: &scopes,
+                created_at: now,
+                expires_at,
+            })
+            .execute(&mut conn)?;
+        Ok(api_tokens::table
+            .filter(api_tokens::token_hash.eq(&hash))
+            .select(ApiToken::as_select())
+            .first(&mut conn)?)
+    })
+    .await??;
+    Ok(Response::ok(CreatedToken {
+        info: created.into(),
+        token,
+    }))
+}
+
+/// 撤销令牌，记录保留以便查看最后使用时间
+#[utoipa::path(tag = tags::AUTH, responses((status = 200, body = Response<TokenInfo>)))]
+#[delete("/tokens/{id}")]
+async fn revoke_token(
+    user: CurrentUser,
+    id: web::Path<i32>,
+    db: web::Data<Database>,
+) -> Result<impl Responder, AppError> {
+    let id = id.into_inner();
+    let token = web::block(move || -> Result<TokenInfo, AppError> {
+        let mut conn = db.connect()?;
+        let target = api_tokens::table
+            .filter(api_tokens::id.eq(id))
+            .filter(api_tokens::user_id.eq(user.id));
+        diesel::update(target.filter(api_tokens::revoked_at.is_null()))
+            .set(api_tokens::revoked_at.eq(Utc::now().naive_utc()))
+            .execute(&mut conn)?;
+        Ok(target
+            .select(ApiToken::as_select())
+            .first(&mut conn)
+            .optional()?
+            .ok_or_else(|| AppError::NotFound(format!("令牌不存在: {}", id)))?
+            .into())
+    })
+    .await??;
+    Ok(Response::ok(token))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::TestDb;
+
+    #[test]
+    fn test_token_user() {
+        let test_db = TestDb::new();
+        test_db.create_user("ci", "operator");
+        let mut conn = test_db.conn();
+        let now = Utc::now().naive_utc();
+        let token = format!("{}abc", TOKEN_PREFIX);
+        diesel::insert_into(api_tokens::table)
+            .values(&NewApiToken {
+                user_id: 1,
+                name: "deploy",
+                token_hash: &token_hash(&token),
+                scopes: &join_scopes(&[Permission::ImagesRead, Permission::ImagesWrite]),
+                created_at: now,
+                expires_at: Some(now + Duration::hours(1)),
+            })
+            .execute(&mut conn)
+            .unwrap();
+
+        let (user, scopes) = token_user(&mut conn, &token, now).unwrap().unwrap();
+        assert_eq!(user.username, "ci");
+        assert_eq!(
+            scopes,
+            vec![Permission::ImagesRead, Permission::ImagesWrite]
+        );
+        let last_used: Option<NaiveDateTime> = api_tokens::table
+            .select(api_tokens::last_used_at)
+            .first(&mut conn)
+            .unwrap();
+        assert_eq!(last_used, Some(now));
+
+        assert!(token_user(&mut conn, "rpt_other", now).unwrap().is_none());
+        let later = now + Duration::hours(2);
+        assert!(token_user(&mut conn, &token, later).unwrap().is_none());
+        diesel::update(api_tokens::table)
+            .set(api_tokens::revoked_at.eq(now))
+            .execute(&mut conn)
+            .unwrap();
+        assert!(token_user(&mut conn, &token, now).unwrap().is_none());
+    }
+}
@@ -0,0 +1,389 @@
+use actix_web::{Responder, get, post, web};
+use chrono::{NaiveDateTime, Utc};
+use diesel::prelude::*;
+use rand::{Rng, RngCore};
+use serde::{Deserialize, Serialize};
+use totp_rs::{Algorithm, Secret, TOTP};
+use utoipa::ToSchema;
+
+use super::model::User;
+use super::password::verify_password;
+use super::session::token_hash;
+use crate::base::Response;
+use crate::config::Config;
+use crate::db::Database;
+use crate::error::AppError;
+use crate::middleware::auth::CurrentUser;
+use crate::openapi::tags;
+use crate::schema::{recovery_codes, users};
+
+/// 时间步长（秒）和验证码位数，与常见的验证器 App 一致
+const STEP: u64 = 30;
+const DIGITS: usize = 6;
+/// 允许前后各一个时间步的时钟偏差
+const SKEW: i64 = 1;
+const RECOVERY_CODE_COUNT: usize = 10;
+/// 恢复码字符集，去掉了容易混淆的 0/o、1/l/i
+const RECOVERY_CHARSET: &[u8] = b"abcdefghjkmnpqrstuvwxyz23456789";
+
+/// 生成 160 位随机密钥，返回 base32 编码
+pub fn new_secret() -> String {
+    let mut bytes = [0u8; 20];
+    rand::rngs::OsRng.fill_bytes(&mut bytes);
+    Secret::Raw(bytes.to_vec()).to_encoded().to_string()
+}
+
+fn build_totp(secret: &str, issuer: &str, account: &str) -> Result<TOTP, AppError> {
+    let bytes = Secret::Encoded(secret.to_string())
+        .to_bytes()
+        .map_err(|e| AppError::Unknown(format!("TOTP 密钥无效: {:?}", e)))?;
+    TOTP::new(
+        Algorithm::SHA1,
+        DIGITS,
+        SKEW as u8,
+        STEP,
+        bytes,
+        Some(issuer.to_string()),
+        account.to_string(),
+    )
+    .map_err(|e| AppError::Unknown(format!("TOTP 参数无效: {}", e)))
+}
+
+/// 校验验证码，返回匹配的时间步；last_step 及之前的时间步不再接受，防止同一验证码被重放
+pub fn verify_code(secret: &str, code: &str, now: u64, last_step: Option<i64>) -> Option<i64> {
+    let code = code.trim();
+    if code.len() != DIGITS || !code.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let totp = build_totp(secret, "", "").ok()?;
+    let current = (now / STEP) as i64;
+    (current - SKEW..=current + SKEW)
+        .filter(|step| *step >= 0 && last_step.is_none_or(|last| *step > last))
+        .find(|step| {
+            let expected = totp.generate(*step as u64 * STEP);
+            constant_time_eq::constant_time_eq(expected.as_bytes(), code.as_bytes())
+        })
+}
+
+/// 恢复码不区分大小写，忽略空白和分隔符
+fn normalize_recovery_code(code: &str) -> String {
+    code.chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+/// 重新生成恢复码，原有的恢复码全部作废；返回明文，只展示一次
+pub fn new_recovery_codes(
+    conn: &mut SqliteConnection,
+    user_id: i32,
+) -> Result<Vec<String>, AppError> {
+    let mut rng = rand::rngs::OsRng;
+    let codes: Vec<String> = (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            let chars: String = (0..10)
+                .map(|_| RECOVERY_CHARSET[rng.gen_range(0..RECOVERY_CHARSET.len())] as char)
+                .collect();
+            format!("{}-{}", &chars[..5], &chars[5..])
+        })
+        .collect();
+    diesel::delete(recovery_codes::table.filter(recovery_codes::user_id.eq(user_id)))
+        .execute(conn)?;
+    let rows: Vec<_> = codes
+        .iter()
+        .map(|code| {
+            (
+                recovery_codes::user_id.eq(user_id),
+                recovery_codes::code_hash.eq(token_hash(&normalize_recovery_code(code))),
+            )
+        })
+        .collect();
+    diesel::insert_into(recovery_codes::table)
+        .values(&rows)
+        .execute(conn)?;
+    Ok(codes)
+}
+
+/// 校验第二步验证：6 位数字按 TOTP 校验，其它按恢复码校验；恢复码只能使用一次
+pub fn check_second_factor(
+    conn: &mut SqliteConnection,
+    user: &User,
+    code: &str,
+    now: NaiveDateTime,
+) -> Result<bool, AppError> {
+    let Some(secret) = user.totp_secret.as_deref().filter(|_| user.totp_enabled) else {
+        return Ok(false);
+    };
+    let timestamp = now.and_utc().timestamp().max(0) as u64;
+    if let Some(step) = verify_code(secret, code, timestamp, user.totp_last_step) {
+        diesel::update(users::table.find(user.id))
+            .set(users::totp_last_step.eq(step))
+            .execute(conn)?;
+        return Ok(true);
+    }
+    let hash = token_hash(&normalize_recovery_code(code));
+    let used = diesel::update(
+        recovery_codes::table
+            .filter(recovery_codes::user_id.eq(user.id))
+            .filter(recovery_codes::code_hash.eq(hash))
+            .filter(recovery_codes::used_at.is_null()),
+    )
+    .set(recovery_codes::used_at.eq(now))
+    .execute(conn)?;
+    Ok(used > 0)
+}
+
+fn find_user(conn: &mut SqliteConnection, id: i32) -> Result<User, AppError> {
+    Ok(users::table
+        .find(id)
+        .select(User::as_select())
+        .first(conn)?)
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+struct TotpStatus {
+    enabled: bool,
+    required: bool,
+    recovery_codes_left: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+struct TotpSetup {
+    /// base32 编码的密钥，供无法扫码时手动输入
+    secret: String,
+    /// 生成二维码用的 otpauth URI
+    otpauth_url: String,
+}
+
+#[derive(Deserialize, ToSchema)]
+struct CodeRequest {
+    code: String,
+}
+
+#[derive(Deserialize, ToSchema)]
+struct DisableRequest {
+    password: String,
+    code: String,
+}
+
+#[utoipa::path(tag = tags::AUTH, responses((status = 200, body = Response<TotpStatus>)))]
+#[get("/totp")]
+async fn status(user: CurrentUser, db: web::Data<Database>) -> Result<impl Responder, AppError> {
+    let status = web::block(move || -> Result<TotpStatus, AppError> {
+        let mut conn = db.connect()?;
+        let found = find_user(&mut conn, user.id)?;
+        let recovery_codes_left = recovery_codes::table
+            .filter(recovery_codes::user_id.eq(user.id))
+            .filter(recovery_codes::used_at.is_null())
+            .count()
+            .get_result(&mut conn)?;
+        Ok(TotpStatus {
+            enabled: found.totp_enabled,
+            required: found.totp_required,
+            recovery_codes_left,
+        })
+    })
+    .await??;
+    Ok(Response::ok(status))
+}
+
+/// 生成待确认的密钥，调用 /totp/enable 校验验证码后才会生效
+#[utoipa::path(tag = tags::AUTH, responses((status = 200, body = Response<TotpSetup>)))]
+#[post("/totp/setup")]
+async fn setup(
+    user: CurrentUser,
+    config: web::Data<Config>,
+    db: web::Data<Database>,
+) -> Result<impl Responder, AppError> {
+    user.require_session()?;
+    let secret = new_secret();
+    let otpauth_url = build_totp(&secret, &config.auth.totp_issuer, &user.username)?.get_url();
+    let saved = secret.clone();
+    web::block(move || -> Result<(), AppError> {
+        let mut conn = db.connect()?;
+        let updated = diesel::update(users::table.find(user.id))
+            .filter(users::totp_enabled.eq(false))
+            .set(users::totp_secret.eq(saved))
+            .execute(&mut conn)?;
+        if updated == 0 {
+            return Err(AppError::Conflict("已启用两步验证".into()));
+        }
+        Ok(())
+    })
+    .await??;
+    Ok(Response::ok(TotpSetup {
+        secret,
+        otpauth_url,
+    }))
+}
+
+/// 校验待确认密钥生成的验证码后启用两步验证，返回恢复码
+#[utoipa::path(tag = tags::AUTH, responses((status = 200, body = Response<Vec<String>>)))]
+#[post("/totp/enable")]
+async fn enable(
+    user: CurrentUser,
+    req: web::Json<CodeRequest>,
+    db: web::Data<Database>,
+) -> Result<impl Responder, AppError> {
+    user.require_session()?;
+    let codes = web::block(move || -> Result<Vec<String>, AppError> {
+        let mut conn = db.connect()?;
+        conn.transaction(|conn| {
+            let found = find_user(conn, user.id)?;
+            if found.totp_enabled {
+                return Err(AppError::Conflict("已启用两步验证".into()));
+            }
+            let secret = found
+                .totp_secret
+                .ok_or_else(|| AppError::InvalidParam("请先调用 /totp/setup 生成密钥".into()))?;
+            let now = Utc::now().timestamp().max(0) as u64;
+            let step = verify_code(&secret, &req.code, now, None)
+                .ok_or_else(|| AppError::InvalidParam("验证码错误".into()))?;
+            diesel::update(users::table.find(user.id))
+                .set((
+                    users::totp_enabled.eq(true),
+                    users::totp_last_step.eq(step),
+                    users::updated_at.eq(Utc::now().naive_utc()),
+                ))
+                .execute(conn)?;
+            new_recovery_codes(conn, user.id)
+        })
+    })
+    .await??;
+    Ok(Response::ok(codes))
+}
+
+/// 关闭两步验证需要密码和验证码；被管理员要求启用时不能关闭
+#[utoipa::path(tag = tags::AUTH, responses((status = 200, body = Response<utoipa::TupleUnit>)))]
+#[post("/totp/disable")]
+async fn disable(
+    user: CurrentUser,
+    req: web::Json<DisableRequest>,
+    db: web::Data<Database>,
+) -> Result<impl Responder, AppError> {
+    user.require_session()?;
+    web::block(move || -> Result<(), AppError> {
+        let mut conn = db.connect()?;
+        conn.transaction(|conn| {
+            let found = find_user(conn, user.id)?;
+            if !found.totp_enabled {
+                return Err(AppError::InvalidParam("未启用两步验证".into()));
+            }
+            if found.totp_required {
+                return Err(AppError::Forbidden("管理员要求启用两步验证".into()));
+            }
+            if !verify_password(&req.password, Some(&found.password_hash))
+                || !check_second_factor(conn, &found, &req.code, Utc::now().naive_utc())?
+            {
+                return Err(AppError::InvalidParam("密码或验证码错误".into()));
+            }
+            reset(conn, user.id)
+        })
+    })
+    .await??;
+    Ok(Response::ok(()))
+}
+
+/// 校验验证码后重新生成恢复码
+#[utoipa::path(tag = tags::AUTH, responses((status = 200, body = Response<Vec<String>>)))]
+#[post("/totp/recovery_codes")]
+async fn regenerate_recovery_codes(
+    user: CurrentUser,
+    req: web::Json<CodeRequest>,
+    db: web::Data<Database>,
+) -> Result<impl Responder, AppError> {
+    user.require_session()?;
+    let codes = web::block(move || -> Result<Vec<String>, AppError> {
+        let mut conn = db.connect()?;
+        conn.transaction(|conn| {
+            let found = find_user(conn, user.id)?;
+            if !check_second_factor(conn, &found, &req.code, Utc::now().naive_utc())? {
+                return Err(AppError::InvalidParam("验证码错误".into()));
+            }
+            new_recovery_codes(conn, user.id)
+        })
+    })
+    .await??;
+    Ok(Response::ok(codes))
+}
+
+/// 清除用户的两步验证密钥和恢复码
+pub fn reset(conn: &mut SqliteConnection, user_id: i32) -> Result<(), AppError> {
+    diesel::update(users::table.find(user_id))
+        .set((
+            users::totp_secret.eq(None::<String>),
+            users::totp_enabled.eq(false),
+            users::totp_last_step.eq(None::<i64>),
+            users::updated_at.eq(Utc::now().naive_utc()),
+        ))
+        .execute(conn)?;
+    diesel::delete(recovery_codes::table.filter(recovery_codes::user_id.eq(user_id)))
+        .execute(conn)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::TestDb;
+    use chrono::DateTime;
+
+    /// RFC 6238 附录 B 中 SHA1 的测试密钥 "12345678901234567890"
+    const RFC_SECRET: &str = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+
+    #[test]
+    fn test_verify_code() {
+        // RFC 6238: T = 59 时 8 位验证码为 94287082，取后 6 位
+        assert_eq!(verify_code(RFC_SECRET, "287082", 59, None), Some(1));
+        // 允许一个时间步的偏差
+        assert_eq!(verify_code(RFC_SECRET, "287082", 89, None), Some(1));
+        assert_eq!(verify_code(RFC_SECRET, "287082", 150, None), None);
+        // 已使用过的时间步不能再次通过
+        assert_eq!(verify_code(RFC_SECRET, "287082", 59, Some(1)), None);
+        assert_eq!(verify_code(RFC_SECRET, "28708", 59, None), None);
+        assert_eq!(verify_code(RFC_SECRET, "abcdef", 59, None), None);
+    }
+
+    #[test]
+    fn test_otpauth_url() {
+        let url = build_totp(&new_secret(), "rpanel", "alice")
+            .unwrap()
+            .get_url();
+        assert!(url.starts_with("otpauth://totp/rpanel:alice?secret="));
+        assert!(url.contains("issuer=rpanel"));
+    }
+
+    #[test]
+    fn test_check_second_factor() {
+        let test_db = TestDb::new();
+        test_db.create_user("alice", "viewer");
+        let mut conn = test_db.conn();
+        let now = DateTime::from_timestamp(59, 0).unwrap().naive_utc();
+        diesel::update(users::table.find(1))
+            .set((
+                users::totp_secret.eq(RFC_SECRET),
+                users::totp_enabled.eq(true),
+            ))
+            .execute(&mut conn)
+            .unwrap();
+        let codes = new_recovery_codes(&mut conn, 1).unwrap();
+        assert_eq!(codes.len(), RECOVERY_CODE_COUNT);
+
+        let user = find_user(&mut conn, 1).unwrap();
+        assert!(check_second_factor(&mut conn, &user, "287082", now).unwrap());
+        // 同一验证码不能重放
+        let user = find_user(&mut conn, 1).unwrap();
+        assert_eq!(user.totp_last_step, Some(1));
+        assert!(!check_second_factor(&mut conn, &user, "287082", now).unwrap());
+
+        let code = codes[0].to_uppercase().replace('-', " ");
+        assert!(check_second_factor(&mut conn, &user, &code, now).unwrap());
+        assert!(!check_second_factor(&mut conn, &user, &codes[0], now).unwrap());
+        assert!(!check_second_factor(&mut conn, &user, "wrong-code", now).unwrap());
+
+        reset(&mut conn, 1).unwrap();
+        let user = find_user(&mut conn, 1).unwrap();
+        assert!(!user.totp_enabled);
+        assert!(!check_second_factor(&mut conn, &user, &codes[1], now).unwrap());
+    }
+}
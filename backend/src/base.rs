@@ -1,39 +1,109 @@
 use actix_web::body::BoxBody;
-use actix_web::http::StatusCode;
+use actix_web::error::{JsonPayloadError, QueryPayloadError};
+use actix_web::web::{JsonConfig, PathConfig, QueryConfig};
 use actix_web::{HttpRequest, HttpResponse, Responder};
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
-#[derive(Serialize, Deserialize, Debug)]
+use crate::error::{AppError, FieldError, code};
+use crate::middleware::request_id;
+
+/// 所有 JSON 接口统一的响应格式
+///
+/// - 成功：HTTP 200，code 为 0，data 为结果，没有结果时为 null
+/// - 失败：HTTP 状态码表示错误类别，code 为 [`crate::error::code`] 中的应用错误码，
+///   msg 为错误说明，data 为 null；参数校验失败时 errors 逐个列出有问题的字段
+///
+/// request_id 与响应头 X-Request-Id 相同，用于在日志中查找对应的请求
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
 #[serde(bound = "T: Serialize + DeserializeOwned")]
 pub struct Response<T: Serialize + DeserializeOwned> {
-    pub data: Option<T>,
-    pub msg: String,
     pub code: u16,
+    pub msg: String,
+    pub data: Option<T>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<FieldError>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
 }
 
 impl<T: Serialize + DeserializeOwned> Response<T> {
-    pub fn new(data: Option<T>, msg: String, code: u16) -> Self {
+    pub fn ok(data: T) -> Self {
         Response {
-            data: data,
-            msg,
-            code,
+            code: code::SUCCESS,
+            msg: "Success".into(),
+            data: Some(data),
+            errors: Vec::new(),
+            request_id: request_id::current(),
         }
     }
 }
 
+/// 分页列表，page 从 1 开始
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+#[serde(bound = "T: Serialize + DeserializeOwned")]
+pub struct Page<T: Serialize + DeserializeOwned> {
+    pub items: Vec<T>,
+    pub total: i64,
+    pub page: i64,
+    pub page_size: i64,
+}
+
 impl<T: Serialize + DeserializeOwned> Responder for Response<T> {
     type Body = BoxBody;
     fn respond_to(self, _: &HttpRequest) -> HttpResponse {
-        let status = match self.code {
-            0 => StatusCode::OK,
-            404 => StatusCode::NOT_FOUND,
-            _ => StatusCode::INTERNAL_SERVER_ERROR,
-        };
-        HttpResponse::build(status).json(self)
+        HttpResponse::Ok().json(self)
     }
 }
 
+/// serde 报告缺少或多余的字段时转为字段错误，其他反序列化错误保留原始信息
+fn deserialize_error(msg: String) -> AppError {
+    for (prefix, reason) in [
+        ("missing field `", "缺少该字段"),
+        ("unknown field `", "未知字段"),
+    ] {
+        if let Some(field) = msg
+            .split_once(prefix)
+            .and_then(|(_, rest)| rest.split_once('`'))
+            .map(|(field, _)| field)
+        {
+            return AppError::field(field, reason);
+        }
+    }
+    AppError::InvalidParam(msg)
+}
+
+fn json_error(err: JsonPayloadError) -> AppError {
+    match err {
+        JsonPayloadError::Overflow { limit }
+        | JsonPayloadError::OverflowKnownLength { limit, .. } => {
+            AppError::PayloadTooLarge(format!("请求体不能超过 {} 字节", limit))
+        }
+        JsonPayloadError::ContentType => {
+            AppError::InvalidParam("Content-Type 必须为 application/json".into())
+        }
+        JsonPayloadError::Deserialize(e) => deserialize_error(e.to_string()),
+        e => AppError::InvalidParam(e.to_string()),
+    }
+}
+
+/// JSON 请求体解析失败时返回统一格式的错误；需要修改大小限制时在此基础上调用 limit
+pub fn json_config() -> JsonConfig {
+    JsonConfig::default().error_handler(|err, _| json_error(err).into())
+}
+
+pub fn query_config() -> QueryConfig {
+    QueryConfig::default().error_handler(|err, _| match err {
+        QueryPayloadError::Deserialize(e) => deserialize_error(e.to_string()).into(),
+        e => AppError::InvalidParam(e.to_string()).into(),
+    })
+}
+
+pub fn path_config() -> PathConfig {
+    PathConfig::default().error_handler(|err, _| AppError::InvalidParam(err.to_string()).into())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -46,14 +116,10 @@ mod tests {
 
     #[test]
     fn test_response_serialization() {
-        let response = Response {
-            data: Some(TestData {
-                field1: "value1".into(),
-                field2: 42,
-            }),
-            msg: "Success".into(),
-            code: 200,
-        };
+        let response = Response::ok(TestData {
+            field1: "value1".into(),
+            field2: 42,
+        });
 
         let json = serde_json::to_string(&response).unwrap();
         let deserialized: Response<TestData> = serde_json::from_str(&json).unwrap();
@@ -66,4 +132,18 @@ mod tests {
         assert_eq!(response.msg, deserialized.msg);
         assert_eq!(response.code, deserialized.code);
     }
+
+    #[test]
+    fn test_deserialize_error() {
+        let err = serde_json::from_str::<TestData>(r#"{"field2": 1}"#).unwrap_err();
+        match deserialize_error(err.to_string()) {
+            AppError::Validation(errors) => assert_eq!(errors[0].field, "field1"),
+            e => panic!("unexpected error: {}", e),
+        }
+        let err = serde_json::from_str::<TestData>(r#"{"field1": 1}"#).unwrap_err();
+        assert!(matches!(
+            deserialize_error(err.to_string()),
+            AppError::InvalidParam(_)
+        ));
+    }
 }
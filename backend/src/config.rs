@@ -0,0 +1,684 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::ServerError;
+use crate::middleware::rate_limit::IpNet;
+use crate::storage::Operation;
+
+/// 未通过 RPANEL_CONFIG 指定时，在当前目录查找的配置文件
+pub const DEFAULT_CONFIG_FILE: &str = "rpanel.toml";
+
+/// 环境变量覆盖配置项时使用的前缀，层级之间用 __ 分隔，如 RPANEL_SERVER__PORT=9000
+pub const ENV_PREFIX: &str = "RPANEL_";
+
+/// 服务配置，对应 rpanel.toml
+///
+/// 按 默认值 < 配置文件 < 环境变量（含 .env）< 命令行参数 的顺序逐层覆盖，见 [`Config::load_with`]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub server: ServerConfig,
+    pub tls: TlsConfig,
+    pub features: FeaturesConfig,
+    pub storage: StorageConfig,
+    pub image: ImageConfig,
+    pub archive: ArchiveConfig,
+    pub database: DatabaseConfig,
+    pub share: ShareConfig,
+    pub auth: AuthConfig,
+    pub audit: AuditConfig,
+    pub web: WebConfig,
+    pub rate_limit: RateLimitConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub host: String,
+    pub port: u16,
+    pub workers: usize,
+    /// env_logger 过滤规则，如 "info" 或 "info,actix_web=warn"；设置了 RUST_LOG 时以 RUST_LOG 为准
+    pub log_level: String,
+    /// 收到 SIGTERM 后等待处理中的请求和后台任务完成的时间（秒）
+    pub shutdown_timeout_secs: u64,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            host: "127.0.0.1".into(),
+            port: 8080,
+            workers: 2,
+            log_level: "info".into(),
+            shutdown_timeout_secs: 30,
+        }
+    }
+}
+
+/// HTTPS，启用后 server.port 只接受 HTTPS 连接
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TlsConfig {
+    pub enabled: bool,
+    /// PEM 格式的证书链，服务器证书在前
+    pub cert_file: PathBuf,
+    /// PEM 格式的私钥，支持 PKCS#8、PKCS#1 和 SEC1
+    pub key_file: PathBuf,
+    /// 额外监听的 HTTP 端口，收到的请求都重定向到 HTTPS；不填则不监听
+    pub redirect_port: Option<u16>,
+    /// 检查证书文件是否更新的间隔（秒），0 表示只在收到 SIGHUP 时重新加载
+    pub reload_interval_secs: u64,
+}
+
+impl Default for TlsConfig {
+    fn default() -> Self {
+        TlsConfig {
+            enabled: false,
+            cert_file: "data/tls/cert.pem".into(),
+            key_file: "data/tls/key.pem".into(),
+            redirect_port: None,
+            reload_interval_secs: 60,
+        }
+    }
+}
+
+/// 功能开关，关闭后对应的接口不再注册
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FeaturesConfig {
+    pub files: bool,
+    pub images: bool,
+    pub shares: bool,
+    pub audit: bool,
+    /// 在响应头 X-Version 中返回版本号
+    pub version_header: bool,
+    /// 提供 /v1/openapi.json 和 /docs 接口文档
+    pub docs: bool,
+    /// 提供前端页面
+    pub web: bool,
+    /// 按 [rate_limit] 限制请求频率
+    pub rate_limit: bool,
+}
+
+impl Default for FeaturesConfig {
+    fn default() -> Self {
+        FeaturesConfig {
+            files: true,
+            images: true,
+            shares: true,
+            audit: true,
+            version_header: true,
+            docs: true,
+            web: true,
+            rate_limit: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StorageConfig {
+    pub roots: Vec<RootConfig>,
+    /// 图床使用的存储根 id
+    pub image_root: String,
+}
+
+/// 一个具名的存储根
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RootConfig {
+    pub id: String,
+    pub path: PathBuf,
+    #[serde(default)]
+    pub read_only: bool,
+    /// 允许的操作，不填表示全部允许
+    pub operations: Option<Vec<Operation>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ImageConfig {
+    /// 单张图片的大小上限（字节）
+    pub max_upload_bytes: u64,
+    /// 单次上传请求的总大小上限（字节）
+    pub max_request_bytes: u64,
+    /// 允许上传的格式：png、jpeg、gif、bmp、webp
+    pub allowed_types: Vec<String>,
+    /// 缩略图缓存目录
+    pub cache_dir: PathBuf,
+}
+
+/// 解压限制，超过时中止解压，防止压缩炸弹占满磁盘
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ArchiveConfig {
+    /// 单个压缩包解压后的总大小上限（字节）
+    pub max_extract_bytes: u64,
+    /// 单个压缩包的条目数上限
+    pub max_entries: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DatabaseConfig {
+    /// SQLite 数据库文件路径
+    pub url: String,
+    /// 连接池最大连接数
+    pub pool_size: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ShareConfig {
+    /// 分享链接签名密钥文件，不存在时自动生成
+    pub key_file: PathBuf,
+    /// 未指定有效期时的默认值（秒）
+    pub default_ttl_secs: u64,
+    /// 有效期上限（秒）
+    pub max_ttl_secs: u64,
+    /// 生成完整链接时使用的外部地址，如 https://panel.example.com；不填只返回路径
+    pub base_url: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthConfig {
+    /// 登录会话有效期（秒）
+    pub session_ttl_secs: u64,
+    pub cookie_name: String,
+    /// 通过 HTTPS 访问时应设为 true，浏览器只在 HTTPS 下发送 Cookie
+    pub cookie_secure: bool,
+    /// 两步验证 otpauth URI 中显示的发行方名称
+    pub totp_issuer: String,
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        AuthConfig {
+            session_ttl_secs: 7 * 24 * 3600,
+            cookie_name: "rpanel_session".into(),
+            cookie_secure: false,
+            totp_issuer: "rpanel".into(),
+        }
+    }
+}
+
+impl Default for ShareConfig {
+    fn default() -> Self {
+        ShareConfig {
+            key_file: "data/share.key".into(),
+            default_ttl_secs: 24 * 3600,
+            max_ttl_secs: 30 * 24 * 3600,
+            base_url: None,
+        }
+    }
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        DatabaseConfig {
+            url: "data/rpanel.db".into(),
+            pool_size: 8,
+        }
+    }
+}
+
+impl Default for ImageConfig {
+    fn default() -> Self {
+        ImageConfig {
+            max_upload_bytes: 20 * 1024 * 1024,
+            max_request_bytes: 100 * 1024 * 1024,
+            allowed_types: ["png", "jpeg", "gif", "bmp", "webp"]
+                .map(String::from)
+                .to_vec(),
+            cache_dir: "data/cache/thumbs".into(),
+        }
+    }
+}
+
+impl Default for ArchiveConfig {
+    fn default() -> Self {
+        ArchiveConfig {
+            max_extract_bytes: 10 * 1024 * 1024 * 1024,
+            max_entries: 100_000,
+        }
+    }
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        StorageConfig {
+            roots: vec![
+                RootConfig {
+                    id: "files".into(),
+                    path: "data/files".into(),
+                    read_only: false,
+                    operations: None,
+                },
+                RootConfig {
+                    id: "images".into(),
+                    path: "data/images".into(),
+                    read_only: false,
+                    operations: None,
+                },
+            ],
+            image_root: "images".into(),
+        }
+    }
+}
+
+/// 审计日志
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuditConfig {
+    /// 日志保留天数，0 表示永久保留
+    pub retention_days: u32,
+    /// 记录的 JSON 请求体大小上限（字节），超过时只记录路径和查询参数
+    pub max_body_bytes: usize,
+}
+
+impl Default for AuditConfig {
+    fn default() -> Self {
+        AuditConfig {
+            retention_days: 90,
+            max_body_bytes: 64 * 1024,
+        }
+    }
+}
+
+/// 前端页面，默认使用编译时打包进程序的 web/dist
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WebConfig {
+    /// 改为从该目录读取前端文件，修改后不需要重新编译，用于前端开发
+    pub dir: Option<PathBuf>,
+}
+
+/// 令牌桶：最多连续 burst 个请求，之后每秒恢复 per_sec 个
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BucketConfig {
+    pub burst: u32,
+    pub per_sec: f64,
+}
+
+/// 请求限流，客户端按连接的对端地址区分
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RateLimitConfig {
+    /// 每个 IP 的全部请求
+    pub ip: BucketConfig,
+    /// 每个已登录用户的请求，会话和 API 令牌合并计算
+    pub user: BucketConfig,
+    /// 每个 IP 的登录请求（/v1/auth/login 和 /v1/auth/login/totp）
+    pub auth: BucketConfig,
+    /// 同一 IP 登录失败达到该次数后锁定，0 表示不锁定
+    pub max_login_failures: u32,
+    /// 锁定时长（秒），同时也是失败次数的统计窗口
+    pub lockout_secs: u64,
+    /// 不受限制的 IP 或网段，如 127.0.0.1、10.0.0.0/8
+    pub allow_list: Vec<String>,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        RateLimitConfig {
+            ip: BucketConfig {
+                burst: 200,
+                per_sec: 50.0,
+            },
+            user: BucketConfig {
+                burst: 100,
+                per_sec: 20.0,
+            },
+            auth: BucketConfig {
+                burst: 5,
+                per_sec: 0.1,
+            },
+            max_login_failures: 10,
+            lockout_secs: 15 * 60,
+            allow_list: Vec::new(),
+        }
+    }
+}
+
+/// 把 "8080"、"true"、"[1, 2]" 这样的文本按 TOML 值解析，解析失败时作为字符串；
+/// 默认配置中该项是字符串时，除了带引号的 TOML 字符串外都原样使用，cookie 名为 123 时不会变成整数
+fn parse_value(raw: &str, defaults: &toml::Table, path: &[&str]) -> toml::Value {
+    let parsed = toml::from_str::<toml::Table>(&format!("v = {}", raw))
+        .ok()
+        .and_then(|mut t| t.remove("v"));
+    let is_str = path
+        .split_last()
+        .and_then(|(last, parents)| {
+            parents
+                .iter()
+                .try_fold(defaults, |table, key| table.get(*key)?.as_table())?
+                .get(*last)
+        })
+        .is_some_and(toml::Value::is_str);
+    match parsed {
+        Some(value) if !is_str || value.is_str() => value,
+        _ => toml::Value::String(raw.to_string()),
+    }
+}
+
+/// 按 server.port 这样的路径设置配置项，中间层不存在时自动创建
+fn set_path(
+    table: &mut toml::Table,
+    path: &[&str],
+    value: toml::Value,
+    source: &str,
+) -> Result<(), ServerError> {
+    let invalid = || ServerError::Config(format!("{}: invalid key {:?}", source, path.join(".")));
+    let (last, parents) = path.split_last().ok_or_else(invalid)?;
+    if path.iter().any(|key| key.is_empty()) {
+        return Err(invalid());
+    }
+    let mut current = table;
+    for key in parents {
+        current = current
+            .entry(key.to_string())
+            .or_insert_with(|| toml::Value::Table(toml::Table::new()))
+            .as_table_mut()
+            .ok_or_else(invalid)?;
+    }
+    current.insert(last.to_string(), value);
+    Ok(())
+}
+
+/// 检查 env_logger 过滤规则：逗号分隔的 级别、模块名 或 模块名=级别
+fn check_log_filter(filter: &str) -> bool {
+    let is_module = |s: &str| {
+        !s.is_empty()
+            && s.chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == ':')
+    };
+    !filter.trim().is_empty()
+        && filter
+            .split(',')
+            .map(str::trim)
+            .all(|directive| match directive.split_once('=') {
+                Some((module, level)) => {
+                    is_module(module) && level.parse::<log::LevelFilter>().is_ok()
+                }
+                None => directive.parse::<log::LevelFilter>().is_ok() || is_module(directive),
+            })
+}
+
+impl Config {
+    /// 逐层加载配置并校验
+    ///
+    /// 配置文件依次取 file、RPANEL_CONFIG、当前目录下的 rpanel.toml，都没有时只使用默认值；
+    /// overrides 为命令行中的 (server.port, 9000) 形式的覆盖项
+    pub fn load_with(
+        file: Option<&Path>,
+        overrides: &[(String, String)],
+    ) -> Result<Config, ServerError> {
+        // .env 不存在时忽略
+        dotenv::dotenv().ok();
+        let env: Vec<(String, String)> = std::env::vars().collect();
+        Config::layered(file, &env, overrides)
+    }
+
+    fn layered(
+        file: Option<&Path>,
+        env: &[(String, String)],
+        overrides: &[(String, String)],
+    ) -> Result<Config, ServerError> {
+        let env_file = env
+            .iter()
+            .find(|(k, _)| k == "RPANEL_CONFIG")
+            .map(|(_, v)| PathBuf::from(v));
+        let file = match file.map(Path::to_path_buf).or(env_file) {
+            Some(path) => Some(path),
+            None if Path::new(DEFAULT_CONFIG_FILE).exists() => Some(DEFAULT_CONFIG_FILE.into()),
+            None => None,
+        };
+        let mut table = match &file {
+            Some(path) => {
+                let text = std::fs::read_to_string(path)
+                    .map_err(|e| ServerError::Config(format!("{}: {}", path.display(), e)))?;
+                toml::from_str::<toml::Table>(&text)
+                    .map_err(|e| ServerError::Config(format!("{}: {}", path.display(), e)))?
+            }
+            None => toml::Table::new(),
+        };
+        let defaults = toml::Table::try_from(Config::default())
+            .map_err(|e| ServerError::Config(e.to_string()))?;
+        for (key, value) in env {
+            // 只有带 __ 的变量是配置项，RPANEL_CONFIG 这类不是
+            let Some(name) = key.strip_prefix(ENV_PREFIX).filter(|n| n.contains("__")) else {
+                continue;
+            };
+            let name = name.to_lowercase();
+            let path: Vec<&str> = name.split("__").collect();
+            set_path(&mut table, &path, parse_value(value, &defaults, &path), key)?;
+        }
+        for (key, value) in overrides {
+            let path: Vec<&str> = key.split('.').collect();
+            let value = parse_value(value, &defaults, &path);
+            set_path(&mut table, &path, value, "command line")?;
+        }
+        let config: Config = table.try_into().map_err(|e: toml::de::Error| {
+            let source = file
+                .as_ref()
+                .map(|p| p.display().to_string())
+                .unwrap_or_else(|| "config".into());
+            ServerError::Config(format!("{}: {}", source, e))
+        })?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// 序列化为 TOML，用于生成默认配置文件和输出生效的配置
+    pub fn to_toml(&self) -> Result<String, ServerError> {
+        toml::to_string_pretty(self).map_err(|e| ServerError::Config(e.to_string()))
+    }
+
+    /// 检查文件系统之外的配置项；存储根和数据库在启动时由 Storage、Database 检查
+    pub fn validate(&self) -> Result<(), ServerError> {
+        let invalid = |msg: String| Err(ServerError::Config(msg));
+        if self.server.port == 0 {
+            return Err(ServerError::InvalidPort(self.server.port));
+        }
+        if self.server.host.trim().is_empty() {
+            return invalid("server.host must not be empty".into());
+        }
+        if self.server.workers == 0 {
+            return invalid("server.workers must be at least 1".into());
+        }
+        if !check_log_filter(&self.server.log_level) {
+            return invalid(format!(
+                "server.log_level is not a valid filter: {:?}",
+                self.server.log_level
+            ));
+        }
+        if self.tls.enabled {
+            if self.tls.cert_file.as_os_str().is_empty() || self.tls.key_file.as_os_str().is_empty()
+            {
+                return invalid("tls.cert_file and tls.key_file are required".into());
+            }
+            if let Some(port) = self.tls.redirect_port {
+                if port == 0 {
+                    return Err(ServerError::InvalidPort(port));
+                }
+                if port == self.server.port {
+                    return invalid("tls.redirect_port must differ from server.port".into());
+                }
+            }
+        }
+        if self.storage.roots.is_empty() {
+            return invalid("storage.roots must not be empty".into());
+        }
+        if self.features.images && self.storage.image_root.is_empty() {
+            return invalid("storage.image_root is required when images are enabled".into());
+        }
+        if self.database.url.trim().is_empty() {
+            return invalid("database.url must not be empty".into());
+        }
+        if self.database.pool_size == 0 {
+            return invalid("database.pool_size must be at least 1".into());
+        }
+        if self.share.default_ttl_secs == 0 || self.share.default_ttl_secs > self.share.max_ttl_secs
+        {
+            return invalid("share.default_ttl_secs must be between 1 and max_ttl_secs".into());
+        }
+        if self.archive.max_extract_bytes == 0 || self.archive.max_entries == 0 {
+            return invalid("archive limits must be greater than 0".into());
+        }
+        if self.auth.session_ttl_secs == 0 {
+            return invalid("auth.session_ttl_secs must be greater than 0".into());
+        }
+        let rate_limit = &self.rate_limit;
+        for (name, bucket) in [
+            ("ip", rate_limit.ip),
+            ("user", rate_limit.user),
+            ("auth", rate_limit.auth),
+        ] {
+            if bucket.burst == 0 || !(bucket.per_sec > 0.0 && bucket.per_sec.is_finite()) {
+                return invalid(format!(
+                    "rate_limit.{} needs burst >= 1 and per_sec > 0",
+                    name
+                ));
+            }
+        }
+        if rate_limit.max_login_failures > 0 && rate_limit.lockout_secs == 0 {
+            return invalid("rate_limit.lockout_secs must be greater than 0".into());
+        }
+        for entry in &rate_limit.allow_list {
+            if entry.parse::<IpNet>().is_err() {
+                return invalid(format!(
+                    "rate_limit.allow_list: invalid address or network {:?}",
+                    entry
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_roots() {
+        let config: Config = toml::from_str(
+            r#"
+            [storage]
+            image_root = "shots"
+
+            [[storage.roots]]
+            id = "logs"
+            path = "/var/log"
+            read_only = true
+
+            [[storage.roots]]
+            id = "shots"
+            path = "/srv/shots"
+            operations = ["read", "write", "delete"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.storage.image_root, "shots");
+        assert_eq!(config.storage.roots.len(), 2);
+        assert!(config.storage.roots[0].read_only);
+        assert_eq!(
+            config.storage.roots[1].operations,
+            Some(vec![Operation::Read, Operation::Write, Operation::Delete])
+        );
+    }
+
+    #[test]
+    fn test_layered() {
+        let dir = std::env::temp_dir().join(format!("rpanel-config-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("rpanel.toml");
+        std::fs::write(
+            &file,
+            r#"
+            [server]
+            port = 9000
+            workers = 4
+            "#,
+        )
+        .unwrap();
+        let env = vec![
+            ("RPANEL_SERVER__PORT".to_string(), "9100".to_string()),
+            ("RPANEL_SERVER__HOST".to_string(), "0.0.0.0".to_string()),
+            ("RPANEL_FEATURES__AUDIT".to_string(), "false".to_string()),
+            ("RPANEL_CONFIG".to_string(), "/nonexistent.toml".to_string()),
+            ("HOME".to_string(), "/root".to_string()),
+        ];
+        let overrides = vec![("server.port".to_string(), "9200".to_string())];
+        let config = Config::layered(Some(&file), &env, &overrides).unwrap();
+        // 命令行 > 环境变量 > 配置文件 > 默认值
+        assert_eq!(config.server.port, 9200);
+        assert_eq!(config.server.host, "0.0.0.0");
+        assert_eq!(config.server.workers, 4);
+        assert_eq!(config.server.log_level, "info");
+        assert!(!config.features.audit);
+        assert!(config.features.images);
+
+        // 字符串项的值不按 TOML 猜测类型，带引号时按 TOML 字符串解析
+        let env = vec![
+            ("RPANEL_AUTH__COOKIE_NAME".to_string(), "123".to_string()),
+            ("RPANEL_SERVER__LOG_LEVEL".to_string(), "true".to_string()),
+            ("RPANEL_SERVER__HOST".to_string(), r#""::""#.to_string()),
+        ];
+        let overrides = vec![("database.url".to_string(), "[1, 2]".to_string())];
+        let config = Config::layered(Some(&file), &env, &overrides).unwrap();
+        assert_eq!(config.auth.cookie_name, "123");
+        assert_eq!(config.server.log_level, "true");
+        assert_eq!(config.server.host, "::");
+        assert_eq!(config.database.url, "[1, 2]");
+
+        let bad = vec![("server.port".to_string(), "0".to_string())];
+        assert!(matches!(
+            Config::layered(Some(&file), &[], &bad),
+            Err(ServerError::InvalidPort(0))
+        ));
+        let bad = vec![("server.workers".to_string(), "many".to_string())];
+        assert!(Config::layered(Some(&file), &[], &bad).is_err());
+        let bad = vec![("server.nope".to_string(), "1".to_string())];
+        assert!(Config::layered(Some(&file), &[], &bad).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_check_log_filter() {
+        assert!(check_log_filter("info"));
+        assert!(check_log_filter("info,actix_web=warn"));
+        assert!(check_log_filter("backend"));
+        assert!(!check_log_filter("info level"));
+        assert!(!check_log_filter("info,actix_web=loud"));
+        assert!(!check_log_filter(""));
+    }
+
+    #[test]
+    fn test_reject_unknown_operation() {
+        let res = toml::from_str::<Config>(
+            r#"
+            [[storage.roots]]
+            id = "x"
+            path = "/x"
+            operations = ["format"]
+            "#,
+        );
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_validate_rate_limit() {
+        let mut config = Config::default();
+        config.rate_limit.allow_list = vec!["127.0.0.1".into(), "10.0.0.0/8".into()];
+        assert!(config.validate().is_ok());
+        config.rate_limit.allow_list.push("10.0.0.0/40".into());
+        assert!(config.validate().is_err());
+
+        let mut config = Config::default();
+        config.rate_limit.auth.per_sec = 0.0;
+        assert!(config.validate().is_err());
+    }
+}
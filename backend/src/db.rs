@@ -0,0 +1,196 @@
+use diesel::connection::SimpleConnection;
+use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, CustomizeConnection, Pool, PooledConnection};
+use diesel_migrations::{EmbeddedMigrations, MigrationHarness, embed_migrations};
+use log::info;
+use std::path::Path;
+
+use crate::ServerError;
+use crate::config::DatabaseConfig;
+use crate::error::AppError;
+
+/// 编译时嵌入 migrations/ 目录下的迁移，已执行的版本记录在 __diesel_schema_migrations 中，与 diesel CLI 相同
+const MIGRATIONS: EmbeddedMigrations = embed_migrations!("migrations");
+
+/// 连接池中的连接，可当作 `&mut SqliteConnection` 使用
+pub type DbConnection = PooledConnection<ConnectionManager<SqliteConnection>>;
+
+/// 每个新连接都要设置的 PRAGMA，SQLite 的这些设置只对当前连接有效
+#[derive(Debug)]
+struct ConnectionOptions;
+
+impl CustomizeConnection<SqliteConnection, diesel::r2d2::Error> for ConnectionOptions {
+    fn on_acquire(&self, conn: &mut SqliteConnection) -> Result<(), diesel::r2d2::Error> {
+        conn.batch_execute("PRAGMA busy_timeout = 5000; PRAGMA foreign_keys = ON;")
+            .map_err(diesel::r2d2::Error::QueryError)
+    }
+}
+
+/// SQLite 连接池，以 `web::Data<Database>` 共享给各个 handler
+#[derive(Debug, Clone)]
+pub struct Database {
+    pool: Pool<ConnectionManager<SqliteConnection>>,
+}
+
+impl Database {
+    /// 创建数据库所在目录并执行迁移
+    pub fn open(config: &DatabaseConfig) -> Result<Database, ServerError> {
+        Database::migrate(config).map(|(db, _)| db)
+    }
+
+    /// 同 [`Database::open`]，同时返回本次执行的迁移版本
+    pub fn migrate(config: &DatabaseConfig) -> Result<(Database, Vec<String>), ServerError> {
+        let err = |e: &dyn std::fmt::Display| {
+            ServerError::Config(format!("database {}: {}", config.url, e))
+        };
+        if let Some(parent) = Path::new(&config.url).parent()
+            && !parent.as_os_str().is_empty()
+        {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| ServerError::Config(format!("{}: {}", parent.display(), e)))?;
+        }
+        let pool = Pool::builder()
+            .max_size(config.pool_size)
+            .connection_customizer(Box::new(ConnectionOptions))
+            .build(ConnectionManager::new(&config.url))
+            .map_err(|e| err(&e))?;
+        let mut conn = pool.get().map_err(|e| err(&e))?;
+        // WAL 模式写入数据库文件，只需设置一次；读写可以并发进行
+        conn.batch_execute("PRAGMA journal_mode = WAL;")
+            .map_err(|e| err(&e))?;
+        let applied = conn
+            .run_pending_migrations(MIGRATIONS)
+            .map_err(|e| err(&e))?
+            .into_iter()
+            .map(|version| version.to_string())
+            .collect::<Vec<_>>();
+        for version in &applied {
+            info!("Applied migration {}", version);
+        }
+        info!("Opened database {}", config.url);
+        Ok((Database { pool }, applied))
+    }
+
+    /// 从连接池取一个连接，会阻塞，需在 web::block 中使用；async 代码中优先用 [`Database::run`]
+    pub fn connect(&self) -> Result<DbConnection, AppError> {
+        self.pool
+            .get()
+            .map_err(|e| AppError::Unknown(format!("数据库连接失败: {}", e)))
+    }
+
+    /// 在阻塞线程池中取连接并执行 f，避免数据库操作占用 actix 的 worker 线程
+    pub async fn run<F, T>(&self, f: F) -> Result<T, AppError>
+    where
+        F: FnOnce(&mut SqliteConnection) -> Result<T, AppError> + Send + 'static,
+        T: Send + 'static,
+    {
+        let db = self.clone();
+        actix_web::web::block(move || {
+            let mut conn = db.connect()?;
+            f(&mut conn)
+        })
+        .await?
+    }
+}
+
+/// 测试用的临时数据库，每个实例使用独立的目录，drop 时删除
+#[cfg(test)]
+pub struct TestDb {
+    pub db: Database,
+    pub config: DatabaseConfig,
+    pub dir: std::path::PathBuf,
+}
+
+#[cfg(test)]
+impl TestDb {
+    pub fn new() -> TestDb {
+        let dir = std::env::temp_dir().join(format!("rpanel-db-{}", uuid::Uuid::new_v4()));
+        let config = DatabaseConfig {
+            url: dir.join("test.db").to_string_lossy().to_string(),
+            ..DatabaseConfig::default()
+        };
+        let db = Database::open(&config).unwrap();
+        TestDb { db, config, dir }
+    }
+
+    pub fn conn(&self) -> DbConnection {
+        self.db.connect().unwrap()
+    }
+
+    /// 插入一个密码哈希无效（无法登录）的用户，返回用户 id
+    pub fn create_user(&self, username: &str, role: &str) -> i32 {
+        use crate::auth_api::model::NewUser;
+        use crate::schema::users;
+        let now = chrono::Utc::now().naive_utc();
+        diesel::insert_into(users::table)
+            .values(&NewUser {
+                username,
+                password_hash: "x",
+                role,
+                created_at: now,
+                updated_at: now,
+            })
+            .execute(&mut self.conn())
+            .unwrap();
+        users::table
+            .filter(users::username.eq(username))
+            .select(users::id)
+            .first(&mut self.conn())
+            .unwrap()
+    }
+}
+
+#[cfg(test)]
+impl Drop for TestDb {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_open_twice() {
+        let test_db = TestDb::new();
+        // 已执行的迁移不会重复执行
+        let (db, applied) = Database::migrate(&test_db.config).unwrap();
+        assert!(applied.is_empty());
+        let count: i64 = crate::schema::images::table
+            .count()
+            .get_result(&mut db.connect().unwrap())
+            .unwrap();
+        assert_eq!(count, 0);
+    }
+
+    #[actix_web::test]
+    async fn test_run() {
+        let test_db = TestDb::new();
+        let id = test_db.create_user("alice", "viewer");
+        let name = test_db
+            .db
+            .run(move |conn| {
+                Ok(crate::schema::users::table
+                    .find(id)
+                    .select(crate::schema::users::username)
+                    .first::<String>(conn)?)
+            })
+            .await
+            .unwrap();
+        assert_eq!(name, "alice");
+        // 外键约束在每个连接上都生效
+        let result = test_db
+            .db
+            .run(|conn| {
+                diesel::sql_query(
+                    "INSERT INTO sessions (token_hash, user_id, created_at, expires_at, last_seen_at) \
+                     VALUES ('x', 999, 0, 0, 0)",
+                )
+                .execute(conn)?;
+                Ok(())
+            })
+            .await;
+        assert!(result.is_err());
+    }
+}
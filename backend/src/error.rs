@@ -1,8 +1,43 @@
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
+use utoipa::ToSchema;
 
-use actix_web::http::StatusCode;
+use actix_web::http::{StatusCode, header};
 use actix_web::{HttpResponse, ResponseError};
 
+/// 响应中的应用错误码，每个 AppError 变体对应一个，发布后不再改变
+///
+/// 前三位与 HTTP 状态码相同，后两位区分同一状态码下的不同错误
+pub mod code {
+    pub const SUCCESS: u16 = 0;
+    pub const INVALID_PARAM: u16 = 40000;
+    pub const VALIDATION: u16 = 40001;
+    pub const UNAUTHORIZED: u16 = 40100;
+    pub const FORBIDDEN: u16 = 40300;
+    pub const NOT_FOUND: u16 = 40400;
+    pub const CONFLICT: u16 = 40900;
+    pub const GONE: u16 = 41000;
+    pub const PAYLOAD_TOO_LARGE: u16 = 41300;
+    pub const TOO_MANY_REQUESTS: u16 = 42900;
+    pub const UNKNOWN: u16 = 50000;
+    pub const IO: u16 = 50001;
+}
+
+/// 参数校验失败的字段，field 为请求中的字段名
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct FieldError {
+    pub field: String,
+    pub msg: String,
+}
+
+fn join_fields(errors: &[FieldError]) -> String {
+    errors
+        .iter()
+        .map(|e| format!("{}: {}", e.field, e.msg))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
 #[derive(Error, Debug)]
 pub enum AppError {
     #[error("IO错误: {0}")]
@@ -11,32 +46,125 @@ pub enum AppError {
     #[error("无效参数: {0}")]
     InvalidParam(String),
 
+    /// 一个或多个字段未通过校验，响应的 errors 中逐个列出
+    #[error("参数校验失败: {}", join_fields(.0))]
+    Validation(Vec<FieldError>),
+
     #[error("未授权访问")]
     Unauthorized,
 
+    #[error("权限不足: {0}")]
+    Forbidden(String),
+
     #[error("找不到资源: {0}")]
     NotFound(String),
 
+    #[error("资源冲突: {0}")]
+    Conflict(String),
+
+    #[error("内容过大: {0}")]
+    PayloadTooLarge(String),
+
+    #[error("链接已失效: {0}")]
+    Gone(String),
+
+    /// 触发限流，值为建议的重试等待秒数，通过 Retry-After 响应头返回
+    #[error("请求过于频繁，请 {0} 秒后重试")]
+    TooManyRequests(u64),
+
     #[error("未知错误: {0}")]
     Unknown(String),
 }
 
+impl AppError {
+    /// 单个字段未通过校验
+    pub fn field(field: &str, msg: impl Into<String>) -> Self {
+        AppError::Validation(vec![FieldError {
+            field: field.to_string(),
+            msg: msg.into(),
+        }])
+    }
+
+    /// 应用错误码，见 [`code`]
+    pub fn code(&self) -> u16 {
+        match self {
+            AppError::Io(_) => code::IO,
+            AppError::InvalidParam(_) => code::INVALID_PARAM,
+            AppError::Validation(_) => code::VALIDATION,
+            AppError::Unauthorized => code::UNAUTHORIZED,
+            AppError::Forbidden(_) => code::FORBIDDEN,
+            AppError::NotFound(_) => code::NOT_FOUND,
+            AppError::Conflict(_) => code::CONFLICT,
+            AppError::PayloadTooLarge(_) => code::PAYLOAD_TOO_LARGE,
+            AppError::Gone(_) => code::GONE,
+            AppError::TooManyRequests(_) => code::TOO_MANY_REQUESTS,
+            AppError::Unknown(_) => code::UNKNOWN,
+        }
+    }
+}
+
+/// 逐个收集字段错误，全部检查完后一起返回
+#[derive(Debug, Default)]
+pub struct Validator {
+    errors: Vec<FieldError>,
+}
+
+impl Validator {
+    pub fn new() -> Self {
+        Validator::default()
+    }
+
+    /// ok 为 false 时记录该字段的错误
+    pub fn check(&mut self, ok: bool, field: &str, msg: impl Into<String>) -> &mut Self {
+        if !ok {
+            self.errors.push(FieldError {
+                field: field.to_string(),
+                msg: msg.into(),
+            });
+        }
+        self
+    }
+
+    pub fn finish(&mut self) -> Result<(), AppError> {
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(AppError::Validation(std::mem::take(&mut self.errors)))
+        }
+    }
+}
+
 impl ResponseError for AppError {
     fn status_code(&self) -> StatusCode {
         match self {
             AppError::Io(_) => StatusCode::INTERNAL_SERVER_ERROR,
-            AppError::InvalidParam(_) => StatusCode::BAD_REQUEST,
+            AppError::InvalidParam(_) | AppError::Validation(_) => StatusCode::BAD_REQUEST,
             AppError::Unauthorized => StatusCode::UNAUTHORIZED,
+            AppError::Forbidden(_) => StatusCode::FORBIDDEN,
             AppError::Unknown(_) => StatusCode::INTERNAL_SERVER_ERROR,
             AppError::NotFound(_) => StatusCode::NOT_FOUND,
+            AppError::Conflict(_) => StatusCode::CONFLICT,
+            AppError::PayloadTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            AppError::Gone(_) => StatusCode::GONE,
+            AppError::TooManyRequests(_) => StatusCode::TOO_MANY_REQUESTS,
         }
     }
 
     fn error_response(&self) -> HttpResponse {
-        HttpResponse::build(self.status_code()).json(crate::base::Response::<()> {
-            data: None,
+        let errors = match self {
+            AppError::Validation(errors) => errors.clone(),
+            _ => Vec::new(),
+        };
+        let mut response = HttpResponse::build(self.status_code());
+        if let AppError::TooManyRequests(secs) = self {
+            response.insert_header((header::RETRY_AFTER, secs.to_string()));
+        }
+        response.json(crate::base::Response::<()> {
+            code: self.code(),
             msg: self.to_string(),
-            code: self.status_code().as_u16(),
+            data: None,
+            errors,
+            request_id: crate::middleware::request_id::current(),
         })
     }
 }
@@ -46,3 +174,58 @@ impl From<anyhow::Error> for AppError {
         AppError::Unknown(e.to_string())
     }
 }
+
+impl From<actix_web::error::BlockingError> for AppError {
+    fn from(e: actix_web::error::BlockingError) -> Self {
+        AppError::Unknown(e.to_string())
+    }
+}
+
+impl From<diesel::result::Error> for AppError {
+    fn from(e: diesel::result::Error) -> Self {
+        match e {
+            diesel::result::Error::NotFound => AppError::NotFound("记录不存在".into()),
+            e => AppError::Unknown(format!("数据库错误: {}", e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 错误码的前三位与 HTTP 状态码一致，且各变体的错误码互不相同
+    #[test]
+    fn test_codes() {
+        let errors = [
+            AppError::Io(std::io::Error::other("x")),
+            AppError::InvalidParam("x".into()),
+            AppError::field("x", "x"),
+            AppError::Unauthorized,
+            AppError::Forbidden("x".into()),
+            AppError::NotFound("x".into()),
+            AppError::Conflict("x".into()),
+            AppError::PayloadTooLarge("x".into()),
+            AppError::Gone("x".into()),
+            AppError::TooManyRequests(1),
+            AppError::Unknown("x".into()),
+        ];
+        let mut codes = std::collections::HashSet::new();
+        for e in &errors {
+            assert_eq!(e.code() / 100, e.status_code().as_u16(), "{}", e);
+            assert!(codes.insert(e.code()));
+        }
+    }
+
+    #[test]
+    fn test_validator() {
+        assert!(Validator::new().check(true, "a", "x").finish().is_ok());
+        let err = Validator::new()
+            .check(false, "a", "x")
+            .check(true, "b", "y")
+            .check(false, "c", "z")
+            .finish()
+            .unwrap_err();
+        assert_eq!(err.to_string(), "参数校验失败: a: x; c: z");
+    }
+}
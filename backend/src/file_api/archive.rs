@@ -0,0 +1,993 @@
+use actix_multipart::form::{MultipartForm, tempfile::TempFile};
+use actix_web::{Responder, get, post, web};
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::{Duration, Instant};
+use utoipa::{IntoParams, ToSchema};
+use walkdir::WalkDir;
+use zip::write::SimpleFileOptions;
+
+use crate::base::Response;
+use crate::config::{ArchiveConfig, Config};
+use crate::error::AppError;
+use crate::openapi::tags;
+use crate::storage::{self, Operation, UserStorage};
+
+/// 上传压缩包的大小上限
+pub const MAX_UPLOAD_SIZE: usize = 1024 * 1024 * 1024;
+
+/// 已结束的任务保留多久，便于前端拿到最终状态
+const JOB_TTL: Duration = Duration::from_secs(3600);
+
+static JOBS: LazyLock<Mutex<HashMap<String, Arc<Job>>>> = LazyLock::new(Default::default);
+
+/// 服务关闭时置位，正在执行的任务在下次读取数据时中止
+static CANCELLED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, ToSchema)]
+pub enum ArchiveFormat {
+    #[serde(rename = "zip")]
+    Zip,
+    #[serde(rename = "tar.gz")]
+    TarGz,
+}
+
+impl ArchiveFormat {
+    /// 先看扩展名，识别不了再看文件头
+    fn detect(path: &Path) -> Result<Self, AppError> {
+        let name = path.to_string_lossy().to_lowercase();
+        if name.ends_with(".zip") {
+            return Ok(ArchiveFormat::Zip);
+        }
+        if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            return Ok(ArchiveFormat::TarGz);
+        }
+        let mut magic = [0u8; 4];
+        let n = File::open(path)?.read(&mut magic)?;
+        match &magic[..n] {
+            [b'P', b'K', 3, 4] | [b'P', b'K', 5, 6] => Ok(ArchiveFormat::Zip),
+            [0x1f, 0x8b, ..] => Ok(ArchiveFormat::TarGz),
+            _ => Err(AppError::InvalidParam("不支持的压缩包格式".into())),
+        }
+    }
+}
+
+enum JobState {
+    Running,
+    Done(Option<String>),
+    Failed(String),
+}
+
+/// 后台压缩/解压任务，进度按字节统计
+pub struct Job {
+    id: String,
+    kind: &'static str,
+    total: AtomicU64,
+    processed: AtomicU64,
+    state: Mutex<JobState>,
+    started: Instant,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct JobStatus {
+    pub id: String,
+    pub kind: String,
+    /// running / done / failed
+    pub state: String,
+    pub total_bytes: u64,
+    pub processed_bytes: u64,
+    pub percent: f32,
+    pub output: Option<String>,
+    pub error: Option<String>,
+}
+
+impl Job {
+    fn status(&self) -> JobStatus {
+        let total = self.total.load(Ordering::Relaxed);
+        let processed = self.processed.load(Ordering::Relaxed);
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let (state, output, error) = match &*state {
+            JobState::Running => ("running", None, None),
+            JobState::Done(output) => ("done", output.clone(), None),
+            JobState::Failed(e) => ("failed", None, Some(e.clone())),
+        };
+        let percent = match state {
+            "done" => 100.0,
+            _ if total == 0 => 0.0,
+            _ => (processed.min(total) as f64 / total as f64 * 100.0) as f32,
+        };
+        JobStatus {
+            id: self.id.clone(),
+            kind: self.kind.to_string(),
+            state: state.to_string(),
+            total_bytes: total,
+            processed_bytes: processed,
+            percent,
+            output,
+            error,
+        }
+    }
+
+    fn is_finished(&self) -> bool {
+        !matches!(
+            *self.state.lock().unwrap_or_else(|e| e.into_inner()),
+            JobState::Running
+        )
+    }
+}
+
+/// 在阻塞线程池中执行任务，立即返回任务状态
+fn spawn_job<F>(kind: &'static str, work: F) -> JobStatus
+where
+    F: FnOnce(&Job) -> Result<Option<String>, AppError> + Send + 'static,
+{
+    let job = Arc::new(Job {
+        id: uuid::Uuid::new_v4().to_string(),
+        kind,
+        total: AtomicU64::new(0),
+        processed: AtomicU64::new(0),
+        state: Mutex::new(JobState::Running),
+        started: Instant::now(),
+    });
+    {
+        let mut jobs = JOBS.lock().unwrap_or_else(|e| e.into_inner());
+        jobs.retain(|_, j| !(j.is_finished() && j.started.elapsed() > JOB_TTL));
+        jobs.insert(job.id.clone(), job.clone());
+    }
+    let status = job.status();
+    actix_web::rt::task::spawn_blocking(move || {
+        let state = match work(&job) {
+            Ok(output) => JobState::Done(output),
+            Err(e) => JobState::Failed(e.to_string()),
+        };
+        *job.state.lock().unwrap_or_else(|e| e.into_inner()) = state;
+    });
+    status
+}
+
+fn running_jobs() -> usize {
+    JOBS.lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .values()
+        .filter(|j| !j.is_finished())
+        .count()
+}
+
+/// 等待正在执行的任务完成，超过 grace 后中止剩余任务，再等待其清理未完成的输出
+pub async fn stop_jobs(grace: Duration) {
+    let wait_until = |deadline: Instant| async move {
+        while running_jobs() > 0 && Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    };
+    wait_until(Instant::now() + grace).await;
+    let running = running_jobs();
+    if running == 0 {
+        return;
+    }
+    warn!("Cancelling {} unfinished archive jobs", running);
+    CANCELLED.store(true, Ordering::Relaxed);
+    wait_until(Instant::now() + Duration::from_secs(5)).await;
+}
+
+/// 统计读取字节数的 Reader，用于汇报进度
+struct Counting<'a, R> {
+    inner: R,
+    job: &'a Job,
+}
+
+impl<R: Read> Read for Counting<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if CANCELLED.load(Ordering::Relaxed) {
+            return Err(io::Error::other("服务正在关闭，任务已取消"));
+        }
+        let n = self.inner.read(buf)?;
+        self.job.processed.fetch_add(n as u64, Ordering::Relaxed);
+        Ok(n)
+    }
+}
+
+/// 校验压缩包内的条目路径，防止 zip-slip 写到目标目录之外
+fn safe_entry_path(dest: &Path, name: &Path) -> Result<PathBuf, AppError> {
+    let mut out = dest.to_path_buf();
+    for comp in name.components() {
+        match comp {
+            Component::Normal(part) => out.push(part),
+            Component::CurDir => {}
+            _ => {
+                return Err(AppError::InvalidParam(format!(
+                    "压缩包包含非法路径: {}",
+                    name.display()
+                )));
+            }
+        }
+    }
+    if out == dest {
+        return Err(AppError::InvalidParam("压缩包包含空路径".into()));
+    }
+    Ok(out)
+}
+
+/// 解析 base 下要压缩的文件或目录，在创建任务前校验
+fn resolve_sources(base: &Path, names: &[String]) -> Result<Vec<PathBuf>, AppError> {
+    names
+        .iter()
+        .map(|name| {
+            let src = storage::resolve(base, name)?;
+            if src.symlink_metadata().is_err() {
+                return Err(AppError::NotFound(format!("{} 不存在", name)));
+            }
+            Ok(src)
+        })
+        .collect()
+}
+
+/// 收集待压缩的文件列表，返回 (绝对路径, 包内路径)
+fn collect_sources(base: &Path, srcs: &[PathBuf]) -> Result<Vec<(PathBuf, String)>, AppError> {
+    let mut sources = Vec::new();
+    for src in srcs {
+        for entry in WalkDir::new(src).follow_links(false) {
+            let entry = entry.map_err(|e| AppError::Io(e.into()))?;
+            let rel = entry
+                .path()
+                .strip_prefix(base)
+                .map_err(|e| AppError::Unknown(e.to_string()))?
+                .to_string_lossy()
+                .replace('\\', "/");
+            sources.push((entry.path().to_path_buf(), rel));
+        }
+    }
+    Ok(sources)
+}
+
+/// 压缩任务的输入，路径都已在创建任务前解析和校验
+struct CompressJob {
+    base: PathBuf,
+    sources: Vec<PathBuf>,
+    dest: PathBuf,
+    format: ArchiveFormat,
+}
+
+fn compress(job: &Job, input: CompressJob) -> Result<(), AppError> {
+    let CompressJob {
+        base,
+        sources,
+        dest,
+        format,
+    } = input;
+    if dest.exists() {
+        return Err(AppError::Conflict(format!("{} 已存在", dest.display())));
+    }
+    let sources = collect_sources(&base, &sources)?;
+    let total = sources
+        .iter()
+        .filter_map(|(p, _)| fs::symlink_metadata(p).ok())
+        .filter(|m| m.is_file())
+        .map(|m| m.len())
+        .sum();
+    job.total.store(total, Ordering::Relaxed);
+
+    // 先写到 .part 文件，完成后再改名，失败时不留下残缺的压缩包
+    let mut part = dest.clone().into_os_string();
+    part.push(".part");
+    let part = PathBuf::from(part);
+    let result = match format {
+        ArchiveFormat::Zip => write_zip(job, &part, &sources),
+        ArchiveFormat::TarGz => write_tar_gz(job, &part, &sources),
+    }
+    .and_then(|_| fs::rename(&part, &dest).map_err(AppError::from));
+    if result.is_err() {
+        let _ = fs::remove_file(&part);
+    }
+    result
+}
+
+fn write_zip(job: &Job, dest: &Path, sources: &[(PathBuf, String)]) -> Result<(), AppError> {
+    use std::os::unix::fs::PermissionsExt;
+    let mut zip = zip::ZipWriter::new(File::create(dest)?);
+    for (src, name) in sources {
+        let meta = fs::symlink_metadata(src)?;
+        let options = SimpleFileOptions::default()
+            .compression_method(zip::CompressionMethod::Deflated)
+            .unix_permissions(meta.permissions().mode())
+            .large_file(meta.len() >= u32::MAX as u64);
+        if meta.is_dir() {
+            zip.add_directory(name.as_str(), options)
+                .map_err(|e| AppError::Unknown(e.to_string()))?;
+        } else if meta.is_file() {
+            zip.start_file(name.as_str(), options)
+                .map_err(|e| AppError::Unknown(e.to_string()))?;
+            io::copy(
+                &mut Counting {
+                    inner: File::open(src)?,
+                    job,
+                },
+                &mut zip,
+            )?;
+        }
+    }
+    zip.finish()
+        .map_err(|e| AppError::Unknown(e.to_string()))?
+        .sync_all()?;
+    Ok(())
+}
+
+fn write_tar_gz(job: &Job, dest: &Path, sources: &[(PathBuf, String)]) -> Result<(), AppError> {
+    let gz = GzEncoder::new(File::create(dest)?, Compression::default());
+    let mut tar = tar::Builder::new(gz);
+    tar.follow_symlinks(false);
+    for (src, name) in sources {
+        let meta = fs::symlink_metadata(src)?;
+        if meta.is_file() {
+            let mut header = tar::Header::new_gnu();
+            header.set_metadata(&meta);
+            tar.append_data(
+                &mut header,
+                name,
+                Counting {
+                    inner: File::open(src)?,
+                    job,
+                },
+            )?;
+        } else if meta.is_dir() || meta.file_type().is_symlink() {
+            tar.append_path_with_name(src, name)?;
+        }
+    }
+    tar.into_inner()?.finish()?.sync_all()?;
+    Ok(())
+}
+
+/// 不还原 setuid/setgid/sticky 位和其他用户的写权限
+const UNSAFE_MODE_BITS: u32 = 0o7002;
+
+/// 按配置限制解压的条目数和总大小，声明的大小不可信，按实际写出的字节数计算
+struct ExtractLimits {
+    max_bytes: u64,
+    max_entries: u64,
+    bytes: u64,
+    entries: u64,
+}
+
+impl ExtractLimits {
+    fn new(config: &ArchiveConfig) -> Self {
+        ExtractLimits {
+            max_bytes: config.max_extract_bytes,
+            max_entries: config.max_entries,
+            bytes: 0,
+            entries: 0,
+        }
+    }
+
+    fn check(&self, entries: u64, bytes: u64) -> Result<(), AppError> {
+        if entries > self.max_entries {
+            return Err(AppError::PayloadTooLarge(format!(
+                "压缩包条目数超过 {}",
+                self.max_entries
+            )));
+        }
+        if bytes > self.max_bytes {
+            return Err(AppError::PayloadTooLarge(format!(
+                "解压后的大小超过 {} 字节",
+                self.max_bytes
+            )));
+        }
+        Ok(())
+    }
+
+    fn add_entry(&mut self) -> Result<(), AppError> {
+        self.entries += 1;
+        self.check(self.entries, self.bytes)
+    }
+
+    fn add_bytes(&mut self, size: u64) -> Result<(), AppError> {
+        self.bytes = self.bytes.saturating_add(size);
+        self.check(self.entries, self.bytes)
+    }
+
+    /// 最多复制到剩余额度多一个字节，超出即报错
+    fn copy(&mut self, reader: &mut impl Read, out: &mut impl io::Write) -> Result<(), AppError> {
+        let remaining = self.max_bytes.saturating_sub(self.bytes);
+        let n = io::copy(&mut reader.take(remaining.saturating_add(1)), out)?;
+        self.add_bytes(n)
+    }
+}
+
+/// 解压任务的输入，格式和路径都已在创建任务前校验
+struct ExtractJob<'a> {
+    /// dest 所在的存储根，写出的文件不能超出此目录
+    root: &'a Path,
+    archive: &'a Path,
+    format: ArchiveFormat,
+    dest: &'a Path,
+    overwrite: bool,
+    limits: &'a ArchiveConfig,
+}
+
+fn extract(job: &Job, input: ExtractJob) -> Result<(), AppError> {
+    fs::create_dir_all(input.dest)?;
+    let mut limits = ExtractLimits::new(input.limits);
+    match input.format {
+        ArchiveFormat::Zip => extract_zip(job, &input, &mut limits),
+        ArchiveFormat::TarGz => extract_tar_gz(job, &input, &mut limits),
+    }
+}
+
+/// 创建解压任务前的同步校验：压缩包格式可识别，目标不是已存在的文件
+fn check_extract(archive: &Path, dest: &Path) -> Result<ArchiveFormat, AppError> {
+    let format = ArchiveFormat::detect(archive)?;
+    if dest.exists() && !dest.is_dir() {
+        return Err(AppError::Conflict(format!(
+            "{} 已存在且不是目录",
+            dest.display()
+        )));
+    }
+    Ok(format)
+}
+
+fn check_target(target: &Path, overwrite: bool) -> Result<(), AppError> {
+    if !overwrite && target.exists() && !target.is_dir() {
+        return Err(AppError::Conflict(format!("{} 已存在", target.display())));
+    }
+    Ok(())
+}
+
+/// zip 条目写出前的检查：dest 到目标之间不能经过已存在的符号链接，目标的实际位置在存储根内，
+/// 已存在的符号链接既不覆盖也不跟随
+fn check_zip_target(input: &ExtractJob, name: &Path, target: &Path) -> Result<(), AppError> {
+    let rel = name.to_string_lossy();
+    let mut path = input.dest.to_path_buf();
+    for comp in name.components() {
+        path.push(comp);
+        match path.symlink_metadata() {
+            Ok(meta) if meta.file_type().is_symlink() => {
+                return Err(AppError::Forbidden(format!(
+                    "压缩包路径经过符号链接: {}",
+                    rel
+                )));
+            }
+            Ok(_) => {}
+            Err(e) if e.kind() == io::ErrorKind::NotFound => break,
+            Err(e) => return Err(e.into()),
+        }
+    }
+    storage::check_within(input.root, target, &rel)?;
+    Ok(())
+}
+
+fn extract_zip(job: &Job, input: &ExtractJob, limits: &mut ExtractLimits) -> Result<(), AppError> {
+    use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+    let (dest, overwrite) = (input.dest, input.overwrite);
+    let mut zip = zip::ZipArchive::new(File::open(input.archive)?)
+        .map_err(|e| AppError::InvalidParam(format!("无法读取压缩包: {}", e)))?;
+    let total: u64 = (0..zip.len())
+        .filter_map(|i| zip.by_index_raw(i).ok().map(|f| f.size()))
+        .sum();
+    // 按目录中声明的数量和大小先检查一遍，明显超限的压缩包不写出任何文件
+    limits.check(zip.len() as u64, total)?;
+    job.total.store(total, Ordering::Relaxed);
+    for i in 0..zip.len() {
+        limits.add_entry()?;
+        let mut file = zip
+            .by_index(i)
+            .map_err(|e| AppError::InvalidParam(format!("无法读取压缩包: {}", e)))?;
+        let name = file.enclosed_name().ok_or_else(|| {
+            AppError::InvalidParam(format!("压缩包包含非法路径: {}", file.name()))
+        })?;
+        let target = safe_entry_path(dest, &name)?;
+        // 不还原符号链接，避免链接指向目标目录之外
+        if file.is_symlink() {
+            continue;
+        }
+        check_zip_target(input, &name, &target)?;
+        if file.is_dir() {
+            fs::create_dir_all(&target)?;
+            continue;
+        }
+        check_target(&target, overwrite)?;
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        // 检查之后目标被换成符号链接时打开失败，不会写到链接指向处
+        let mut out = fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .custom_flags(nix::libc::O_NOFOLLOW)
+            .open(&target)?;
+        limits.copy(
+            &mut Counting {
+                inner: &mut file,
+                job,
+            },
+            &mut out,
+        )?;
+        if let Some(mode) = file.unix_mode() {
+            let mode = mode & 0o777 & !UNSAFE_MODE_BITS;
+            fs::set_permissions(&target, fs::Permissions::from_mode(mode))?;
+        }
+    }
+    Ok(())
+}
+
+fn extract_tar_gz(
+    job: &Job,
+    input: &ExtractJob,
+    limits: &mut ExtractLimits,
+) -> Result<(), AppError> {
+    let (dest, overwrite) = (input.dest, input.overwrite);
+    // gzip 流无法预知解压后的大小，按已读取的压缩数据汇报进度
+    job.total
+        .store(fs::metadata(input.archive)?.len(), Ordering::Relaxed);
+    let reader = Counting {
+        inner: File::open(input.archive)?,
+        job,
+    };
+    let mut tar = tar::Archive::new(GzDecoder::new(reader));
+    tar.set_mask(UNSAFE_MODE_BITS);
+    for entry in tar.entries()? {
+        let mut entry = entry?;
+        limits.add_entry()?;
+        // tar 条目的数据长度由头部决定，解压时不会超出
+        limits.add_bytes(entry.size())?;
+        let name = entry.path()?.into_owned();
+        let target = safe_entry_path(dest, &name)?;
+        let kind = entry.header().entry_type();
+        if kind.is_symlink() || kind.is_hard_link() {
+            continue;
+        }
+        if !kind.is_dir() {
+            check_target(&target, overwrite)?;
+        }
+        entry.unpack_in(dest)?;
+    }
+    Ok(())
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct ArchiveEntry {
+    pub name: String,
+    pub size: u64,
+    pub compressed_size: Option<u64>,
+    pub is_dir: bool,
+}
+
+fn list_entries(archive: &Path) -> Result<Vec<ArchiveEntry>, AppError> {
+    let mut entries = Vec::new();
+    match ArchiveFormat::detect(archive)? {
+        ArchiveFormat::Zip => {
+            let mut zip = zip::ZipArchive::new(File::open(archive)?)
+                .map_err(|e| AppError::InvalidParam(format!("无法读取压缩包: {}", e)))?;
+            for i in 0..zip.len() {
+                let file = zip
+                    .by_index_raw(i)
+                    .map_err(|e| AppError::InvalidParam(format!("无法读取压缩包: {}", e)))?;
+                entries.push(ArchiveEntry {
+                    name: file.name().to_string(),
+                    size: file.size(),
+                    compressed_size: Some(file.compressed_size()),
+                    is_dir: file.is_dir(),
+                });
+            }
+        }
+        ArchiveFormat::TarGz => {
+            let mut tar = tar::Archive::new(GzDecoder::new(File::open(archive)?));
+            for entry in tar.entries()? {
+                let entry = entry?;
+                entries.push(ArchiveEntry {
+                    name: entry.path()?.to_string_lossy().to_string(),
+                    size: entry.header().size()?,
+                    compressed_size: None,
+                    is_dir: entry.header().entry_type().is_dir(),
+                });
+            }
+        }
+    }
+    Ok(entries)
+}
+
+#[derive(Deserialize, ToSchema)]
+struct CompressRequest {
+    root: String,
+    /// 被压缩文件所在目录
+    dir: String,
+    /// dir 下要打包的文件或目录
+    names: Vec<String>,
+    /// 生成的压缩包路径
+    dest: String,
+    format: ArchiveFormat,
+}
+
+#[derive(Deserialize, ToSchema)]
+struct ExtractRequest {
+    root: String,
+    path: String,
+    dest: String,
+    #[serde(default)]
+    overwrite: bool,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct UploadQuery {
+    root: String,
+    dest: String,
+    #[serde(default)]
+    overwrite: bool,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct EntriesQuery {
+    root: String,
+    path: String,
+}
+
+#[derive(Debug, MultipartForm, ToSchema)]
+#[schema(as = ArchiveUploadForm)]
+struct UploadForm {
+    #[schema(value_type = String, format = Binary)]
+    file: TempFile,
+}
+
+#[utoipa::path(tag = tags::FILES, responses((status = 200, body = Response<JobStatus>)))]
+#[post("/archive/compress")]
+pub async fn compress_files(
+    req: web::Json<CompressRequest>,
+    storage: UserStorage,
+) -> Result<impl Responder, AppError> {
+    let req = req.into_inner();
+    if req.names.is_empty() {
+        return Err(AppError::InvalidParam("未选择要压缩的文件".into()));
+    }
+    let root = storage.root(&req.root, Operation::Archive)?;
+    let base = root.resolve(&req.dir)?;
+    if !base.is_dir() {
+        return Err(AppError::NotFound("目录不存在".into()));
+    }
+    let dest = root.resolve(&req.dest)?;
+    if dest.symlink_metadata().is_ok() {
+        return Err(AppError::Conflict(format!("{} 已存在", req.dest)));
+    }
+    if !dest.parent().is_some_and(Path::is_dir) {
+        return Err(AppError::NotFound("目标目录不存在".into()));
+    }
+    let input = CompressJob {
+        sources: resolve_sources(&base, &req.names)?,
+        base,
+        dest,
+        format: req.format,
+    };
+    let output = req.dest;
+    let status = spawn_job("compress", move |job| {
+        compress(job, input).map(|_| Some(output))
+    });
+    Ok(Response::ok(status))
+}
+
+#[utoipa::path(tag = tags::FILES, responses((status = 200, body = Response<JobStatus>)))]
+#[post("/archive/extract")]
+pub async fn extract_file(
+    req: web::Json<ExtractRequest>,
+    storage: UserStorage,
+    config: web::Data<Config>,
+) -> Result<impl Responder, AppError> {
+    let root = storage.root(&req.root, Operation::Archive)?;
+    let archive = root.resolve(&req.path)?;
+    if !archive.is_file() {
+        return Err(AppError::NotFound("压缩包不存在".into()));
+    }
+    let dest = root.resolve(&req.dest)?;
+    let format = check_extract(&archive, &dest)?;
+    let overwrite = req.overwrite;
+    let output = req.dest.clone();
+    let root = root.path.clone();
+    let status = spawn_job("extract", move |job| {
+        let input = ExtractJob {
+            root: &root,
+            archive: &archive,
+            format,
+            dest: &dest,
+            overwrite,
+            limits: &config.archive,
+        };
+        extract(job, input).map(|_| Some(output))
+    });
+    Ok(Response::ok(status))
+}
+
+#[utoipa::path(
+    tag = tags::FILES,
+    params(UploadQuery),
+    request_body(content = UploadForm, content_type = "multipart/form-data"),
+    responses((status = 200, body = Response<JobStatus>))
+)]
+#[post("/archive/upload")]
+pub async fn extract_upload(
+    query: web::Query<UploadQuery>,
+    MultipartForm(form): MultipartForm<UploadForm>,
+    storage: UserStorage,
+    config: web::Data<Config>,
+) -> Result<impl Responder, AppError> {
+    let query = query.into_inner();
+    let root = storage.root(&query.root, Operation::Archive)?;
+    let dest = root.resolve(&query.dest)?;
+    let root = root.path.clone();
+    let file = form.file;
+    // 临时文件没有扩展名，按文件头识别格式
+    let format = check_extract(file.file.path(), &dest)?;
+    let status = spawn_job("extract", move |job| {
+        // file 在任务结束后才会被删除
+        let input = ExtractJob {
+            root: &root,
+            archive: file.file.path(),
+            format,
+            dest: &dest,
+            overwrite: query.overwrite,
+            limits: &config.archive,
+        };
+        extract(job, input).map(|_| Some(query.dest))
+    });
+    Ok(Response::ok(status))
+}
+
+#[utoipa::path(
+    tag = tags::FILES,
+    params(EntriesQuery),
+    responses((status = 200, body = Response<Vec<ArchiveEntry>>))
+)]
+#[get("/archive/entries")]
+pub async fn archive_entries(
+    query: web::Query<EntriesQuery>,
+    storage: UserStorage,
+) -> Result<impl Responder, AppError> {
+    let archive = storage
+        .root(&query.root, Operation::Read)?
+        .resolve(&query.path)?;
+    if !archive.is_file() {
+        return Err(AppError::NotFound("压缩包不存在".into()));
+    }
+    let entries = web::block(move || list_entries(&archive)).await??;
+    Ok(Response::ok(entries))
+}
+
+#[utoipa::path(tag = tags::FILES, responses((status = 200, body = Response<JobStatus>)))]
+#[get("/archive/jobs/{id}")]
+pub async fn job_status(id: web::Path<String>) -> Result<impl Responder, AppError> {
+    let jobs = JOBS.lock().unwrap_or_else(|e| e.into_inner());
+    let job = jobs
+        .get(id.as_str())
+        .ok_or_else(|| AppError::NotFound("任务不存在".into()))?;
+    Ok(Response::ok(job.status()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_safe_entry_path() {
+        let dest = Path::new("/tmp/out");
+        assert_eq!(
+            safe_entry_path(dest, Path::new("a/b.txt")).unwrap(),
+            Path::new("/tmp/out/a/b.txt")
+        );
+        assert!(safe_entry_path(dest, Path::new("../evil")).is_err());
+        assert!(safe_entry_path(dest, Path::new("/etc/passwd")).is_err());
+        assert!(safe_entry_path(dest, Path::new("a/../../evil")).is_err());
+    }
+
+    fn new_job() -> Job {
+        Job {
+            id: "test".into(),
+            kind: "test",
+            total: AtomicU64::new(0),
+            processed: AtomicU64::new(0),
+            state: Mutex::new(JobState::Running),
+            started: Instant::now(),
+        }
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let tmp = std::env::temp_dir().join(format!("rpanel-archive-{}", uuid::Uuid::new_v4()));
+        let src = tmp.join("src");
+        fs::create_dir_all(src.join("conf")).unwrap();
+        fs::write(src.join("conf/app.toml"), "port = 8080\n").unwrap();
+        fs::write(src.join("readme.txt"), "hello").unwrap();
+        let srcs = resolve_sources(&src, &["conf".into(), "readme.txt".into()]).unwrap();
+        let sources = collect_sources(&src, &srcs).unwrap();
+        assert!(matches!(
+            resolve_sources(&src, &["missing".into()]),
+            Err(AppError::NotFound(_))
+        ));
+
+        for format in [ArchiveFormat::Zip, ArchiveFormat::TarGz] {
+            let archive = tmp.join(match format {
+                ArchiveFormat::Zip => "out.zip",
+                ArchiveFormat::TarGz => "out.tar.gz",
+            });
+            let job = new_job();
+            match format {
+                ArchiveFormat::Zip => write_zip(&job, &archive, &sources).unwrap(),
+                ArchiveFormat::TarGz => write_tar_gz(&job, &archive, &sources).unwrap(),
+            }
+            assert_eq!(job.processed.load(Ordering::Relaxed), 17);
+
+            let names: Vec<String> = list_entries(&archive)
+                .unwrap()
+                .into_iter()
+                .map(|e| e.name.trim_end_matches('/').to_string())
+                .collect();
+            assert!(names.contains(&"conf/app.toml".to_string()));
+            assert!(names.contains(&"readme.txt".to_string()));
+
+            let dest = tmp.join("dest");
+            assert_eq!(check_extract(&archive, &dest).unwrap(), format);
+            let limits = ArchiveConfig::default();
+            extract(&new_job(), extract_job(&archive, format, &dest, &limits)).unwrap();
+            assert_eq!(
+                fs::read_to_string(dest.join("conf/app.toml")).unwrap(),
+                "port = 8080\n"
+            );
+            assert!(extract(&new_job(), extract_job(&archive, format, &dest, &limits)).is_err());
+            fs::remove_dir_all(&dest).unwrap();
+
+            // 超过条目数或总大小限制时中止
+            for limits in [
+                ArchiveConfig {
+                    max_entries: 2,
+                    ..Default::default()
+                },
+                ArchiveConfig {
+                    max_extract_bytes: 16,
+                    ..Default::default()
+                },
+            ] {
+                let res = extract(&new_job(), extract_job(&archive, format, &dest, &limits));
+                assert!(matches!(res, Err(AppError::PayloadTooLarge(_))));
+                fs::remove_dir_all(&dest).unwrap();
+            }
+        }
+        assert!(matches!(
+            check_extract(&src.join("readme.txt"), &tmp.join("dest")),
+            Err(AppError::InvalidParam(_))
+        ));
+        assert!(matches!(
+            check_extract(&tmp.join("out.zip"), &src.join("readme.txt")),
+            Err(AppError::Conflict(_))
+        ));
+        fs::remove_dir_all(&tmp).unwrap();
+    }
+
+    fn extract_job<'a>(
+        archive: &'a Path,
+        format: ArchiveFormat,
+        dest: &'a Path,
+        limits: &'a ArchiveConfig,
+    ) -> ExtractJob<'a> {
+        // 测试中 dest 所在的临时目录作为存储根
+        ExtractJob {
+            root: dest.parent().unwrap(),
+            archive,
+            format,
+            dest,
+            overwrite: false,
+            limits,
+        }
+    }
+
+    /// dest 中已有指向存储根外的符号链接时，zip 条目不能经由链接写到外面
+    #[test]
+    fn test_zip_symlink_escape() {
+        let tmp = std::env::temp_dir().join(format!("rpanel-archive-{}", uuid::Uuid::new_v4()));
+        let (root, outside) = (tmp.join("root"), tmp.join("outside"));
+        let dest = root.join("dest");
+        fs::create_dir_all(&dest).unwrap();
+        fs::create_dir_all(&outside).unwrap();
+        std::os::unix::fs::symlink(&outside, dest.join("link")).unwrap();
+        std::os::unix::fs::symlink(outside.join("new.txt"), dest.join("dangling")).unwrap();
+        std::os::unix::fs::symlink(outside.join("dir"), dest.join("dir")).unwrap();
+        let limits = ArchiveConfig::default();
+
+        for entry in ["link/evil.txt", "dangling", "dir/", "dir/a/evil.txt"] {
+            let archive = tmp.join("evil.zip");
+            let mut zip = zip::ZipWriter::new(File::create(&archive).unwrap());
+            if let Some(dir) = entry.strip_suffix('/') {
+                zip.add_directory(dir, SimpleFileOptions::default())
+                    .unwrap();
+            } else {
+                zip.start_file(entry, SimpleFileOptions::default()).unwrap();
+                io::Write::write_all(&mut zip, b"hi").unwrap();
+            }
+            zip.finish().unwrap();
+
+            for overwrite in [false, true] {
+                let input = ExtractJob {
+                    root: &root,
+                    archive: &archive,
+                    format: ArchiveFormat::Zip,
+                    dest: &dest,
+                    overwrite,
+                    limits: &limits,
+                };
+                let res = extract(&new_job(), input);
+                assert!(matches!(res, Err(AppError::Forbidden(_))), "{}", entry);
+            }
+            assert_eq!(fs::read_dir(&outside).unwrap().count(), 0, "{}", entry);
+        }
+        fs::remove_dir_all(&tmp).unwrap();
+    }
+
+    #[test]
+    fn test_limits_ignore_declared_size() {
+        let mut limits = ExtractLimits::new(&ArchiveConfig {
+            max_extract_bytes: 10,
+            max_entries: 1,
+        });
+        assert!(limits.add_entry().is_ok());
+        assert!(limits.add_entry().is_err());
+
+        let mut limits = ExtractLimits::new(&ArchiveConfig {
+            max_extract_bytes: 10,
+            max_entries: 1,
+        });
+        let mut out = Vec::new();
+        assert!(limits.copy(&mut &[0u8; 8][..], &mut out).is_ok());
+        // 不论声明的大小，按实际数据计算，超出后最多多写一个字节即中止
+        assert!(limits.copy(&mut &[0u8; 1000][..], &mut out).is_err());
+        assert_eq!(out.len(), 11);
+    }
+
+    #[test]
+    fn test_unsafe_modes() {
+        use std::os::unix::fs::PermissionsExt;
+        let tmp = std::env::temp_dir().join(format!("rpanel-archive-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&tmp).unwrap();
+        let limits = ArchiveConfig::default();
+        let tar_path = tmp.join("modes.tar.gz");
+        let mut tar = tar::Builder::new(GzEncoder::new(
+            File::create(&tar_path).unwrap(),
+            Compression::default(),
+        ));
+        let mut header = tar::Header::new_gnu();
+        header.set_size(2);
+        header.set_mode(0o4777);
+        header.set_cksum();
+        tar.append_data(&mut header, "suid", &b"hi"[..]).unwrap();
+        tar.into_inner().unwrap().finish().unwrap();
+
+        let zip_path = tmp.join("modes.zip");
+        let mut zip = zip::ZipWriter::new(File::create(&zip_path).unwrap());
+        zip.start_file(
+            "suid",
+            SimpleFileOptions::default().unix_permissions(0o4777),
+        )
+        .unwrap();
+        io::Write::write_all(&mut zip, b"hi").unwrap();
+        zip.finish().unwrap();
+
+        for (archive, format) in [
+            (&tar_path, ArchiveFormat::TarGz),
+            (&zip_path, ArchiveFormat::Zip),
+        ] {
+            let dest = tmp.join("dest");
+            extract(&new_job(), extract_job(archive, format, &dest, &limits)).unwrap();
+            let mode = fs::metadata(dest.join("suid"))
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o7777, 0o775, "{:?}", format);
+            fs::remove_dir_all(&dest).unwrap();
+        }
+        fs::remove_dir_all(&tmp).unwrap();
+    }
+}
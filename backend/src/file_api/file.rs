@@ -1,71 +1,196 @@
 use actix_web::{Responder, get, web};
+use nix::unistd::{Gid, Group, Uid, User};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
+use std::os::unix::fs::{FileTypeExt, MetadataExt};
 use std::path::Path;
+use utoipa::{IntoParams, ToSchema};
 
+use super::path;
+use crate::base::Response;
 use crate::error::AppError;
+use crate::openapi::tags;
+use crate::storage::{Operation, StorageRoot, UserStorage};
 
-#[derive(Deserialize)]
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 struct Info {
+    root: String,
     dir: String,
     page: Option<u32>,
     page_size: Option<u32>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
 pub struct FileInfo {
     pub name: String,
     pub size: u64,
     pub is_dir: bool,
     pub created_at: u64,
     pub modified_at: u64,
+    /// file / dir / symlink / fifo / socket / block_device / char_device
+    pub file_type: String,
+    /// 权限位（含 setuid/setgid/sticky），如 0o755
+    pub mode: u32,
+    /// ls 风格的权限字符串，如 rwxr-xr-x
+    pub permissions: String,
+    pub uid: u32,
+    pub gid: u32,
+    pub owner: Option<String>,
+    pub group: Option<String>,
+    /// 符号链接指向的路径
+    pub symlink_target: Option<String>,
 }
 
+/// 用户名/组名查询缓存，列目录时同一个 uid 只查一次
+#[derive(Default)]
+pub struct NameCache {
+    users: HashMap<u32, Option<String>>,
+    groups: HashMap<u32, Option<String>>,
+}
+
+impl NameCache {
+    pub fn user(&mut self, uid: u32) -> Option<String> {
+        self.users
+            .entry(uid)
+            .or_insert_with(|| {
+                User::from_uid(Uid::from_raw(uid))
+                    .ok()
+                    .flatten()
+                    .map(|u| u.name)
+            })
+            .clone()
+    }
+
+    pub fn group(&mut self, gid: u32) -> Option<String> {
+        self.groups
+            .entry(gid)
+            .or_insert_with(|| {
+                Group::from_gid(Gid::from_raw(gid))
+                    .ok()
+                    .flatten()
+                    .map(|g| g.name)
+            })
+            .clone()
+    }
+}
+
+fn file_type_name(ft: fs::FileType) -> &'static str {
+    if ft.is_symlink() {
+        "symlink"
+    } else if ft.is_dir() {
+        "dir"
+    } else if ft.is_fifo() {
+        "fifo"
+    } else if ft.is_socket() {
+        "socket"
+    } else if ft.is_block_device() {
+        "block_device"
+    } else if ft.is_char_device() {
+        "char_device"
+    } else {
+        "file"
+    }
+}
+
+/// 把权限位格式化为 rwxr-xr-x，setuid/setgid/sticky 按 ls 的习惯显示为 s/t
+pub fn mode_string(mode: u32) -> String {
+    let mut out = String::with_capacity(9);
+    for (shift, special, special_char) in [(6, 0o4000, 's'), (3, 0o2000, 's'), (0, 0o1000, 't')] {
+        let bits = (mode >> shift) & 0o7;
+        out.push(if bits & 0o4 != 0 { 'r' } else { '-' });
+        out.push(if bits & 0o2 != 0 { 'w' } else { '-' });
+        out.push(match (bits & 0o1 != 0, mode & special != 0) {
+            (true, true) => special_char,
+            (false, true) => special_char.to_ascii_uppercase(),
+            (true, false) => 'x',
+            (false, false) => '-',
+        });
+    }
+    out
+}
+
+impl FileInfo {
+    /// 由 symlink_metadata 构造，符号链接本身不会被跟随
+    pub fn from_metadata(
+        name: String,
+        path: &Path,
+        meta: &fs::Metadata,
+        names: &mut NameCache,
+    ) -> Self {
+        let file_type = meta.file_type();
+        let symlink_target = if file_type.is_symlink() {
+            fs::read_link(path)
+                .ok()
+                .map(|t| t.to_string_lossy().to_string())
+        } else {
+            None
+        };
+        let mode = meta.mode() & 0o7777;
+        FileInfo {
+            name,
+            size: if meta.is_file() { meta.len() } else { 0 },
+            is_dir: meta.is_dir(),
+            created_at: meta
+                .created()
+                .ok()
+                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+            modified_at: path::modified_secs(meta),
+            file_type: file_type_name(file_type).to_string(),
+            mode,
+            permissions: mode_string(mode),
+            uid: meta.uid(),
+            gid: meta.gid(),
+            owner: names.user(meta.uid()),
+            group: names.group(meta.gid()),
+            symlink_target,
+        }
+    }
+}
+
+#[utoipa::path(
+    tag = tags::FILES,
+    params(Info),
+    responses((status = 200, body = Response<Vec<FileInfo>>))
+)]
 #[get("/")]
-pub async fn file_list(info: web::Query<Info>) -> Result<impl Responder, AppError> {
+pub async fn file_list(
+    info: web::Query<Info>,
+    storage: UserStorage,
+) -> Result<impl Responder, AppError> {
     let dir = info.dir.clone();
     let _page = info.page.unwrap_or(1);
     let _page_size = info.page_size.unwrap_or(10);
-    let root = Path::new("/home");
-    let path = root.join(dir);
-    if path.exists() == false || path.is_dir() == false {
+    let root = storage.root(&info.root, Operation::Read)?;
+    let path = root.resolve(&dir)?;
+    if !path.is_dir() {
         return Err(AppError::NotFound("目录不存在".into()));
     }
     let mut files = Vec::new();
+    let mut names = NameCache::default();
     if let Ok(entries) = fs::read_dir(path) {
         for entry in entries.flatten() {
-            let meta = entry.metadata();
             let name = entry.file_name().to_string_lossy().to_string();
-            let (size, is_dir, created_at, modified_at) = if let Ok(meta) = meta {
-                let size = if meta.is_file() { meta.len() } else { 0 };
-                let is_dir = meta.is_dir();
-                let created_at = meta
-                    .created()
-                    .ok()
-                    .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
-                    .map(|d| d.as_secs())
-                    .unwrap_or(0);
-                let modified_at = meta
-                    .modified()
-                    .ok()
-                    .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
-                    .map(|d| d.as_secs())
-                    .unwrap_or(0);
-                (size, is_dir, created_at, modified_at)
-            } else {
-                return Err(AppError::Io(std::io::Error::new(
-                    std::io::ErrorKind::Other,
-                    "无法读取文件元数据",
-                )));
+            let Ok(meta) = entry.metadata() else {
+                return Err(AppError::Io(std::io::Error::other("无法读取文件元数据")));
             };
-            files.push(FileInfo {
+            files.push(FileInfo::from_metadata(
                 name,
-                size,
-                is_dir,
-                created_at,
-                modified_at,
-            });
+                &entry.path(),
+                &meta,
+                &mut names,
+            ));
         }
     }
-    Ok(crate::base::Response::new(Some(files), "Success".into(), 0))
+    Ok(Response::ok(files))
+}
+
+/// 列出当前用户可以访问的存储根
+#[utoipa::path(tag = tags::FILES, responses((status = 200, body = Response<Vec<StorageRoot>>)))]
+#[get("/roots")]
+pub async fn list_roots(storage: UserStorage) -> impl Responder {
+    Response::ok(storage.roots())
 }
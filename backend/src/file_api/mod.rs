@@ -1,7 +1,29 @@
-use actix_web::web::ServiceConfig;
+use actix_multipart::form::MultipartFormConfig;
+use utoipa_actix_web::service_config::ServiceConfig;
 
+mod archive;
 pub mod file;
+mod path;
+mod perm;
+mod search;
+mod text;
+pub use archive::stop_jobs;
 
 pub fn handle(cfg: &mut ServiceConfig) {
+    // 文本编辑需要提交整个文件内容，放宽 JSON 请求体大小限制
+    cfg.app_data(crate::base::json_config().limit(text::MAX_TEXT_SIZE as usize * 2));
+    cfg.app_data(MultipartFormConfig::default().total_limit(archive::MAX_UPLOAD_SIZE));
     cfg.service(file::file_list);
+    cfg.service(file::list_roots);
+    cfg.service(text::read_text);
+    cfg.service(text::save_text);
+    cfg.service(archive::compress_files);
+    cfg.service(archive::extract_file);
+    cfg.service(archive::extract_upload);
+    cfg.service(archive::archive_entries);
+    cfg.service(archive::job_status);
+    cfg.service(perm::chmod);
+    cfg.service(perm::chown);
+    cfg.service(search::search);
+    cfg.service(search::disk_usage);
 }
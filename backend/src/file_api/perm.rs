@@ -0,0 +1,381 @@
+use actix_web::{Responder, post, web};
+use nix::unistd::{Group, User};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::ErrorKind;
+use std::os::unix::fs::{PermissionsExt, lchown};
+use std::path::Path;
+use utoipa::ToSchema;
+use walkdir::WalkDir;
+
+use super::file::{FileInfo, NameCache};
+use crate::auth_api::role::Role;
+use crate::base::Response;
+use crate::error::AppError;
+use crate::middleware::auth::CurrentUser;
+use crate::openapi::tags;
+use crate::storage::{Operation, StorageRoot, UserStorage};
+
+#[derive(Deserialize, ToSchema)]
+struct ChmodRequest {
+    root: String,
+    path: String,
+    /// 八进制权限字符串，如 "755"、"0644"、"2775"
+    mode: String,
+    #[serde(default)]
+    recursive: bool,
+}
+
+#[derive(Deserialize, ToSchema)]
+struct ChownRequest {
+    root: String,
+    path: String,
+    /// 用户名或 uid，为空表示不修改
+    owner: Option<String>,
+    /// 组名或 gid，为空表示不修改
+    group: Option<String>,
+    #[serde(default)]
+    recursive: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct PermResult {
+    /// 实际修改的文件数量
+    pub changed: u64,
+    pub file: FileInfo,
+}
+
+/// setuid、setgid 和粘滞位
+const SPECIAL_MODE_BITS: u32 = 0o7000;
+
+/// 服务以 root 运行时，非管理员可借 setuid 文件提权，特殊权限位只允许管理员设置
+fn parse_mode(mode: &str, role: Role) -> Result<u32, AppError> {
+    let digits = mode.trim().trim_start_matches("0o");
+    if digits.is_empty() || digits.len() > 4 {
+        return Err(AppError::InvalidParam(format!("无效的权限: {}", mode)));
+    }
+    let mode = u32::from_str_radix(digits, 8)
+        .map_err(|_| AppError::InvalidParam(format!("无效的权限: {}", mode)))?;
+    if mode & SPECIAL_MODE_BITS != 0 && role != Role::Admin {
+        return Err(AppError::Forbidden(
+            "只有管理员可以设置 setuid、setgid 和粘滞位".into(),
+        ));
+    }
+    Ok(mode)
+}
+
+fn lookup_uid(owner: &str) -> Result<u32, AppError> {
+    if let Ok(uid) = owner.parse::<u32>() {
+        return Ok(uid);
+    }
+    User::from_name(owner)
+        .map_err(|e| AppError::Unknown(e.to_string()))?
+        .map(|u| u.uid.as_raw())
+        .ok_or_else(|| AppError::InvalidParam(format!("用户不存在: {}", owner)))
+}
+
+fn lookup_gid(group: &str) -> Result<u32, AppError> {
+    if let Ok(gid) = group.parse::<u32>() {
+        return Ok(gid);
+    }
+    Group::from_name(group)
+        .map_err(|e| AppError::Unknown(e.to_string()))?
+        .map(|g| g.gid.as_raw())
+        .ok_or_else(|| AppError::InvalidParam(format!("用户组不存在: {}", group)))
+}
+
+/// 权限不足时给出明确提示，其它错误原样返回
+fn map_io(path: &Path, e: std::io::Error, action: &str) -> AppError {
+    if e.kind() == ErrorKind::PermissionDenied {
+        AppError::Forbidden(format!(
+            "服务进程无权{} {}，请确认运行用户的权限",
+            action,
+            path.display()
+        ))
+    } else {
+        AppError::Io(e)
+    }
+}
+
+/// 依次处理目标及（递归时）其下所有条目，不跟随符号链接
+fn apply<F>(target: &Path, recursive: bool, mut f: F) -> Result<u64, AppError>
+where
+    F: FnMut(&Path, &fs::Metadata) -> Result<bool, AppError>,
+{
+    let mut changed = 0;
+    let walker = WalkDir::new(target)
+        .follow_links(false)
+        .max_depth(if recursive { usize::MAX } else { 0 });
+    for entry in walker {
+        let entry = entry.map_err(|e| AppError::Io(e.into()))?;
+        let meta = entry.metadata().map_err(|e| AppError::Io(e.into()))?;
+        if f(entry.path(), &meta)? {
+            changed += 1;
+        }
+    }
+    Ok(changed)
+}
+
+fn file_info(target: &Path) -> Result<FileInfo, AppError> {
+    let meta = fs::symlink_metadata(target)?;
+    let name = target
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    Ok(FileInfo::from_metadata(
+        name,
+        target,
+        &meta,
+        &mut NameCache::default(),
+    ))
+}
+
+fn chmod_files(root: StorageRoot, req: ChmodRequest, role: Role) -> Result<PermResult, AppError> {
+    let mode = parse_mode(&req.mode, role)?;
+    let target = root.resolve(&req.path)?;
+    if fs::symlink_metadata(&target).is_err() {
+        return Err(AppError::NotFound("文件不存在".into()));
+    }
+    let changed = apply(&target, req.recursive, |p, meta| {
+        // chmod 会跟随符号链接，递归时跳过链接以免改到根目录之外的文件
+        if meta.file_type().is_symlink() || meta.permissions().mode() & 0o7777 == mode {
+            return Ok(false);
+        }
+        fs::set_permissions(p, fs::Permissions::from_mode(mode))
+            .map_err(|e| map_io(p, e, "修改权限"))?;
+        Ok(true)
+    })?;
+    Ok(PermResult {
+        changed,
+        file: file_info(&target)?,
+    })
+}
+
+/// 把文件改给其它用户（尤其是 root）相当于代其授权，只允许管理员操作
+fn chown_files(root: StorageRoot, req: ChownRequest, role: Role) -> Result<PermResult, AppError> {
+    use std::os::unix::fs::MetadataExt;
+    if role != Role::Admin {
+        return Err(AppError::Forbidden("只有管理员可以修改所有者".into()));
+    }
+    if req.owner.is_none() && req.group.is_none() {
+        return Err(AppError::InvalidParam("owner 和 group 至少指定一个".into()));
+    }
+    let uid = req.owner.as_deref().map(lookup_uid).transpose()?;
+    let gid = req.group.as_deref().map(lookup_gid).transpose()?;
+    let target = root.resolve(&req.path)?;
+    if fs::symlink_metadata(&target).is_err() {
+        return Err(AppError::NotFound("文件不存在".into()));
+    }
+    let changed = apply(&target, req.recursive, |p, meta| {
+        if uid.is_none_or(|u| u == meta.uid()) && gid.is_none_or(|g| g == meta.gid()) {
+            return Ok(false);
+        }
+        lchown(p, uid, gid).map_err(|e| map_io(p, e, "修改所有者"))?;
+        Ok(true)
+    })?;
+    Ok(PermResult {
+        changed,
+        file: file_info(&target)?,
+    })
+}
+
+#[utoipa::path(tag = tags::FILES, responses((status = 200, body = Response<PermResult>)))]
+#[post("/chmod")]
+pub async fn chmod(
+    req: web::Json<ChmodRequest>,
+    storage: UserStorage,
+    user: CurrentUser,
+) -> Result<impl Responder, AppError> {
+    let req = req.into_inner();
+    let root = storage.root(&req.root, Operation::Permissions)?.clone();
+    let role = user.role;
+    let result = web::block(move || chmod_files(root, req, role)).await??;
+    Ok(Response::ok(result))
+}
+
+#[utoipa::path(tag = tags::FILES, responses((status = 200, body = Response<PermResult>)))]
+#[post("/chown")]
+pub async fn chown(
+    req: web::Json<ChownRequest>,
+    storage: UserStorage,
+    user: CurrentUser,
+) -> Result<impl Responder, AppError> {
+    let req = req.into_inner();
+    let root = storage.root(&req.root, Operation::Permissions)?.clone();
+    let role = user.role;
+    let result = web::block(move || chown_files(root, req, role)).await??;
+    Ok(Response::ok(result))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_api::file::mode_string;
+    use crate::storage::TestRoot;
+
+    #[test]
+    fn test_parse_mode() {
+        let op = Role::Operator;
+        assert_eq!(parse_mode("755", op).unwrap(), 0o755);
+        assert_eq!(parse_mode("0644", op).unwrap(), 0o644);
+        assert_eq!(parse_mode("2775", Role::Admin).unwrap(), 0o2775);
+        assert!(parse_mode("789", op).is_err());
+        assert!(parse_mode("17777", Role::Admin).is_err());
+        assert!(parse_mode("", op).is_err());
+        // 非管理员不能设置特殊权限位
+        for mode in ["4755", "2775", "1777", "0o6755"] {
+            assert!(
+                matches!(parse_mode(mode, op), Err(AppError::Forbidden(_))),
+                "{}",
+                mode
+            );
+        }
+    }
+
+    #[test]
+    fn test_mode_string() {
+        assert_eq!(mode_string(0o755), "rwxr-xr-x");
+        assert_eq!(mode_string(0o640), "rw-r-----");
+        assert_eq!(mode_string(0o4755), "rwsr-xr-x");
+        assert_eq!(mode_string(0o1777), "rwxrwxrwt");
+        assert_eq!(mode_string(0o2644), "rw-r-Sr--");
+    }
+
+    fn mode(path: &Path) -> u32 {
+        fs::symlink_metadata(path).unwrap().permissions().mode() & 0o7777
+    }
+
+    fn chmod_request(path: &str, mode: &str, recursive: bool) -> ChmodRequest {
+        ChmodRequest {
+            root: "test".into(),
+            path: path.into(),
+            mode: mode.into(),
+            recursive,
+        }
+    }
+
+    #[test]
+    fn test_chmod_recursive() {
+        let root = TestRoot::new("test");
+        let dir = root.path.join("site");
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::write(dir.join("a.html"), "a").unwrap();
+        fs::write(dir.join("sub/b.css"), "b").unwrap();
+        fs::set_permissions(dir.join("a.html"), fs::Permissions::from_mode(0o600)).unwrap();
+
+        let result = chmod_files(
+            root.clone(),
+            chmod_request("site", "750", false),
+            Role::Admin,
+        )
+        .unwrap();
+        assert_eq!(result.changed, 1);
+        assert_eq!(mode(&dir), 0o750);
+        assert_eq!(mode(&dir.join("a.html")), 0o600);
+
+        let result = chmod_files(
+            root.clone(),
+            chmod_request("site", "750", true),
+            Role::Admin,
+        )
+        .unwrap();
+        // 目录本身已经是 750，不计入
+        assert_eq!(result.changed, 3);
+        for path in ["sub", "a.html", "sub/b.css"] {
+            assert_eq!(mode(&dir.join(path)), 0o750, "{}", path);
+        }
+        assert!(matches!(
+            chmod_files(
+                root.clone(),
+                chmod_request("missing", "750", true),
+                Role::Admin
+            ),
+            Err(AppError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_chmod_skips_symlinks() {
+        let root = TestRoot::new("test");
+        let outside = root.dir.join("outside.txt");
+        fs::write(&outside, "secret").unwrap();
+        fs::set_permissions(&outside, fs::Permissions::from_mode(0o600)).unwrap();
+        let dir = root.path.join("site");
+        fs::create_dir_all(&dir).unwrap();
+        std::os::unix::fs::symlink(&outside, dir.join("link")).unwrap();
+
+        let result = chmod_files(
+            root.clone(),
+            chmod_request("site", "777", true),
+            Role::Admin,
+        )
+        .unwrap();
+        assert_eq!(result.changed, 1);
+        assert_eq!(mode(&dir), 0o777);
+        assert_eq!(mode(&outside), 0o600);
+    }
+
+    /// 操作员不能把文件改给其它用户，也不能设置 setuid，避免以 root 运行时被提权
+    #[test]
+    fn test_operator_cannot_escalate() {
+        let root = TestRoot::new("test");
+        fs::write(root.path.join("a.sh"), "a").unwrap();
+        fs::set_permissions(root.path.join("a.sh"), fs::Permissions::from_mode(0o644)).unwrap();
+        let req = ChownRequest {
+            root: "test".into(),
+            path: "a.sh".into(),
+            owner: Some("0".into()),
+            group: Some("0".into()),
+            recursive: false,
+        };
+        assert!(matches!(
+            chown_files(root.clone(), req, Role::Operator),
+            Err(AppError::Forbidden(_))
+        ));
+        let res = chmod_files(
+            root.clone(),
+            chmod_request("a.sh", "4755", false),
+            Role::Operator,
+        );
+        assert!(matches!(res, Err(AppError::Forbidden(_))));
+        assert_eq!(mode(&root.path.join("a.sh")), 0o644);
+
+        let res = chmod_files(
+            root.clone(),
+            chmod_request("a.sh", "755", false),
+            Role::Operator,
+        );
+        assert_eq!(res.unwrap().changed, 1);
+        assert_eq!(mode(&root.path.join("a.sh")), 0o755);
+    }
+
+    #[test]
+    fn test_permission_denied() {
+        let path = Path::new("/srv/site");
+        let denied = std::io::Error::from(ErrorKind::PermissionDenied);
+        assert!(matches!(
+            map_io(path, denied, "修改权限"),
+            AppError::Forbidden(_)
+        ));
+        let other = std::io::Error::from(ErrorKind::NotFound);
+        assert!(matches!(map_io(path, other, "修改权限"), AppError::Io(_)));
+
+        // 非 root 运行时把文件改给 root 会被拒绝；以 root 运行时不会失败，跳过
+        if nix::unistd::geteuid().is_root() {
+            return;
+        }
+        let root = TestRoot::new("test");
+        fs::write(root.path.join("a.txt"), "a").unwrap();
+        let req = ChownRequest {
+            root: "test".into(),
+            path: "a.txt".into(),
+            owner: Some("0".into()),
+            group: None,
+            recursive: false,
+        };
+        assert!(matches!(
+            chown_files(root.clone(), req, Role::Admin),
+            Err(AppError::Forbidden(_))
+        ));
+    }
+}
@@ -0,0 +1,426 @@
+use actix_web::{Responder, get, web};
+use glob::{MatchOptions, Pattern};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::os::unix::fs::MetadataExt;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use utoipa::{IntoParams, ToSchema};
+use walkdir::WalkDir;
+
+use super::path;
+use crate::base::Response;
+use crate::error::AppError;
+use crate::openapi::tags;
+use crate::storage::{Operation, StorageRoot, UserStorage};
+
+/// 单次搜索最多返回的结果数
+const MAX_LIMIT: usize = 5000;
+const DEFAULT_LIMIT: usize = 200;
+
+/// 客户端断开时 actix 会丢弃 handler 的 future，借此通知阻塞任务停止遍历
+struct CancelOnDrop(Arc<AtomicBool>);
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+fn cancelled() -> AppError {
+    AppError::Unknown("请求已取消".into())
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct SearchQuery {
+    root: String,
+    #[serde(default)]
+    dir: String,
+    /// 文件名通配符，如 *.log、nginx*.conf
+    name: Option<String>,
+    #[serde(default)]
+    case_sensitive: bool,
+    min_size: Option<u64>,
+    max_size: Option<u64>,
+    /// 修改时间下限（秒级时间戳）
+    modified_after: Option<u64>,
+    /// 修改时间上限（秒级时间戳）
+    modified_before: Option<u64>,
+    /// file 或 dir，不填则都返回
+    file_type: Option<String>,
+    limit: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct SearchHit {
+    /// 相对于根目录的路径
+    pub path: String,
+    pub size: u64,
+    pub is_dir: bool,
+    pub modified_at: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct SearchResult {
+    pub items: Vec<SearchHit>,
+    /// 结果数达到上限后停止遍历
+    pub truncated: bool,
+    /// 遍历过程中无法访问而跳过的条目数
+    pub skipped: u64,
+}
+
+fn search_files(
+    root: StorageRoot,
+    query: SearchQuery,
+    cancel: &AtomicBool,
+) -> Result<SearchResult, AppError> {
+    let base = root.resolve(&query.dir)?;
+    if !base.is_dir() {
+        return Err(AppError::NotFound("目录不存在".into()));
+    }
+    let pattern = query
+        .name
+        .as_deref()
+        .map(Pattern::new)
+        .transpose()
+        .map_err(|e| AppError::InvalidParam(format!("无效的通配符: {}", e)))?;
+    let options = MatchOptions {
+        case_sensitive: query.case_sensitive,
+        ..Default::default()
+    };
+    let want_dir = match query.file_type.as_deref() {
+        None => None,
+        Some("file") => Some(false),
+        Some("dir") => Some(true),
+        Some(other) => return Err(AppError::InvalidParam(format!("无效的类型: {}", other))),
+    };
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+
+    let mut result = SearchResult {
+        items: Vec::new(),
+        truncated: false,
+        skipped: 0,
+    };
+    for entry in WalkDir::new(&base).min_depth(1).follow_links(false) {
+        if cancel.load(Ordering::Relaxed) {
+            return Err(cancelled());
+        }
+        let Ok(entry) = entry else {
+            result.skipped += 1;
+            continue;
+        };
+        if let Some(pattern) = &pattern
+            && !pattern.matches_with(&entry.file_name().to_string_lossy(), options)
+        {
+            continue;
+        }
+        let Ok(meta) = entry.metadata() else {
+            result.skipped += 1;
+            continue;
+        };
+        let is_dir = meta.is_dir();
+        if want_dir.is_some_and(|d| d != is_dir) {
+            continue;
+        }
+        let size = if meta.is_file() { meta.len() } else { 0 };
+        if query.min_size.is_some_and(|min| size < min)
+            || query.max_size.is_some_and(|max| size > max)
+        {
+            continue;
+        }
+        let modified_at = path::modified_secs(&meta);
+        if query.modified_after.is_some_and(|t| modified_at < t)
+            || query.modified_before.is_some_and(|t| modified_at > t)
+        {
+            continue;
+        }
+        if result.items.len() >= limit {
+            result.truncated = true;
+            break;
+        }
+        result.items.push(SearchHit {
+            path: root.relative(entry.path()),
+            size,
+            is_dir,
+            modified_at,
+        });
+    }
+    Ok(result)
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct UsageQuery {
+    root: String,
+    #[serde(default)]
+    dir: String,
+    /// 类似 du -x，不跨越文件系统
+    #[serde(default)]
+    one_file_system: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct DirUsage {
+    pub name: String,
+    pub is_dir: bool,
+    /// 文件内容的字节数之和
+    pub apparent_size: u64,
+    /// 实际占用的磁盘空间（按 512 字节块计算），与 du 一致
+    pub disk_usage: u64,
+    pub files: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct UsageResult {
+    /// 按 disk_usage 从大到小排列
+    pub children: Vec<DirUsage>,
+    pub apparent_size: u64,
+    pub disk_usage: u64,
+    pub skipped: u64,
+}
+
+fn dir_usage(
+    root: StorageRoot,
+    query: UsageQuery,
+    cancel: &AtomicBool,
+) -> Result<UsageResult, AppError> {
+    let base = root.resolve(&query.dir)?;
+    if !base.is_dir() {
+        return Err(AppError::NotFound("目录不存在".into()));
+    }
+    // 硬链接只统计一次
+    let mut seen = HashSet::new();
+    let mut skipped = 0;
+    let mut children = Vec::new();
+    for child in fs::read_dir(&base)? {
+        let Ok(child) = child else {
+            skipped += 1;
+            continue;
+        };
+        let mut usage = DirUsage {
+            name: child.file_name().to_string_lossy().to_string(),
+            is_dir: child.file_type().is_ok_and(|t| t.is_dir()),
+            apparent_size: 0,
+            disk_usage: 0,
+            files: 0,
+        };
+        let walker = WalkDir::new(child.path())
+            .follow_links(false)
+            .same_file_system(query.one_file_system);
+        for entry in walker {
+            if cancel.load(Ordering::Relaxed) {
+                return Err(cancelled());
+            }
+            let Some(meta) = entry.ok().and_then(|e| e.metadata().ok()) else {
+                skipped += 1;
+                continue;
+            };
+            if meta.nlink() > 1 && !meta.is_dir() && !seen.insert((meta.dev(), meta.ino())) {
+                continue;
+            }
+            usage.disk_usage += meta.blocks() * 512;
+            if meta.is_file() {
+                usage.apparent_size += meta.len();
+                usage.files += 1;
+            }
+        }
+        children.push(usage);
+    }
+    children.sort_by_key(|c| std::cmp::Reverse(c.disk_usage));
+    Ok(UsageResult {
+        apparent_size: children.iter().map(|c| c.apparent_size).sum(),
+        disk_usage: children.iter().map(|c| c.disk_usage).sum(),
+        children,
+        skipped,
+    })
+}
+
+#[utoipa::path(
+    tag = tags::FILES,
+    params(SearchQuery),
+    responses((status = 200, body = Response<SearchResult>))
+)]
+#[get("/search")]
+pub async fn search(
+    query: web::Query<SearchQuery>,
+    storage: UserStorage,
+) -> Result<impl Responder, AppError> {
+    let query = query.into_inner();
+    let root = storage.root(&query.root, Operation::Search)?.clone();
+    let cancel = Arc::new(AtomicBool::new(false));
+    let _guard = CancelOnDrop(cancel.clone());
+    let result = web::block(move || search_files(root, query, &cancel)).await??;
+    Ok(Response::ok(result))
+}
+
+#[utoipa::path(
+    tag = tags::FILES,
+    params(UsageQuery),
+    responses((status = 200, body = Response<UsageResult>))
+)]
+#[get("/usage")]
+pub async fn disk_usage(
+    query: web::Query<UsageQuery>,
+    storage: UserStorage,
+) -> Result<impl Responder, AppError> {
+    let query = query.into_inner();
+    let root = storage.root(&query.root, Operation::Search)?.clone();
+    let cancel = Arc::new(AtomicBool::new(false));
+    let _guard = CancelOnDrop(cancel.clone());
+    let result = web::block(move || dir_usage(root, query, &cancel)).await??;
+    Ok(Response::ok(result))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::TestRoot;
+    use std::path::PathBuf;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    fn write(path: PathBuf, size: usize, modified_at: u64) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, vec![b'x'; size]).unwrap();
+        fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(UNIX_EPOCH + Duration::from_secs(modified_at))
+            .unwrap();
+    }
+
+    fn query(name: Option<&str>) -> SearchQuery {
+        SearchQuery {
+            root: "test".into(),
+            dir: String::new(),
+            name: name.map(String::from),
+            case_sensitive: false,
+            min_size: None,
+            max_size: None,
+            modified_after: None,
+            modified_before: None,
+            file_type: None,
+            limit: None,
+        }
+    }
+
+    fn paths(result: &SearchResult) -> Vec<&str> {
+        let mut paths: Vec<_> = result.items.iter().map(|h| h.path.as_str()).collect();
+        paths.sort();
+        paths
+    }
+
+    #[test]
+    fn test_search_filters() {
+        let root = TestRoot::new("test");
+        write(root.path.join("logs/app.log"), 100, 1_000);
+        write(root.path.join("logs/old/APP.LOG"), 5_000, 2_000);
+        write(root.path.join("nginx.conf"), 10, 3_000);
+        let cancel = AtomicBool::new(false);
+
+        let result = search_files(root.clone(), query(Some("*.log")), &cancel).unwrap();
+        assert_eq!(paths(&result), ["logs/app.log", "logs/old/APP.LOG"]);
+        assert!(!result.truncated);
+        let mut q = query(Some("*.log"));
+        q.case_sensitive = true;
+        let result = search_files(root.clone(), q, &cancel).unwrap();
+        assert_eq!(paths(&result), ["logs/app.log"]);
+
+        let mut q = query(None);
+        q.min_size = Some(50);
+        q.max_size = Some(1_000);
+        let result = search_files(root.clone(), q, &cancel).unwrap();
+        assert_eq!(paths(&result), ["logs/app.log"]);
+
+        let mut q = query(None);
+        q.file_type = Some("file".into());
+        q.modified_after = Some(1_500);
+        q.modified_before = Some(2_500);
+        let result = search_files(root.clone(), q, &cancel).unwrap();
+        assert_eq!(paths(&result), ["logs/old/APP.LOG"]);
+
+        let mut q = query(None);
+        q.file_type = Some("dir".into());
+        let result = search_files(root.clone(), q, &cancel).unwrap();
+        assert_eq!(paths(&result), ["logs", "logs/old"]);
+
+        let mut q = query(None);
+        q.file_type = Some("link".into());
+        assert!(matches!(
+            search_files(root.clone(), q, &cancel),
+            Err(AppError::InvalidParam(_))
+        ));
+    }
+
+    #[test]
+    fn test_search_limit() {
+        let root = TestRoot::new("test");
+        for i in 0..5 {
+            write(root.path.join(format!("{}.txt", i)), 1, 0);
+        }
+        let cancel = AtomicBool::new(false);
+        let mut q = query(None);
+        q.limit = Some(3);
+        let result = search_files(root.clone(), q, &cancel).unwrap();
+        assert_eq!(result.items.len(), 3);
+        assert!(result.truncated);
+        // 结果数正好等于上限时不算截断
+        let mut q = query(None);
+        q.limit = Some(5);
+        let result = search_files(root.clone(), q, &cancel).unwrap();
+        assert_eq!(result.items.len(), 5);
+        assert!(!result.truncated);
+    }
+
+    #[test]
+    fn test_dir_usage() {
+        let root = TestRoot::new("test");
+        write(root.path.join("a/big.bin"), 10_000, 0);
+        write(root.path.join("a/sub/small.txt"), 100, 0);
+        write(root.path.join("c.txt"), 50, 0);
+        fs::create_dir_all(root.path.join("b")).unwrap();
+        // b 中的硬链接与 a/big.bin 是同一个文件，只统计一次
+        fs::hard_link(root.path.join("a/big.bin"), root.path.join("b/big.bin")).unwrap();
+        let q = UsageQuery {
+            root: "test".into(),
+            dir: String::new(),
+            one_file_system: true,
+        };
+        let result = dir_usage(root.clone(), q, &AtomicBool::new(false)).unwrap();
+
+        assert_eq!(result.apparent_size, 10_150);
+        assert_eq!(result.children.iter().map(|c| c.files).sum::<u64>(), 3);
+        let child = |name: &str| result.children.iter().find(|c| c.name == name).unwrap();
+        assert_eq!(child("a").apparent_size + child("b").apparent_size, 10_100);
+        assert_eq!(child("c.txt").apparent_size, 50);
+        assert!(child("a").is_dir && !child("c.txt").is_dir);
+        assert!(
+            result
+                .children
+                .windows(2)
+                .all(|w| w[0].disk_usage >= w[1].disk_usage)
+        );
+        assert_eq!(
+            result.disk_usage,
+            result.children.iter().map(|c| c.disk_usage).sum::<u64>()
+        );
+    }
+
+    #[test]
+    fn test_cancel() {
+        let root = TestRoot::new("test");
+        write(root.path.join("a.txt"), 1, 0);
+        let cancel = Arc::new(AtomicBool::new(false));
+        drop(CancelOnDrop(cancel.clone()));
+        assert!(cancel.load(Ordering::Relaxed));
+        assert!(search_files(root.clone(), query(None), &cancel).is_err());
+        let q = UsageQuery {
+            root: "test".into(),
+            dir: String::new(),
+            one_file_system: false,
+        };
+        assert!(dir_usage(root.clone(), q, &cancel).is_err());
+    }
+}
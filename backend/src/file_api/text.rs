@@ -0,0 +1,458 @@
+use actix_web::{Responder, get, put, web};
+use encoding_rs::{Encoding, UTF_8, UTF_16BE, UTF_16LE};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+use utoipa::{IntoParams, ToSchema};
+
+use super::path;
+use crate::base::Response;
+use crate::error::AppError;
+use crate::openapi::tags;
+use crate::storage::{Operation, StorageRoot, UserStorage};
+
+/// 在线编辑允许打开的最大文件大小
+pub const MAX_TEXT_SIZE: u64 = 2 * 1024 * 1024;
+
+/// 串行化“校验前置条件 + 替换文件”，避免两个保存请求同时通过校验
+static WRITE_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct ReadQuery {
+    root: String,
+    path: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct TextFile {
+    pub path: String,
+    pub content: String,
+    /// 检测到的编码名称，如 UTF-8、GBK、UTF-16LE
+    pub encoding: String,
+    /// 原文件是否带 BOM
+    pub bom: bool,
+    pub size: u64,
+    pub modified_at: u64,
+    /// 纳秒级修改时间，保存时可作为前置条件回传
+    pub modified_at_ns: u64,
+    /// 文件内容的 SHA-256，保存时作为前置条件回传
+    pub hash: String,
+}
+
+#[derive(Deserialize, ToSchema)]
+struct SaveRequest {
+    root: String,
+    path: String,
+    content: String,
+    encoding: Option<String>,
+    #[serde(default)]
+    bom: bool,
+    /// 读取时拿到的 hash，文件已被他人修改时返回冲突；覆盖已有文件时与 expected_mtime_ns 至少提供一个
+    expected_hash: Option<String>,
+    /// 读取时拿到的 modified_at_ns，作用同 expected_hash，两者都提供时只比较 hash
+    expected_mtime_ns: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct SaveResult {
+    pub size: u64,
+    pub modified_at: u64,
+    pub modified_at_ns: u64,
+    pub hash: String,
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    hex::encode(Sha256::digest(bytes))
+}
+
+/// 检测编码并解码，返回 (内容, 编码, 是否带 BOM)
+fn decode(bytes: &[u8]) -> Result<(String, &'static Encoding, bool), AppError> {
+    if let Some((encoding, bom_len)) = Encoding::for_bom(bytes) {
+        let (content, had_errors) = encoding.decode_without_bom_handling(&bytes[bom_len..]);
+        if had_errors {
+            return Err(AppError::InvalidParam("文件不是有效的文本".into()));
+        }
+        return Ok((content.into_owned(), encoding, true));
+    }
+    // 没有 BOM 时出现 NUL 基本可以认定是二进制文件
+    if bytes.contains(&0) {
+        return Err(AppError::InvalidParam("无法编辑二进制文件".into()));
+    }
+    let encoding = if std::str::from_utf8(bytes).is_ok() {
+        UTF_8
+    } else {
+        let mut detector = chardetng::EncodingDetector::new();
+        detector.feed(bytes, true);
+        detector.guess(None, true)
+    };
+    match encoding.decode_without_bom_handling_and_without_replacement(bytes) {
+        Some(content) => Ok((content.into_owned(), encoding, false)),
+        None => Err(AppError::InvalidParam("文件不是有效的文本".into())),
+    }
+}
+
+/// 按指定编码编码文本，encoding_rs 不支持输出 UTF-16，需要单独处理
+fn encode(content: &str, encoding: &'static Encoding, bom: bool) -> Result<Vec<u8>, AppError> {
+    let mut out = Vec::new();
+    if encoding == UTF_16LE || encoding == UTF_16BE {
+        let le = encoding == UTF_16LE;
+        let units = if bom {
+            std::iter::once(0xFEFF)
+                .chain(content.encode_utf16())
+                .collect::<Vec<u16>>()
+        } else {
+            content.encode_utf16().collect()
+        };
+        for unit in units {
+            out.extend_from_slice(&if le {
+                unit.to_le_bytes()
+            } else {
+                unit.to_be_bytes()
+            });
+        }
+        return Ok(out);
+    }
+    if bom && encoding == UTF_8 {
+        out.extend_from_slice(b"\xEF\xBB\xBF");
+    }
+    let (bytes, _, had_errors) = encoding.encode(content);
+    if had_errors {
+        return Err(AppError::InvalidParam(format!(
+            "内容包含 {} 无法表示的字符",
+            encoding.name()
+        )));
+    }
+    out.extend_from_slice(&bytes);
+    Ok(out)
+}
+
+fn read_text_file(root: StorageRoot, rel: String) -> Result<TextFile, AppError> {
+    let file_path = root.resolve(&rel)?;
+    if !file_path.is_file() {
+        return Err(AppError::NotFound("文件不存在".into()));
+    }
+    let meta = fs::metadata(&file_path)?;
+    if meta.len() > MAX_TEXT_SIZE {
+        return Err(AppError::PayloadTooLarge(format!(
+            "文件超过 {} 字节，无法在线编辑",
+            MAX_TEXT_SIZE
+        )));
+    }
+    let bytes = fs::read(&file_path)?;
+    let (content, encoding, bom) = decode(&bytes)?;
+    Ok(TextFile {
+        path: rel,
+        content,
+        encoding: encoding.name().to_string(),
+        bom,
+        size: bytes.len() as u64,
+        modified_at: path::modified_secs(&meta),
+        modified_at_ns: path::modified_nanos(&meta),
+        hash: sha256_hex(&bytes),
+    })
+}
+
+/// 流式计算文件的 SHA-256，不把整个文件读入内存
+fn sha256_file(file_path: &Path) -> Result<String, AppError> {
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut fs::File::open(file_path)?, &mut hasher)?;
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// 先写同目录下的临时文件，再 rename 覆盖，保证不会留下写了一半的文件；失败时原文件不变。
+/// 目标是符号链接时替换链接指向的文件，链接本身保留；覆盖已有文件时沿用其权限和所有者
+fn replace_file(
+    file_path: &Path,
+    bytes: &[u8],
+    current: Option<&fs::Metadata>,
+) -> Result<(), AppError> {
+    use std::os::unix::fs::{MetadataExt, fchown};
+    let real_path;
+    let file_path = match fs::symlink_metadata(file_path) {
+        Ok(meta) if meta.file_type().is_symlink() => {
+            real_path = fs::canonicalize(file_path)?;
+            real_path.as_path()
+        }
+        _ => file_path,
+    };
+    let dir = file_path
+        .parent()
+        .filter(|d| d.is_dir())
+        .ok_or_else(|| AppError::NotFound("目录不存在".into()))?;
+    let file_name = file_path
+        .file_name()
+        .ok_or_else(|| AppError::InvalidParam("缺少文件名".into()))?
+        .to_string_lossy()
+        .to_string();
+    let tmp_path = dir.join(format!(".{}.{}.tmp", file_name, uuid::Uuid::new_v4()));
+    let write = || -> std::io::Result<()> {
+        let mut tmp = fs::File::create(&tmp_path)?;
+        tmp.write_all(bytes)?;
+        if let Some(meta) = current {
+            tmp.set_permissions(meta.permissions())?;
+            fchown(&tmp, Some(meta.uid()), Some(meta.gid()))?;
+        }
+        tmp.sync_all()?;
+        fs::rename(&tmp_path, file_path)
+    };
+    if let Err(e) = write() {
+        let _ = fs::remove_file(&tmp_path);
+        return Err(e.into());
+    }
+    Ok(())
+}
+
+fn save_text_file(root: StorageRoot, req: SaveRequest) -> Result<SaveResult, AppError> {
+    let file_path = root.resolve(&req.path)?;
+    let encoding = match &req.encoding {
+        Some(label) => Encoding::for_label(label.as_bytes())
+            .ok_or_else(|| AppError::InvalidParam(format!("不支持的编码: {}", label)))?,
+        None => UTF_8,
+    };
+    let bytes = encode(&req.content, encoding, req.bom)?;
+    if bytes.len() as u64 > MAX_TEXT_SIZE {
+        return Err(AppError::PayloadTooLarge(format!(
+            "内容超过 {} 字节",
+            MAX_TEXT_SIZE
+        )));
+    }
+
+    let _guard = WRITE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let current = match fs::metadata(&file_path) {
+        Ok(meta) if meta.is_file() => Some(meta),
+        Ok(_) => return Err(AppError::InvalidParam("目标不是文件".into())),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(e) => return Err(e.into()),
+    };
+    // 覆盖已有文件必须带前置条件，新建文件时不能带；hash 优先，不受修改时间精度影响
+    let modified = match (&current, &req.expected_hash, req.expected_mtime_ns) {
+        (None, None, None) => false,
+        (None, _, _) => true,
+        // 超过在线编辑上限的文件读取接口不会返回 hash，不必计算
+        (Some(meta), Some(expected), _) => {
+            meta.len() > MAX_TEXT_SIZE || sha256_file(&file_path)? != *expected
+        }
+        (Some(meta), None, Some(expected)) => path::modified_nanos(meta) != expected,
+        (Some(_), None, None) => {
+            return Err(AppError::Conflict(
+                "文件已存在，覆盖时需提供 expected_hash 或 expected_mtime_ns".into(),
+            ));
+        }
+    };
+    if modified {
+        return Err(AppError::Conflict(match current {
+            Some(_) => "文件已被修改，请重新加载".into(),
+            None => "文件已被删除".into(),
+        }));
+    }
+    replace_file(&file_path, &bytes, current.as_ref())?;
+
+    let meta = fs::metadata(&file_path)?;
+    Ok(SaveResult {
+        size: meta.len(),
+        modified_at: path::modified_secs(&meta),
+        modified_at_ns: path::modified_nanos(&meta),
+        hash: sha256_hex(&bytes),
+    })
+}
+
+#[utoipa::path(
+    tag = tags::FILES,
+    params(ReadQuery),
+    responses((status = 200, body = Response<TextFile>))
+)]
+#[get("/text")]
+pub async fn read_text(
+    query: web::Query<ReadQuery>,
+    storage: UserStorage,
+) -> Result<impl Responder, AppError> {
+    let query = query.into_inner();
+    let root = storage.root(&query.root, Operation::Read)?.clone();
+    let file = web::block(move || read_text_file(root, query.path)).await??;
+    Ok(Response::ok(file))
+}
+
+#[utoipa::path(tag = tags::FILES, responses((status = 200, body = Response<SaveResult>)))]
+#[put("/text")]
+pub async fn save_text(
+    req: web::Json<SaveRequest>,
+    storage: UserStorage,
+) -> Result<impl Responder, AppError> {
+    let req = req.into_inner();
+    let root = storage.root(&req.root, Operation::Write)?.clone();
+    let result = web::block(move || save_text_file(root, req)).await??;
+    Ok(Response::ok(result))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::TestRoot;
+
+    #[test]
+    fn test_decode_utf8_and_gbk() {
+        let (content, encoding, bom) = decode("你好, world".as_bytes()).unwrap();
+        assert_eq!(content, "你好, world");
+        assert_eq!(encoding, UTF_8);
+        assert!(!bom);
+
+        let (gbk, _, _) = encoding_rs::GBK.encode("服务器配置文件，监听端口");
+        let (content, _, _) = decode(&gbk).unwrap();
+        assert_eq!(content, "服务器配置文件，监听端口");
+    }
+
+    #[test]
+    fn test_utf16_roundtrip() {
+        let bytes = encode("key = 值", UTF_16LE, true).unwrap();
+        let (content, encoding, bom) = decode(&bytes).unwrap();
+        assert_eq!(content, "key = 值");
+        assert_eq!(encoding, UTF_16LE);
+        assert!(bom);
+    }
+
+    #[test]
+    fn test_reject_binary() {
+        assert!(decode(&[0x7f, b'E', b'L', b'F', 0, 0, 1]).is_err());
+    }
+
+    fn save(
+        root: &StorageRoot,
+        content: &str,
+        expected_hash: Option<&str>,
+        expected_mtime_ns: Option<u64>,
+    ) -> Result<SaveResult, AppError> {
+        save_text_file(
+            root.clone(),
+            SaveRequest {
+                root: "test".into(),
+                path: "a.conf".into(),
+                content: content.into(),
+                encoding: None,
+                bom: false,
+                expected_hash: expected_hash.map(String::from),
+                expected_mtime_ns,
+            },
+        )
+    }
+
+    #[test]
+    fn test_save_preconditions() {
+        let root = TestRoot::new("test");
+        // 新建文件不需要前置条件，带前置条件说明文件本应存在
+        assert!(matches!(
+            save(&root, "x", Some("abc"), None),
+            Err(AppError::Conflict(_))
+        ));
+        let created = save(&root, "port = 80", None, None).unwrap();
+        assert!(matches!(
+            save(&root, "port = 81", None, None),
+            Err(AppError::Conflict(_))
+        ));
+
+        let file = read_text_file(root.clone(), "a.conf".into()).unwrap();
+        assert_eq!(file.hash, created.hash);
+        assert_eq!(file.modified_at_ns, created.modified_at_ns);
+        // 同一秒内的另一次写入：秒级时间不变，纳秒级时间和 hash 都能发现
+        let path = root.path.join("a.conf");
+        fs::write(&path, "port = 8080").unwrap();
+        let stale =
+            std::time::UNIX_EPOCH + std::time::Duration::from_nanos(file.modified_at_ns + 1);
+        fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(stale)
+            .unwrap();
+        assert!(matches!(
+            save(&root, "port = 81", Some(&file.hash), None),
+            Err(AppError::Conflict(_))
+        ));
+        assert!(matches!(
+            save(&root, "port = 81", None, Some(file.modified_at_ns)),
+            Err(AppError::Conflict(_))
+        ));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "port = 8080");
+
+        let file = read_text_file(root.clone(), "a.conf".into()).unwrap();
+        let saved = save(&root, "port = 81", None, Some(file.modified_at_ns)).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "port = 81");
+        save(&root, "port = 82", Some(&saved.hash), None).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "port = 82");
+
+        // 超过大小上限的文件不读入内存计算 hash，直接视为已修改
+        let big = vec![b'a'; MAX_TEXT_SIZE as usize + 1];
+        fs::write(&path, &big).unwrap();
+        assert!(matches!(
+            save(&root, "x", Some(&sha256_hex(&big)), None),
+            Err(AppError::Conflict(_))
+        ));
+    }
+
+    /// 保存符号链接时写入链接指向的文件，链接、权限和所有者都保持不变
+    #[cfg(unix)]
+    #[test]
+    fn test_replace_file_keeps_link_and_owner() {
+        use std::os::unix::fs::{MetadataExt, PermissionsExt, chown};
+        let root = TestRoot::new("test");
+        let real = root.path.join("conf/app.conf");
+        fs::create_dir_all(real.parent().unwrap()).unwrap();
+        fs::write(&real, "old").unwrap();
+        fs::set_permissions(&real, fs::Permissions::from_mode(0o640)).unwrap();
+        // 以 root 运行时才能把文件改给其它用户，否则保持当前用户
+        let owner = if nix::unistd::geteuid().is_root() {
+            chown(&real, Some(1234), Some(5678)).unwrap();
+            (1234, 5678)
+        } else {
+            let meta = fs::metadata(&real).unwrap();
+            (meta.uid(), meta.gid())
+        };
+        let link = root.path.join("a.conf");
+        std::os::unix::fs::symlink("conf/app.conf", &link).unwrap();
+
+        let current = fs::metadata(&link).unwrap();
+        replace_file(&link, b"new", Some(&current)).unwrap();
+        assert!(
+            fs::symlink_metadata(&link)
+                .unwrap()
+                .file_type()
+                .is_symlink()
+        );
+        assert_eq!(fs::read_to_string(&real).unwrap(), "new");
+        let meta = fs::metadata(&real).unwrap();
+        assert_eq!(meta.permissions().mode() & 0o7777, 0o640);
+        assert_eq!((meta.uid(), meta.gid()), owner);
+        // 临时文件写在链接目标所在的目录
+        assert_eq!(fs::read_dir(real.parent().unwrap()).unwrap().count(), 1);
+        assert_eq!(fs::read_dir(&root.path).unwrap().count(), 2);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_replace_file_atomic() {
+        use std::os::unix::fs::MetadataExt;
+        let root = TestRoot::new("test");
+        let path = root.path.join("a.conf");
+        fs::write(&path, "old").unwrap();
+        let inode = fs::metadata(&path).unwrap().ino();
+        replace_file(&path, b"new", None).unwrap();
+        // 写入的是新文件，改名覆盖原文件，而不是原地截断重写
+        assert_ne!(fs::metadata(&path).unwrap().ino(), inode);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+
+        // 改名失败时目标不变，临时文件被清理
+        let dir = root.path.join("conf.d");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("keep"), "keep").unwrap();
+        assert!(replace_file(&dir, b"new", None).is_err());
+        assert_eq!(fs::read_to_string(dir.join("keep")).unwrap(), "keep");
+        let names: Vec<_> = fs::read_dir(&root.path)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        assert!(names.iter().all(|n| !n.ends_with(".tmp")), "{:?}", names);
+    }
+}
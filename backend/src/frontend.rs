@@ -0,0 +1,246 @@
+// 前端单页应用：默认使用编译时打包进程序的 web/dist，配置 web.dir 时改为从目录读取
+use actix_web::http::Method;
+use actix_web::http::header::{self, EntityTag, Header, IfNoneMatch};
+use actix_web::web::Bytes;
+use actix_web::{HttpRequest, HttpResponse, web};
+use log::warn;
+use rust_embed::RustEmbed;
+use sha2::{Digest, Sha256};
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
+
+use crate::config::Config;
+use crate::error::AppError;
+use crate::storage;
+use crate::{NO_ROUTE, ServerError};
+
+/// 构建时 web/dist 不存在（没有构建前端）时为空
+#[derive(RustEmbed)]
+#[folder = "../web/dist"]
+#[allow_missing = true]
+struct Embedded;
+
+const INDEX: &str = "index.html";
+/// 构建工具输出到该目录的文件名带内容哈希，可以长期缓存
+const HASHED_DIR: &str = "assets/";
+
+/// 前端文件的来源
+#[derive(Debug)]
+pub enum Frontend {
+    /// features.web 关闭
+    Disabled,
+    /// 编译时打包的文件
+    Embedded,
+    /// web.dir 指定的目录，每次请求时读取
+    Dir(PathBuf),
+}
+
+struct Asset {
+    data: Cow<'static, [u8]>,
+    hash: [u8; 32],
+}
+
+impl Frontend {
+    pub fn from_config(config: &Config) -> Result<Frontend, ServerError> {
+        if !config.features.web {
+            return Ok(Frontend::Disabled);
+        }
+        let Some(dir) = &config.web.dir else {
+            if Embedded::get(INDEX).is_none() {
+                warn!(
+                    "No frontend bundle embedded, build web/dist before compiling to serve the panel"
+                );
+            }
+            return Ok(Frontend::Embedded);
+        };
+        let dir = dir
+            .canonicalize()
+            .map_err(|e| ServerError::Config(format!("web.dir {}: {}", dir.display(), e)))?;
+        if !dir.is_dir() {
+            return Err(ServerError::Config(format!(
+                "web.dir is not a directory: {}",
+                dir.display()
+            )));
+        }
+        Ok(Frontend::Dir(dir))
+    }
+
+    /// path 为去掉开头 / 的相对路径
+    async fn get(&self, path: &str) -> Result<Option<Asset>, AppError> {
+        match self {
+            Frontend::Disabled => Ok(None),
+            Frontend::Embedded => Ok(Embedded::get(path).map(|file| Asset {
+                hash: file.metadata.sha256_hash(),
+                data: file.data,
+            })),
+            Frontend::Dir(dir) => {
+                let Ok(file) = storage::resolve(dir, path) else {
+                    return Ok(None);
+                };
+                Ok(web::block(move || -> Result<Option<Asset>, AppError> {
+                    if !file.is_file() {
+                        return Ok(None);
+                    }
+                    let data = std::fs::read(&file)?;
+                    Ok(Some(Asset {
+                        hash: Sha256::digest(&data).into(),
+                        data: data.into(),
+                    }))
+                })
+                .await??)
+            }
+        }
+    }
+
+    /// 带哈希的文件长期缓存；index.html 等其它文件每次通过 ETag 向服务器确认，
+    /// 从目录读取时文件随时会变，一律不长期缓存
+    fn cache_control(&self, path: &str) -> &'static str {
+        match self {
+            Frontend::Embedded if path.starts_with(HASHED_DIR) => {
+                "public, max-age=31536000, immutable"
+            }
+            _ => "no-cache",
+        }
+    }
+}
+
+/// 接口路径下没有匹配的路由时不回退到前端页面
+fn is_api(path: &str) -> bool {
+    path == "/v1" || path.starts_with("/v1/") || path.starts_with("/s/")
+}
+
+/// 最后一段带扩展名的视为请求文件，找不到时返回 404 而不是 index.html
+fn is_file_name(path: &str) -> bool {
+    path.rsplit('/')
+        .next()
+        .is_some_and(|name| name.contains('.'))
+}
+
+/// 没有匹配到接口的请求：GET/HEAD 返回前端文件，路径不是文件名时回退到 index.html 由前端路由处理；
+/// 接口路径和其它方法仍返回统一格式的 404
+pub async fn serve(
+    req: HttpRequest,
+    frontend: web::Data<Frontend>,
+) -> Result<HttpResponse, AppError> {
+    let not_found = || AppError::NotFound(NO_ROUTE.into());
+    if !matches!(*req.method(), Method::GET | Method::HEAD) || is_api(req.path()) {
+        return Err(not_found());
+    }
+    let path = req.path().trim_start_matches('/');
+    let (path, asset) = match frontend.get(path).await? {
+        Some(asset) => (path, asset),
+        None if !is_file_name(path) => (INDEX, frontend.get(INDEX).await?.ok_or_else(not_found)?),
+        None => return Err(not_found()),
+    };
+
+    let etag = EntityTag::new_strong(hex::encode(asset.hash));
+    let not_modified = match IfNoneMatch::parse(&req) {
+        Ok(IfNoneMatch::Any) => true,
+        Ok(IfNoneMatch::Items(tags)) => tags.iter().any(|tag| tag.weak_eq(&etag)),
+        Err(_) => false,
+    };
+    let mut response = if not_modified {
+        HttpResponse::NotModified()
+    } else {
+        HttpResponse::Ok()
+    };
+    response
+        .insert_header(header::ETag(etag))
+        .insert_header((header::CACHE_CONTROL, frontend.cache_control(path)))
+        .insert_header((header::X_CONTENT_TYPE_OPTIONS, "nosniff"));
+    if not_modified {
+        return Ok(response.finish());
+    }
+    let ext = Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default();
+    let body = match asset.data {
+        Cow::Borrowed(data) => Bytes::from_static(data),
+        Cow::Owned(data) => Bytes::from(data),
+    };
+    Ok(response
+        .content_type(actix_files::file_extension_to_mime(ext))
+        .body(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::App;
+    use actix_web::dev::Service;
+    use actix_web::http::StatusCode;
+    use actix_web::test::{TestRequest, call_service, init_service, read_body};
+
+    #[actix_web::test]
+    async fn test_serve_dir() {
+        let dir = std::env::temp_dir().join(format!("rpanel-web-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("assets")).unwrap();
+        std::fs::write(dir.join(INDEX), "<html></html>").unwrap();
+        std::fs::write(dir.join("assets/app.js"), "console.log(1)").unwrap();
+        let app = init_service(
+            App::new()
+                .app_data(web::Data::new(Frontend::Dir(dir.clone())))
+                .default_service(web::route().to(serve)),
+        )
+        .await;
+
+        let res = call_service(&app, TestRequest::get().uri("/").to_request()).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(
+            res.headers().get(header::CONTENT_TYPE).unwrap(),
+            "text/html"
+        );
+        assert_eq!(
+            res.headers().get(header::CACHE_CONTROL).unwrap(),
+            "no-cache"
+        );
+        let etag = res.headers().get(header::ETAG).unwrap().clone();
+        assert_eq!(read_body(res).await, "<html></html>");
+
+        let req = TestRequest::get().uri("/assets/app.js").to_request();
+        let res = call_service(&app, req).await;
+        assert_eq!(
+            res.headers().get(header::CONTENT_TYPE).unwrap(),
+            "text/javascript"
+        );
+        assert_eq!(read_body(res).await, "console.log(1)");
+
+        // 前端路由的路径返回 index.html，未修改时返回 304
+        let req = TestRequest::get()
+            .uri("/files/docs?root=files")
+            .insert_header((header::IF_NONE_MATCH, etag))
+            .to_request();
+        let res = call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+
+        for req in [
+            TestRequest::get().uri("/assets/missing.js"),
+            TestRequest::get().uri("/v1/no/such/api"),
+            TestRequest::get().uri("/s/abc"),
+            TestRequest::post().uri("/files"),
+        ] {
+            let res = app.call(req.to_request()).await;
+            let status = match res {
+                Ok(res) => res.status(),
+                Err(e) => e.as_response_error().status_code(),
+            };
+            assert_eq!(status, StatusCode::NOT_FOUND);
+        }
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_cache_control() {
+        let embedded = Frontend::Embedded;
+        assert_eq!(
+            embedded.cache_control("assets/index-3f2a1b.js"),
+            "public, max-age=31536000, immutable"
+        );
+        assert_eq!(embedded.cache_control(INDEX), "no-cache");
+        assert_eq!(
+            Frontend::Dir(PathBuf::new()).cache_control("assets/index-3f2a1b.js"),
+            "no-cache"
+        );
+    }
+}
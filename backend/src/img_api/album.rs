@@ -0,0 +1,473 @@
+use actix_web::{Responder, delete, get, post, put, web};
+use chrono::{NaiveDateTime, Utc};
+use diesel::dsl::count_star;
+use diesel::prelude::*;
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use utoipa::ToSchema;
+
+use super::api::RootQuery;
+use super::model::{Album, NewAlbum, NewAlbumImage};
+use crate::base::Response;
+use crate::db::Database;
+use crate::error::AppError;
+use crate::openapi::tags;
+use crate::schema::{album_images, albums, images};
+use crate::storage::{Operation, UserStorage};
+
+const MAX_NAME_LEN: usize = 100;
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+struct AlbumInfo {
+    #[serde(flatten)]
+    album: Album,
+    image_count: i64,
+}
+
+#[derive(Deserialize, ToSchema)]
+struct AlbumRequest {
+    root: Option<String>,
+    name: String,
+}
+
+#[derive(Deserialize, ToSchema)]
+struct AlbumImagesRequest {
+    root: Option<String>,
+    names: Vec<String>,
+}
+
+fn check_album_name(name: &str) -> Result<String, AppError> {
+    let name = name.trim();
+    if name.is_empty() || name.chars().count() > MAX_NAME_LEN || name.chars().any(char::is_control)
+    {
+        return Err(AppError::field(
+            "name",
+            format!("不能为空且不超过 {} 个字符", MAX_NAME_LEN),
+        ));
+    }
+    Ok(name.to_string())
+}
+
+fn map_unique(e: DieselError, name: &str) -> AppError {
+    match e {
+        DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, _) => {
+            AppError::Conflict(format!("相册已存在: {}", name))
+        }
+        e => e.into(),
+    }
+}
+
+fn find_album(conn: &mut SqliteConnection, root_id: &str, id: i32) -> Result<Album, AppError> {
+    albums::table
+        .filter(albums::id.eq(id))
+        .filter(albums::root.eq(root_id))
+        .select(Album::as_select())
+        .first(conn)
+        .optional()?
+        .ok_or_else(|| AppError::NotFound(format!("相册不存在: {}", id)))
+}
+
+/// 按文件名查出图片 id，有任何一个不存在时返回 NotFound
+pub(super) fn image_ids(
+    conn: &mut SqliteConnection,
+    root_id: &str,
+    names: &[String],
+) -> Result<Vec<i32>, AppError> {
+    let found: HashMap<String, i32> = images::table
+        .filter(images::root.eq(root_id))
+        .filter(images::name.eq_any(names))
+        .select((images::name, images::id))
+        .load::<(String, i32)>(conn)?
+        .into_iter()
+        .collect();
+    let missing: Vec<&str> = names
+        .iter()
+        .filter(|n| !found.contains_key(*n))
+        .map(String::as_str)
+        .collect();
+    if !missing.is_empty() {
+        return Err(AppError::NotFound(format!(
+            "图片不存在: {}",
+            missing.join(", ")
+        )));
+    }
+    Ok(names.iter().map(|n| found[n]).collect())
+}
+
+#[utoipa::path(
+    tag = tags::IMAGES,
+    params(RootQuery),
+    responses((status = 200, body = Response<Vec<AlbumInfo>>))
+)]
+#[get("/albums")]
+async fn list_albums(
+    query: web::Query<RootQuery>,
+    storage: UserStorage,
+    db: web::Data<Database>,
+) -> Result<impl Responder, AppError> {
+    let root_id = storage
+        .image_root(query.root.as_deref(), Operation::Read)?
+        .id
+        .clone();
+    let result = web::block(move || -> Result<Vec<AlbumInfo>, AppError> {
+        let mut conn = db.connect()?;
+        let counts: HashMap<i32, i64> = album_images::table
+            .inner_join(albums::table)
+            .filter(albums::root.eq(&root_id))
+            .group_by(album_images::album_id)
+            .select((album_images::album_id, count_star()))
+            .load::<(i32, i64)>(&mut conn)?
+            .into_iter()
+            .collect();
+        let albums = albums::table
+            .filter(albums::root.eq(&root_id))
+            .order(albums::name.asc())
+            .select(Album::as_select())
+            .load(&mut conn)?;
+        Ok(albums
+            .into_iter()
+            .map(|album| AlbumInfo {
+                image_count: counts.get(&album.id).copied().unwrap_or(0),
+                album,
+            })
+            .collect())
+    })
+    .await??;
+    Ok(Response::ok(result))
+}
+
+#[utoipa::path(tag = tags::IMAGES, responses((status = 200, body = Response<Album>)))]
+#[post("/albums")]
+async fn create_album(
+    req: web::Json<AlbumRequest>,
+    storage: UserStorage,
+    db: web::Data<Database>,
+) -> Result<impl Responder, AppError> {
+    let req = req.into_inner();
+    let name = check_album_name(&req.name)?;
+    let root_id = storage
+        .image_root(req.root.as_deref(), Operation::Write)?
+        .id
+        .clone();
+    let album = web::block(move || -> Result<Album, AppError> {
+        let mut conn = db.connect()?;
+        let created_at: NaiveDateTime = Utc::now().naive_utc();
+        diesel::insert_into(albums::table)
+            .values(&NewAlbum {
+                root: &root_id,
+                name: &name,
+                created_at,
+            })
+            .execute(&mut conn)
+            .map_err(|e| map_unique(e, &name))?;
+        Ok(albums::table
+            .filter(albums::root.eq(&root_id))
+            .filter(albums::name.eq(&name))
+            .select(Album::as_select())
+            .first(&mut conn)?)
+    })
+    .await??;
+    Ok(Response::ok(album))
+}
+
+#[utoipa::path(tag = tags::IMAGES, responses((status = 200, body = Response<Album>)))]
+#[put("/albums/{id}")]
+async fn rename_album(
+    id: web::Path<i32>,
+    req: web::Json<AlbumRequest>,
+    storage: UserStorage,
+    db: web::Data<Database>,
+) -> Result<impl Responder, AppError> {
+    let id = id.into_inner();
+    let req = req.into_inner();
+    let name = check_album_name(&req.name)?;
+    let root_id = storage
+        .image_root(req.root.as_deref(), Operation::Write)?
+        .id
+        .clone();
+    let album = web::block(move || -> Result<Album, AppError> {
+        let mut conn = db.connect()?;
+        let mut album = find_album(&mut conn, &root_id, id)?;
+        diesel::update(albums::table.filter(albums::id.eq(id)))
+            .set(albums::name.eq(&name))
+            .execute(&mut conn)
+            .map_err(|e| map_unique(e, &name))?;
+        album.name = name;
+        Ok(album)
+    })
+    .await??;
+    Ok(Response::ok(album))
+}
+
+/// 删除相册，相册中的图片保留
+#[utoipa::path(
+    tag = tags::IMAGES,
+    params(RootQuery),
+    responses((status = 200, body = Response<Album>))
+)]
+#[delete("/albums/{id}")]
+async fn delete_album(
+    id: web::Path<i32>,
+    query: web::Query<RootQuery>,
+    storage: UserStorage,
+    db: web::Data<Database>,
+) -> Result<impl Responder, AppError> {
+    let id = id.into_inner();
+    let root_id = storage
+        .image_root(query.root.as_deref(), Operation::Write)?
+        .id
+        .clone();
+    let album = web::block(move || -> Result<Album, AppError> {
+        let mut conn = db.connect()?;
+        let album = find_album(&mut conn, &root_id, id)?;
+        diesel::delete(albums::table.filter(albums::id.eq(id))).execute(&mut conn)?;
+        Ok(album)
+    })
+    .await??;
+    Ok(Response::ok(album))
+}
+
+/// 把图片加入相册，返回新加入的数量（已在相册中的不重复计算）
+#[utoipa::path(tag = tags::IMAGES, responses((status = 200, body = Response<usize>)))]
+#[post("/albums/{id}/add")]
+async fn add_images(
+    id: web::Path<i32>,
+    req: web::Json<AlbumImagesRequest>,
+    storage: UserStorage,
+    db: web::Data<Database>,
+) -> Result<impl Responder, AppError> {
+    let id = id.into_inner();
+    let req = req.into_inner();
+    let root_id = storage
+        .image_root(req.root.as_deref(), Operation::Write)?
+        .id
+        .clone();
+    let added = web::block(move || -> Result<usize, AppError> {
+        let mut conn = db.connect()?;
+        conn.transaction(|conn| {
+            find_album(conn, &root_id, id)?;
+            let added_at = Utc::now().naive_utc();
+            let rows: Vec<NewAlbumImage> = image_ids(conn, &root_id, &req.names)?
+                .into_iter()
+                .map(|image_id| NewAlbumImage {
+                    album_id: id,
+                    image_id,
+                    added_at,
+                })
+                .collect();
+            Ok(diesel::insert_or_ignore_into(album_images::table)
+                .values(&rows)
+                .execute(conn)?)
+        })
+    })
+    .await??;
+    Ok(Response::ok(added))
+}
+
+/// 从相册中移除图片，图片本身不删除；返回移除的数量
+#[utoipa::path(tag = tags::IMAGES, responses((status = 200, body = Response<usize>)))]
+#[post("/albums/{id}/remove")]
+async fn remove_images(
+    id: web::Path<i32>,
+    req: web::Json<AlbumImagesRequest>,
+    storage: UserStorage,
+    db: web::Data<Database>,
+) -> Result<impl Responder, AppError> {
+    let id = id.into_inner();
+    let req = req.into_inner();
+    let root_id = storage
+        .image_root(req.root.as_deref(), Operation::Write)?
+        .id
+        .clone();
+    let removed = web::block(move || -> Result<usize, AppError> {
+        let mut conn = db.connect()?;
+        find_album(&mut conn, &root_id, id)?;
+        let image_ids = images::table
+            .filter(images::root.eq(&root_id))
+            .filter(images::name.eq_any(&req.names))
+            .select(images::id);
+        Ok(diesel::delete(
+            album_images::table
+                .filter(album_images::album_id.eq(id))
+                .filter(album_images::image_id.eq_any(image_ids)),
+        )
+        .execute(&mut conn)?)
+    })
+    .await??;
+    Ok(Response::ok(removed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth_api::role::Role;
+    use crate::db::TestDb;
+    use crate::middleware::auth::CurrentUser;
+    use crate::schema::image_tags;
+    use crate::storage::Storage;
+    use actix_web::dev::Service;
+    use actix_web::http::StatusCode;
+    use actix_web::test::{self, TestRequest};
+    use actix_web::{App, HttpMessage};
+    use serde_json::{Value, json};
+
+    /// 图床存储根中的图片记录，测试只用到数据库，不需要文件
+    fn add_image(test_db: &TestDb, name: &str) {
+        diesel::insert_into(images::table)
+            .values((
+                images::root.eq("images"),
+                images::name.eq(name),
+                images::original_name.eq(name),
+                images::size.eq(1i64),
+                images::width.eq(1),
+                images::height.eq(1),
+                images::mime.eq("image/png"),
+                images::sha256.eq(name),
+                images::uploaded_at.eq(Utc::now().naive_utc()),
+            ))
+            .execute(&mut test_db.conn())
+            .unwrap();
+    }
+
+    #[actix_web::test]
+    async fn test_albums_and_filters() {
+        let test_db = TestDb::new();
+        for name in ["a.png", "b.png", "c.png"] {
+            add_image(&test_db, name);
+        }
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(Storage::for_test(&test_db.dir)))
+                .app_data(web::Data::new(test_db.db.clone()))
+                .wrap_fn(|req, srv| {
+                    req.extensions_mut()
+                        .insert(CurrentUser::for_test(Role::Admin));
+                    srv.call(req)
+                })
+                .service(list_albums)
+                .service(create_album)
+                .service(rename_album)
+                .service(delete_album)
+                .service(add_images)
+                .service(remove_images)
+                .service(super::super::tag::set_tags)
+                .service(super::super::api::list_images),
+        )
+        .await;
+        let call = |req: TestRequest| {
+            let res = test::call_service(&app, req.to_request());
+            async move {
+                let res = res.await;
+                let status = res.status();
+                let body: Value = test::read_body_json(res).await;
+                (status, body["data"].clone())
+            }
+        };
+        let post = |uri: &str, body: Value| TestRequest::post().uri(uri).set_json(body);
+        let total = |uri: &str| {
+            let res = call(TestRequest::get().uri(uri));
+            async move {
+                let (status, data) = res.await;
+                assert_eq!(status, StatusCode::OK);
+                data["total"].as_i64().unwrap()
+            }
+        };
+
+        let (status, album) = call(post("/albums", json!({"name": " 旅行 "}))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(album["name"], "旅行");
+        let id = album["id"].as_i64().unwrap();
+        let (status, _) = call(post("/albums", json!({"name": "旅行"}))).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        let (status, _) = call(post("/albums", json!({"name": " "}))).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let (_, other) = call(post("/albums", json!({"name": "工作"}))).await;
+        let other = other["id"].as_i64().unwrap();
+        let rename = |name: &str| {
+            TestRequest::put()
+                .uri(&format!("/albums/{}", other))
+                .set_json(json!({"name": name}))
+        };
+        assert_eq!(call(rename("旅行")).await.0, StatusCode::CONFLICT);
+        let (status, renamed) = call(rename("项目")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(renamed["name"], "项目");
+        let (status, _) = call(post("/albums/9999/add", json!({"names": ["a.png"]}))).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let add = format!("/albums/{}/add", id);
+        let (_, added) = call(post(&add, json!({"names": ["a.png", "b.png"]}))).await;
+        assert_eq!(added, 2);
+        // 已在相册中的不重复计算；有一张不存在时整批不加入
+        let (_, added) = call(post(&add, json!({"names": ["a.png", "c.png"]}))).await;
+        assert_eq!(added, 1);
+        let (status, _) = call(post(&add, json!({"names": ["a.png", "x.png"]}))).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let (_, added) = call(post(
+            &format!("/albums/{}/add", other),
+            json!({"names": ["c.png"]}),
+        ))
+        .await;
+        assert_eq!(added, 1);
+
+        let (_, albums) = call(TestRequest::get().uri("/albums")).await;
+        let counts: Vec<(&str, i64)> = albums
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|a| {
+                (
+                    a["name"].as_str().unwrap(),
+                    a["image_count"].as_i64().unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(counts, vec![("旅行", 3), ("项目", 1)]);
+
+        let remove = format!("/albums/{}/remove", id);
+        let (_, removed) = call(post(&remove, json!({"names": ["a.png", "x.png"]}))).await;
+        assert_eq!(removed, 1);
+        assert_eq!(total(&format!("/?album={}", id)).await, 2);
+
+        // 标签筛选可与相册筛选组合
+        for (name, tags) in [("a.png", json!(["x"])), ("c.png", json!(["x", "y"]))] {
+            let req = TestRequest::put()
+                .uri(&format!("/{}/tags", name))
+                .set_json(json!({"tags": tags}));
+            assert_eq!(call(req).await.0, StatusCode::OK);
+        }
+        assert_eq!(total("/").await, 3);
+        assert_eq!(total("/?tag=x").await, 2);
+        assert_eq!(total(&format!("/?album={}&tag=x", id)).await, 1);
+        assert_eq!(total(&format!("/?album={}&tag=y", id)).await, 1);
+        assert_eq!(total("/?tag=z").await, 0);
+
+        // 删除相册只删关联，图片保留
+        let req = TestRequest::delete().uri(&format!("/albums/{}", id));
+        assert_eq!(call(req).await.0, StatusCode::OK);
+        let req = TestRequest::delete().uri(&format!("/albums/{}", id));
+        assert_eq!(call(req).await.0, StatusCode::NOT_FOUND);
+        assert_eq!(total(&format!("/?album={}", id)).await, 0);
+        assert_eq!(total("/").await, 3);
+        let links: i64 = album_images::table
+            .filter(album_images::album_id.eq(id as i32))
+            .count()
+            .get_result(&mut test_db.conn())
+            .unwrap();
+        assert_eq!(links, 0);
+
+        // 删除图片记录时级联删除其相册关联和标签
+        diesel::delete(images::table.filter(images::name.eq("c.png")))
+            .execute(&mut test_db.conn())
+            .unwrap();
+        let (_, albums) = call(TestRequest::get().uri("/albums")).await;
+        assert_eq!(albums[0]["image_count"], 0);
+        let tags: i64 = image_tags::table
+            .count()
+            .get_result(&mut test_db.conn())
+            .unwrap();
+        assert_eq!(tags, 1);
+    }
+}
@@ -1,106 +1,813 @@
-use crate::{base::Response, error::AppError};
+use super::meta::ImageMeta;
+use super::model::{Image, NewImage};
+use super::sniff::ImageType;
+use super::thumb::{self, Fit, OutputFormat, ThumbSpec};
+use crate::base::{Page, Response};
+use crate::config::{Config, ImageConfig};
+use crate::db::Database;
+use crate::error::AppError;
+use crate::middleware::auth::CurrentUser;
+use crate::openapi::tags;
+use crate::schema::{album_images, image_tags, images};
+use crate::storage::{Operation, StorageRoot, UserStorage};
+use actix_files::NamedFile;
 use actix_multipart::form::{MultipartForm, tempfile::TempFile};
-use actix_web::{HttpResponse, Responder, web};
-use actix_web::{get, post};
-use log::info;
-use std::fs::create_dir;
+use actix_web::http::header::{self, HeaderValue};
+use actix_web::{HttpRequest, HttpResponse, Responder, web};
+use actix_web::{delete, get, post};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use diesel::prelude::*;
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
+use diesel::sqlite::Sqlite;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::path::Path;
-use uuid;
+use utoipa::{IntoParams, ToSchema};
 
+/// 图床接口通过 ?root= 选择存储根，不填使用配置中的 image_root
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub(super) struct RootQuery {
+    pub(super) root: Option<String>,
+}
+
+/// 带 w/h/fit/format 任一参数时返回缩放后的图片，否则返回原图
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct ImageQuery {
+    root: Option<String>,
+    w: Option<u32>,
+    h: Option<u32>,
+    fit: Option<Fit>,
+    format: Option<OutputFormat>,
+}
+
+#[utoipa::path(
+    tag = tags::IMAGES,
+    params(ImageQuery),
+    responses((status = 200, description = "图片内容", content_type = "image/*"))
+)]
 #[get("/{path}")]
-async fn get_image(path: web::Path<String>) -> Result<impl Responder, AppError> {
+async fn get_image(
+    req: HttpRequest,
+    path: web::Path<String>,
+    query: web::Query<ImageQuery>,
+    storage: UserStorage,
+    config: web::Data<Config>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
     let path = path.into_inner();
-    let img_root = Path::new("/mnt/Leven/img");
-    let image_path = img_root.join(&path);
-    if image_path.exists() == false || image_path.is_file() == false {
+    let query = query.into_inner();
+    let img_root = storage.image_root(query.root.as_deref(), Operation::Read)?;
+    let image_path = img_root.resolve(&path)?;
+    let cache_dir = thumb::cache_dir_for(&config.image.cache_dir, &img_root.id, &path);
+    // 缩略图缓存在删除图片时清理，读取接口不修改文件
+    if !image_path.is_file() {
         return Err(AppError::NotFound("图片不存在".into()));
+    }
+    let (file, mime) = if query.w.is_none()
+        && query.h.is_none()
+        && query.fit.is_none()
+        && query.format.is_none()
+    {
+        let root_id = img_root.id.clone();
+        web::block(move || -> Result<_, AppError> {
+            let mut conn = db.connect()?;
+            let mime = stored_mime(&mut conn, &root_id, &path, &image_path)?;
+            Ok((image_path, mime))
+        })
+        .await??
     } else {
-        let image_type = image_path
-            .extension()
-            .and_then(|ext| ext.to_str())
-            .unwrap_or("");
-        return Ok(HttpResponse::Ok()
-            .content_type(format!("image/{}", image_type))
-            .body(std::fs::read(image_path).map_err(AppError::Io)?));
+        let spec = ThumbSpec {
+            width: query.w,
+            height: query.h,
+            fit: query.fit.unwrap_or_default(),
+            format: query.format,
+        };
+        spec.validate()?;
+        let (file, image_type) =
+            web::block(move || thumb::thumbnail(&image_path, &cache_dir, &spec)).await??;
+        (file, Some(image_type.mime().to_string()))
+    };
+    let mime = mime
+        .and_then(|m| m.parse().ok())
+        .unwrap_or(actix_web::mime::APPLICATION_OCTET_STREAM);
+    let mut response = NamedFile::open_async(file)
+        .await?
+        .set_content_type(mime)
+        .into_response(&req);
+    response.headers_mut().insert(
+        header::X_CONTENT_TYPE_OPTIONS,
+        HeaderValue::from_static("nosniff"),
+    );
+    Ok(response)
+}
+
+/// 上传时记录的 MIME；没有记录（如直接放入存储根的文件）时按文件内容识别，不根据扩展名判断
+fn stored_mime(
+    conn: &mut SqliteConnection,
+    root_id: &str,
+    name: &str,
+    path: &Path,
+) -> Result<Option<String>, AppError> {
+    let stored = images::table
+        .filter(images::root.eq(root_id))
+        .filter(images::name.eq(name))
+        .select(images::mime)
+        .first(conn)
+        .optional()?;
+    match stored {
+        Some(mime) => Ok(Some(mime)),
+        None => Ok(ImageType::sniff_file(path)?.map(|t| t.mime().to_string())),
     }
 }
 
-#[derive(Debug, MultipartForm)]
+#[derive(Debug, MultipartForm, ToSchema)]
+#[schema(as = ImageUploadForm)]
 struct UploadForm {
+    /// 可以有多个 file 字段
     #[multipart(rename = "file")]
+    #[schema(rename = "file", value_type = Vec<String>, format = Binary)]
     files: Vec<TempFile>,
 }
 
+#[utoipa::path(
+    tag = tags::IMAGES,
+    params(RootQuery),
+    request_body(content = UploadForm, content_type = "multipart/form-data"),
+    responses((status = 200, body = Response<Vec<String>>))
+)]
 #[post("/")]
-async fn create_image(form: MultipartForm<UploadForm>) -> Result<impl Responder, AppError> {
-    let img_root = Path::new("/mnt/Leven/img");
-    if img_root.exists() == false {
-        create_dir(img_root).map_err(AppError::Io)?;
-        info!("Created image root directory: {:?}", img_root);
+async fn create_image(
+    MultipartForm(form): MultipartForm<UploadForm>,
+    query: web::Query<RootQuery>,
+    storage: UserStorage,
+    config: web::Data<Config>,
+    db: web::Data<Database>,
+    user: CurrentUser,
+) -> Result<impl Responder, AppError> {
+    let img_root = storage
+        .image_root(query.root.as_deref(), Operation::Write)?
+        .clone();
+    let names =
+        web::block(move || save_files(&img_root, &config.image, &db, &user.username, form.files))
+            .await??;
+    Ok(Response::ok(names))
+}
+
+/// 按文件头校验上传的图片，返回识别出的格式
+fn check_upload(f: &TempFile, config: &ImageConfig) -> Result<ImageType, AppError> {
+    let file_name = f.file_name.clone().unwrap_or_else(|| "unknown".into());
+    if f.size as u64 > config.max_upload_bytes {
+        return Err(AppError::PayloadTooLarge(format!(
+            "{} 超过 {} 字节",
+            file_name, config.max_upload_bytes
+        )));
+    }
+    let image_type = ImageType::sniff_file(f.file.path())?
+        .ok_or_else(|| AppError::InvalidParam(format!("{} 不是支持的图片格式", file_name)))?;
+    if !config
+        .allowed_types
+        .iter()
+        .any(|t| ImageType::from_name(t) == Some(image_type))
+    {
+        return Err(AppError::InvalidParam(format!(
+            "不允许上传 {} 格式的图片",
+            image_type.extension()
+        )));
     }
+    // 扩展名与内容不一致时拒绝，避免把其它格式伪装成图片
+    if let Some((_, ext)) = file_name.rsplit_once('.')
+        && ImageType::from_name(ext) != Some(image_type)
+    {
+        return Err(AppError::InvalidParam(format!(
+            "{} 的扩展名与内容不符（实际为 {}）",
+            file_name,
+            image_type.extension()
+        )));
+    }
+    Ok(image_type)
+}
 
-    return save_files(form).await;
+fn new_image(
+    root: &StorageRoot,
+    name: String,
+    original_name: String,
+    size: u64,
+    image_type: ImageType,
+    meta: ImageMeta,
+    uploaded_at: NaiveDateTime,
+) -> NewImage {
+    NewImage {
+        root: root.id.clone(),
+        name,
+        original_name,
+        size: size as i64,
+        width: meta.width as i32,
+        height: meta.height as i32,
+        mime: image_type.mime().into(),
+        sha256: meta.sha256,
+        uploaded_at,
+        uploader: None,
+        camera_make: meta.exif.camera_make,
+        camera_model: meta.exif.camera_model,
+        taken_at: meta.exif.taken_at,
+        orientation: meta.exif.orientation,
+        gps_latitude: meta.exif.gps_latitude,
+        gps_longitude: meta.exif.gps_longitude,
+    }
 }
 
-async fn save_files(
-    MultipartForm(form): MultipartForm<UploadForm>,
-) -> Result<impl Responder, AppError> {
-    let mut file_paths = Vec::<String>::new();
-    for f in form.files {
-        let file_name = f.file_name.clone().unwrap_or_else(|| "unknown".into());
-        let file_ext = file_name
-            .rsplit('.')
-            .next()
-            .map(|s| s.to_lowercase())
-            .unwrap_or_else(|| "bin".into());
-        if !["png", "jpg", "jpeg", "gif", "bmp"].contains(&file_ext.as_str()) {
-            return Err(AppError::InvalidParam(format!(
-                "Unsupported file type: {}",
-                file_ext
-            )));
+/// 校验并保存上传的图片，同时写入 images 表；任何一步失败都会删除已保存的文件
+fn save_files(
+    img_root: &StorageRoot,
+    config: &ImageConfig,
+    db: &Database,
+    uploader: &str,
+    files: Vec<TempFile>,
+) -> Result<Vec<String>, AppError> {
+    // 先全部校验，避免只保存了一部分文件
+    let mut checked = Vec::new();
+    for f in files {
+        let image_type = check_upload(&f, config)?;
+        let meta = ImageMeta::read(f.file.path())?;
+        checked.push((f, image_type, meta));
+    }
+    let mut conn = db.connect()?;
+    let now = Utc::now().naive_utc();
+    // 本次请求新保存的文件名，出错时删除这些文件和记录
+    let mut saved = Vec::new();
+    let mut names = Vec::new();
+    // 本次请求内已处理过的内容，同一张图片传两次也只保存一份
+    let mut seen = HashMap::<String, String>::new();
+    let result = (|| {
+        for (f, image_type, meta) in checked {
+            if let Some(name) = seen.get(&meta.sha256) {
+                names.push(name.clone());
+                continue;
+            }
+            if let Some(name) = find_duplicate(&mut conn, img_root, &meta.sha256)? {
+                seen.insert(meta.sha256, name.clone());
+                names.push(name);
+                continue;
+            }
+            let file_name = uuid::Uuid::new_v4().to_string() + "." + image_type.extension();
+            let path = img_root.path.join(&file_name);
+            f.file
+                .persist(&path)
+                .map_err(|e| AppError::Io(std::io::Error::other(e)))?;
+            let sha256 = meta.sha256.clone();
+            let original_name = f.file_name.unwrap_or_else(|| file_name.clone());
+            let mut row = new_image(
+                img_root,
+                file_name.clone(),
+                original_name,
+                f.size as u64,
+                image_type,
+                meta,
+                now,
+            );
+            row.uploader = Some(uploader.to_string());
+            let name = insert_image(&mut conn, img_root, &row)?;
+            if name == file_name {
+                saved.push(file_name);
+            }
+            seen.insert(sha256, name.clone());
+            names.push(name);
+        }
+        Ok(())
+    })();
+    if let Err(e) = result {
+        for name in saved {
+            let _ = std::fs::remove_file(img_root.path.join(&name));
+            let _ = diesel::delete(
+                images::table
+                    .filter(images::root.eq(&img_root.id))
+                    .filter(images::name.eq(&name)),
+            )
+            .execute(&mut conn);
         }
-        let file_name = uuid::Uuid::new_v4().to_string() + "." + &file_ext;
-        let path = Path::new("/mnt/Leven/img").join(&file_name);
-        file_paths.push(file_name);
-        f.file
-            .persist(&path)
-            .map_err(|e| AppError::Io(std::io::Error::new(std::io::ErrorKind::Other, e)))?;
+        return Err(e);
     }
+    Ok(names)
+}
+
+/// 插入已保存到存储根的图片记录，返回最终使用的文件名；
+/// 并发请求先保存了相同内容时触发唯一索引，删除本次的文件，改用已有的那份
+fn insert_image(
+    conn: &mut SqliteConnection,
+    img_root: &StorageRoot,
+    row: &NewImage,
+) -> Result<String, AppError> {
+    let path = img_root.path.join(&row.name);
+    match diesel::insert_into(images::table).values(row).execute(conn) {
+        Ok(_) => Ok(row.name.clone()),
+        Err(DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, _)) => {
+            std::fs::remove_file(&path)?;
+            find_duplicate(conn, img_root, &row.sha256)?
+                .ok_or_else(|| AppError::Conflict("相同内容的图片正在保存，请重试".into()))
+        }
+        Err(e) => {
+            let _ = std::fs::remove_file(&path);
+            Err(e.into())
+        }
+    }
+}
+
+/// 查找存储根中内容相同的图片；记录对应的文件已不存在时顺带删除该记录
+fn find_duplicate(
+    conn: &mut SqliteConnection,
+    img_root: &StorageRoot,
+    sha256: &str,
+) -> Result<Option<String>, AppError> {
+    let names: Vec<String> = images::table
+        .filter(images::root.eq(&img_root.id))
+        .filter(images::sha256.eq(sha256))
+        .order(images::id.asc())
+        .select(images::name)
+        .load(conn)?;
+    for name in names {
+        if img_root.path.join(&name).is_file() {
+            return Ok(Some(name));
+        }
+        diesel::delete(
+            images::table
+                .filter(images::root.eq(&img_root.id))
+                .filter(images::name.eq(&name)),
+        )
+        .execute(conn)?;
+    }
+    Ok(None)
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+enum SortField {
+    #[default]
+    UploadedAt,
+    TakenAt,
+    Size,
+    /// 按原始文件名
+    Name,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+enum Order {
+    Asc,
+    #[default]
+    Desc,
+}
 
-    Ok(Response::new(Some(file_paths), "Success".into(), 0))
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct ListQuery {
+    root: Option<String>,
+    page: Option<i64>,
+    page_size: Option<i64>,
+    #[serde(default)]
+    sort: SortField,
+    #[serde(default)]
+    order: Order,
+    /// 只列出该相册中的图片
+    album: Option<i32>,
+    /// 只列出带该标签的图片
+    tag: Option<String>,
 }
 
+/// 列表中的一项：图片记录及其标签
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+struct ImageItem {
+    #[serde(flatten)]
+    image: Image,
+    tags: Vec<String>,
+}
+
+/// 按存储根、相册和标签筛选
+fn filtered<'a>(
+    root_id: &'a str,
+    album: Option<i32>,
+    tag: Option<&'a str>,
+) -> images::BoxedQuery<'a, Sqlite> {
+    let mut query = images::table.filter(images::root.eq(root_id)).into_boxed();
+    if let Some(album) = album {
+        query = query.filter(
+            images::id.eq_any(
+                album_images::table
+                    .filter(album_images::album_id.eq(album))
+                    .select(album_images::image_id),
+            ),
+        );
+    }
+    if let Some(tag) = tag {
+        query = query.filter(
+            images::id.eq_any(
+                image_tags::table
+                    .filter(image_tags::tag.eq(tag))
+                    .select(image_tags::image_id),
+            ),
+        );
+    }
+    query
+}
+
+const DEFAULT_PAGE_SIZE: i64 = 50;
+const MAX_PAGE_SIZE: i64 = 500;
+
+fn sorted<'a>(
+    query: images::BoxedQuery<'a, Sqlite>,
+    sort: SortField,
+    order: Order,
+) -> images::BoxedQuery<'a, Sqlite> {
+    use images::columns::*;
+    // 排序字段相同时按 id 排，保证翻页稳定
+    match (sort, order) {
+        (SortField::UploadedAt, Order::Asc) => query.order((uploaded_at.asc(), id.asc())),
+        (SortField::UploadedAt, Order::Desc) => query.order((uploaded_at.desc(), id.desc())),
+        (SortField::TakenAt, Order::Asc) => query.order((taken_at.asc(), id.asc())),
+        (SortField::TakenAt, Order::Desc) => query.order((taken_at.desc(), id.desc())),
+        (SortField::Size, Order::Asc) => query.order((size.asc(), id.asc())),
+        (SortField::Size, Order::Desc) => query.order((size.desc(), id.desc())),
+        (SortField::Name, Order::Asc) => query.order((original_name.asc(), id.asc())),
+        (SortField::Name, Order::Desc) => query.order((original_name.desc(), id.desc())),
+    }
+}
+
+#[utoipa::path(
+    tag = tags::IMAGES,
+    params(ListQuery),
+    responses((status = 200, body = Response<Page<ImageItem>>))
+)]
 #[get("/")]
-async fn list_images() -> Result<impl Responder, AppError> {
-    let img_root = Path::new("/mnt/Leven/img");
-    if !img_root.exists() {
-        return Ok(Response::new(
-            Some(Vec::<String>::new()),
-            "No images found".into(),
-            0,
-        ));
+async fn list_images(
+    query: web::Query<ListQuery>,
+    storage: UserStorage,
+    db: web::Data<Database>,
+) -> Result<impl Responder, AppError> {
+    let query = query.into_inner();
+    let root_id = storage
+        .image_root(query.root.as_deref(), Operation::Read)?
+        .id
+        .clone();
+    let page = query.page.unwrap_or(1);
+    let page_size = query.page_size.unwrap_or(DEFAULT_PAGE_SIZE);
+    if page < 1 || !(1..=MAX_PAGE_SIZE).contains(&page_size) {
+        return Err(AppError::InvalidParam(format!(
+            "page 必须大于 0，page_size 必须在 1~{} 之间",
+            MAX_PAGE_SIZE
+        )));
     }
-    let mut images = Vec::new();
-    for entry in std::fs::read_dir(img_root).map_err(AppError::Io)? {
-        let entry = entry.map_err(AppError::Io)?;
+    let result = web::block(move || -> Result<Page<ImageItem>, AppError> {
+        let mut conn = db.connect()?;
+        let tag = query.tag.as_deref();
+        let total = filtered(&root_id, query.album, tag)
+            .count()
+            .get_result(&mut conn)?;
+        let images: Vec<Image> = sorted(
+            filtered(&root_id, query.album, tag),
+            query.sort,
+            query.order,
+        )
+        .limit(page_size)
+        .offset((page - 1) * page_size)
+        .select(Image::as_select())
+        .load(&mut conn)?;
+        let mut tags = HashMap::<i32, Vec<String>>::new();
+        for (image_id, tag) in image_tags::table
+            .filter(image_tags::image_id.eq_any(images.iter().map(|i| i.id)))
+            .order(image_tags::tag.asc())
+            .load::<(i32, String)>(&mut conn)?
+        {
+            tags.entry(image_id).or_default().push(tag);
+        }
+        let items = images
+            .into_iter()
+            .map(|image| ImageItem {
+                tags: tags.remove(&image.id).unwrap_or_default(),
+                image,
+            })
+            .collect();
+        Ok(Page {
+            items,
+            total,
+            page,
+            page_size,
+        })
+    })
+    .await??;
+    Ok(Response::ok(result))
+}
+
+/// 为存储根中尚未入库的图片补录元数据，返回新增的文件名
+#[utoipa::path(
+    tag = tags::IMAGES,
+    params(RootQuery),
+    responses((status = 200, body = Response<Vec<String>>))
+)]
+#[post("/rescan")]
+async fn rescan(
+    query: web::Query<RootQuery>,
+    storage: UserStorage,
+    db: web::Data<Database>,
+) -> Result<impl Responder, AppError> {
+    let img_root = storage
+        .image_root(query.root.as_deref(), Operation::Write)?
+        .clone();
+    let names = web::block(move || rescan_root(&img_root, &db)).await??;
+    Ok(Response::ok(names))
+}
+
+fn rescan_root(img_root: &StorageRoot, db: &Database) -> Result<Vec<String>, AppError> {
+    let mut conn = db.connect()?;
+    let (known, mut hashes): (HashSet<String>, HashSet<String>) = images::table
+        .filter(images::root.eq(&img_root.id))
+        .select((images::name, images::sha256))
+        .load::<(String, String)>(&mut conn)?
+        .into_iter()
+        .unzip();
+    let mut rows = Vec::new();
+    for entry in std::fs::read_dir(&img_root.path)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        if known.contains(&name) || !entry.file_type()?.is_file() {
+            continue;
+        }
         let path = entry.path();
-        if path.is_file() {
-            if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
-                let ext = ext.to_lowercase();
-                if ["png", "jpg", "jpeg", "gif", "bmp"].contains(&ext.as_str()) {
-                    if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
-                        images.push(name.to_string());
-                    }
-                }
-            }
+        let Some(image_type) = ImageType::sniff_file(&path)? else {
+            continue;
+        };
+        // 无法解码的文件跳过，不影响其它图片入库
+        let Ok(meta) = ImageMeta::read(&path) else {
+            continue;
+        };
+        // 同一内容只入库一份，多出的副本留在磁盘上
+        if !hashes.insert(meta.sha256.clone()) {
+            continue;
         }
+        let fs_meta = entry.metadata()?;
+        let uploaded_at = fs_meta
+            .modified()
+            .map(|t| DateTime::<Utc>::from(t).naive_utc())
+            .unwrap_or_else(|_| Utc::now().naive_utc());
+        rows.push(new_image(
+            img_root,
+            name.clone(),
+            name,
+            fs_meta.len(),
+            image_type,
+            meta,
+            uploaded_at,
+        ));
+    }
+    diesel::insert_into(images::table)
+        .values(&rows)
+        .execute(&mut conn)?;
+    Ok(rows.into_iter().map(|r| r.name).collect())
+}
+
+/// 图床中的文件名只能是存储根下的一级文件名
+fn check_name(name: &str) -> Result<(), AppError> {
+    if name.is_empty() || name.contains(['/', '\\']) || name == "." || name == ".." {
+        return Err(AppError::InvalidParam(format!("非法文件名: {}", name)));
+    }
+    Ok(())
+}
+
+/// 删除原图、缩略图缓存和数据库记录；文件和记录都不存在时返回 false
+fn delete_image(
+    conn: &mut SqliteConnection,
+    img_root: &StorageRoot,
+    cache_dir: &Path,
+    name: &str,
+) -> Result<bool, AppError> {
+    check_name(name)?;
+    let file_removed = match std::fs::remove_file(img_root.resolve(name)?) {
+        Ok(()) => true,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => false,
+        Err(e) => return Err(e.into()),
+    };
+    thumb::invalidate(cache_dir, &img_root.id, name)?;
+    let rows = diesel::delete(
+        images::table
+            .filter(images::root.eq(&img_root.id))
+            .filter(images::name.eq(name)),
+    )
+    .execute(conn)?;
+    Ok(file_removed || rows > 0)
+}
+
+#[utoipa::path(
+    tag = tags::IMAGES,
+    params(RootQuery),
+    responses((status = 200, body = Response<Option<String>>))
+)]
+#[delete("/{name}")]
+async fn delete_one(
+    name: web::Path<String>,
+    query: web::Query<RootQuery>,
+    storage: UserStorage,
+    config: web::Data<Config>,
+    db: web::Data<Database>,
+) -> Result<impl Responder, AppError> {
+    let name = name.into_inner();
+    let img_root = storage
+        .image_root(query.root.as_deref(), Operation::Delete)?
+        .clone();
+    let deleted = web::block(move || -> Result<Option<String>, AppError> {
+        let mut conn = db.connect()?;
+        let found = delete_image(&mut conn, &img_root, &config.image.cache_dir, &name)?;
+        Ok(found.then_some(name))
+    })
+    .await??
+    .ok_or_else(|| AppError::NotFound("图片不存在".into()))?;
+    Ok(Response::ok(deleted))
+}
+
+/// 单次批量删除的数量上限
+const MAX_BATCH_DELETE: usize = 1000;
+
+#[derive(Deserialize, ToSchema)]
+struct BatchDeleteRequest {
+    root: Option<String>,
+    names: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+struct BatchDeleteResult {
+    deleted: Vec<String>,
+    /// 文件和记录都不存在的名称
+    not_found: Vec<String>,
+}
+
+#[utoipa::path(tag = tags::IMAGES, responses((status = 200, body = Response<BatchDeleteResult>)))]
+#[post("/delete")]
+async fn delete_batch(
+    req: web::Json<BatchDeleteRequest>,
+    storage: UserStorage,
+    config: web::Data<Config>,
+    db: web::Data<Database>,
+) -> Result<impl Responder, AppError> {
+    let req = req.into_inner();
+    if req.names.len() > MAX_BATCH_DELETE {
+        return Err(AppError::InvalidParam(format!(
+            "一次最多删除 {} 张图片",
+            MAX_BATCH_DELETE
+        )));
+    }
+    // 先校验全部文件名，避免删除到一半才报错
+    for name in &req.names {
+        check_name(name)?;
     }
-    Ok(Response::new(Some(images), "Success".into(), 0))
+    let img_root = storage
+        .image_root(req.root.as_deref(), Operation::Delete)?
+        .clone();
+    let result = web::block(move || -> Result<BatchDeleteResult, AppError> {
+        let mut conn = db.connect()?;
+        let mut result = BatchDeleteResult {
+            deleted: Vec::new(),
+            not_found: Vec::new(),
+        };
+        for name in req.names {
+            if delete_image(&mut conn, &img_root, &config.image.cache_dir, &name)? {
+                result.deleted.push(name);
+            } else {
+                result.not_found.push(name);
+            }
+        }
+        Ok(result)
+    })
+    .await??;
+    Ok(Response::ok(result))
 }
 
-#[get("/delete/{path}/{dir}")]
-async fn delete(path: web::Path<(String, String)>) -> Result<impl Responder, AppError> {
-    let path_dir = path.into_inner();
-    println!("path: {}, dir: {}", path_dir.0, path_dir.1);
-    return Ok("删除成功".to_string());
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::TestDb;
+    use crate::storage::TestRoot;
+
+    #[test]
+    fn test_check_name() {
+        assert!(check_name("a.png").is_ok());
+        assert!(check_name("").is_err());
+        assert!(check_name("..").is_err());
+        assert!(check_name("a/b.png").is_err());
+    }
+
+    #[test]
+    fn test_duplicate_and_delete() {
+        let test_db = TestDb::new();
+        let root = TestRoot::new("images");
+        let tmp = root.path.clone();
+        let path = tmp.join("a.png");
+        image::RgbImage::new(2, 2).save(&path).unwrap();
+        let meta = ImageMeta::read(&path).unwrap();
+        let sha256 = meta.sha256.clone();
+        let row = new_image(
+            &root,
+            "a.png".into(),
+            "a.png".into(),
+            1,
+            ImageType::Png,
+            meta,
+            Utc::now().naive_utc(),
+        );
+        let mut conn = test_db.conn();
+        diesel::insert_into(images::table)
+            .values(&row)
+            .execute(&mut conn)
+            .unwrap();
+
+        assert_eq!(
+            find_duplicate(&mut conn, &root, &sha256).unwrap(),
+            Some("a.png".into())
+        );
+        let cache = tmp.join("cache");
+        assert!(delete_image(&mut conn, &root, &cache, "a.png").unwrap());
+        assert!(!path.exists());
+        assert!(!delete_image(&mut conn, &root, &cache, "a.png").unwrap());
+        assert_eq!(find_duplicate(&mut conn, &root, &sha256).unwrap(), None);
+    }
+
+    /// 唯一索引冲突时视为重复图片，删除新文件并返回已有的文件名
+    #[test]
+    fn test_insert_image() {
+        let test_db = TestDb::new();
+        let root = TestRoot::new("images");
+        let tmp = root.path.clone();
+        let row = |name: &str| {
+            let path = tmp.join(name);
+            image::RgbImage::new(2, 2).save(&path).unwrap();
+            new_image(
+                &root,
+                name.into(),
+                name.into(),
+                1,
+                ImageType::Png,
+                ImageMeta::read(&path).unwrap(),
+                Utc::now().naive_utc(),
+            )
+        };
+        let mut conn = test_db.conn();
+        assert_eq!(
+            insert_image(&mut conn, &root, &row("a.png")).unwrap(),
+            "a.png"
+        );
+        assert_eq!(
+            insert_image(&mut conn, &root, &row("b.png")).unwrap(),
+            "a.png"
+        );
+        assert!(tmp.join("a.png").exists());
+        assert!(!tmp.join("b.png").exists());
+
+        // 已有记录的文件不存在时清理记录，本次请求需重试
+        std::fs::remove_file(tmp.join("a.png")).unwrap();
+        let err = insert_image(&mut conn, &root, &row("c.png")).unwrap_err();
+        assert!(matches!(err, AppError::Conflict(_)));
+        assert!(!tmp.join("c.png").exists());
+        assert_eq!(
+            insert_image(&mut conn, &root, &row("d.png")).unwrap(),
+            "d.png"
+        );
+    }
+
+    #[test]
+    fn test_stored_mime() {
+        let test_db = TestDb::new();
+        let root = TestRoot::new("images");
+        let tmp = root.path.clone();
+        let path = tmp.join("a.png");
+        image::RgbImage::new(2, 2).save(&path).unwrap();
+        let mut row = new_image(
+            &root,
+            "a.png".into(),
+            "a.png".into(),
+            1,
+            ImageType::Png,
+            ImageMeta::read(&path).unwrap(),
+            Utc::now().naive_utc(),
+        );
+        row.mime = "image/x-stored".into();
+        let mut conn = test_db.conn();
+        diesel::insert_into(images::table)
+            .values(&row)
+            .execute(&mut conn)
+            .unwrap();
+
+        // 有记录时使用记录中的 MIME，不再识别文件内容
+        assert_eq!(
+            stored_mime(&mut conn, "images", "a.png", &path).unwrap(),
+            Some("image/x-stored".into())
+        );
+        assert_eq!(
+            stored_mime(&mut conn, "other", "a.png", &path).unwrap(),
+            Some("image/png".into())
+        );
+        let text = tmp.join("b.png");
+        std::fs::write(&text, "not an image").unwrap();
+        assert_eq!(
+            stored_mime(&mut conn, "images", "b.png", &text).unwrap(),
+            None
+        );
+    }
 }
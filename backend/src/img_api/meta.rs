@@ -0,0 +1,132 @@
+use chrono::NaiveDateTime;
+use exif::{In, Reader, Tag, Value};
+use image::ImageReader;
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use crate::error::AppError;
+
+/// 从图片文件中读取的元数据
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ImageMeta {
+    pub width: u32,
+    pub height: u32,
+    pub sha256: String,
+    pub exif: ExifInfo,
+}
+
+/// 入库的 EXIF 字段，图片不带 EXIF 或字段缺失时为 None
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ExifInfo {
+    pub camera_make: Option<String>,
+    pub camera_model: Option<String>,
+    pub taken_at: Option<NaiveDateTime>,
+    pub orientation: Option<i32>,
+    pub gps_latitude: Option<f64>,
+    pub gps_longitude: Option<f64>,
+}
+
+impl ImageMeta {
+    /// 读取尺寸、SHA-256 和 EXIF；尺寸读取失败视为图片损坏
+    pub fn read(path: &Path) -> Result<ImageMeta, AppError> {
+        let (width, height) = ImageReader::open(path)?
+            .with_guessed_format()?
+            .into_dimensions()
+            .map_err(|e| AppError::InvalidParam(format!("图片解码失败: {}", e)))?;
+        let mut hasher = Sha256::new();
+        std::io::copy(&mut File::open(path)?, &mut hasher)?;
+        Ok(ImageMeta {
+            width,
+            height,
+            sha256: hex::encode(hasher.finalize()),
+            exif: ExifInfo::read(path),
+        })
+    }
+}
+
+impl ExifInfo {
+    /// EXIF 只是附加信息，解析失败时返回空值而不是拒绝上传
+    pub fn read(path: &Path) -> ExifInfo {
+        let Ok(file) = File::open(path) else {
+            return ExifInfo::default();
+        };
+        let Ok(exif) = Reader::new().read_from_container(&mut BufReader::new(file)) else {
+            return ExifInfo::default();
+        };
+        let field = |tag| exif.get_field(tag, In::PRIMARY).map(|f| &f.value);
+        let text = |tag| field(tag).and_then(ascii);
+        ExifInfo {
+            camera_make: text(Tag::Make),
+            camera_model: text(Tag::Model),
+            taken_at: text(Tag::DateTimeOriginal)
+                .or_else(|| text(Tag::DateTime))
+                .and_then(|s| NaiveDateTime::parse_from_str(&s, "%Y:%m:%d %H:%M:%S").ok()),
+            orientation: field(Tag::Orientation)
+                .and_then(|v| v.get_uint(0))
+                .map(|v| v as i32),
+            gps_latitude: gps_coord(field(Tag::GPSLatitude), text(Tag::GPSLatitudeRef), "S"),
+            gps_longitude: gps_coord(field(Tag::GPSLongitude), text(Tag::GPSLongitudeRef), "W"),
+        }
+    }
+}
+
+fn ascii(value: &Value) -> Option<String> {
+    match value {
+        Value::Ascii(parts) => {
+            let s = String::from_utf8_lossy(parts.first()?)
+                .trim_matches(|c: char| c == '\0' || c.is_whitespace())
+                .to_string();
+            (!s.is_empty()).then_some(s)
+        }
+        _ => None,
+    }
+}
+
+/// 度/分/秒三个有理数转为十进制度数，南纬和西经为负
+fn gps_coord(value: Option<&Value>, reference: Option<String>, negative: &str) -> Option<f64> {
+    let Some(Value::Rational(dms)) = value else {
+        return None;
+    };
+    if dms.len() < 3 || dms.iter().any(|r| r.denom == 0) {
+        return None;
+    }
+    let deg = dms[0].to_f64() + dms[1].to_f64() / 60.0 + dms[2].to_f64() / 3600.0;
+    Some(if reference.as_deref() == Some(negative) {
+        -deg
+    } else {
+        deg
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use exif::Rational;
+
+    #[test]
+    fn test_gps_coord() {
+        let dms = Value::Rational(vec![
+            Rational::from((31, 1)),
+            Rational::from((30, 1)),
+            Rational::from((36, 1)),
+        ]);
+        let lat = gps_coord(Some(&dms), Some("N".into()), "S").unwrap();
+        assert!((lat - 31.51).abs() < 1e-9);
+        let lat = gps_coord(Some(&dms), Some("S".into()), "S").unwrap();
+        assert!((lat + 31.51).abs() < 1e-9);
+        assert_eq!(gps_coord(None, None, "S"), None);
+    }
+
+    #[test]
+    fn test_read_without_exif() {
+        let tmp = std::env::temp_dir().join(format!("rpanel-meta-{}.png", uuid::Uuid::new_v4()));
+        image::RgbImage::new(3, 2).save(&tmp).unwrap();
+        let meta = ImageMeta::read(&tmp).unwrap();
+        assert_eq!((meta.width, meta.height), (3, 2));
+        assert_eq!(meta.sha256.len(), 64);
+        assert_eq!(meta.exif, ExifInfo::default());
+        std::fs::remove_file(&tmp).unwrap();
+    }
+}
@@ -1,9 +1,25 @@
+mod album;
 mod api;
-use actix_web::web::ServiceConfig;
+mod meta;
+pub mod model;
+pub(crate) mod sniff;
+mod tag;
+mod thumb;
+use utoipa_actix_web::service_config::ServiceConfig;
 
 pub fn handle(cfg: &mut ServiceConfig) {
     cfg.service(api::create_image);
+    cfg.service(api::rescan);
+    // 相册和标签的固定路径要先于 /{path} 注册
+    cfg.service(album::list_albums)
+        .service(album::create_album)
+        .service(album::rename_album)
+        .service(album::delete_album)
+        .service(album::add_images)
+        .service(album::remove_images);
+    cfg.service(tag::list_tags).service(tag::set_tags);
     cfg.service(api::get_image);
-    cfg.service(api::list_images).service(api::delete);
-    cfg.service(api::delete);
+    cfg.service(api::list_images);
+    cfg.service(api::delete_batch);
+    cfg.service(api::delete_one);
 }
@@ -0,0 +1,85 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::schema::{album_images, albums, image_tags, images};
+
+/// images 表中的一条记录
+#[derive(Debug, Clone, Queryable, Selectable, Serialize, Deserialize, ToSchema)]
+#[diesel(table_name = images, check_for_backend(diesel::sqlite::Sqlite))]
+pub struct Image {
+    pub id: i32,
+    pub root: String,
+    /// 存储时使用的文件名（uuid + 扩展名）
+    pub name: String,
+    /// 上传时的原始文件名
+    pub original_name: String,
+    pub size: i64,
+    pub width: i32,
+    pub height: i32,
+    pub mime: String,
+    pub sha256: String,
+    pub uploaded_at: NaiveDateTime,
+    pub uploader: Option<String>,
+    pub camera_make: Option<String>,
+    pub camera_model: Option<String>,
+    pub taken_at: Option<NaiveDateTime>,
+    pub orientation: Option<i32>,
+    pub gps_latitude: Option<f64>,
+    pub gps_longitude: Option<f64>,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = images)]
+pub struct NewImage {
+    pub root: String,
+    pub name: String,
+    pub original_name: String,
+    pub size: i64,
+    pub width: i32,
+    pub height: i32,
+    pub mime: String,
+    pub sha256: String,
+    pub uploaded_at: NaiveDateTime,
+    pub uploader: Option<String>,
+    pub camera_make: Option<String>,
+    pub camera_model: Option<String>,
+    pub taken_at: Option<NaiveDateTime>,
+    pub orientation: Option<i32>,
+    pub gps_latitude: Option<f64>,
+    pub gps_longitude: Option<f64>,
+}
+
+/// 相册，只属于一个存储根
+#[derive(Debug, Clone, Queryable, Selectable, Serialize, Deserialize, ToSchema)]
+#[diesel(table_name = albums, check_for_backend(diesel::sqlite::Sqlite))]
+pub struct Album {
+    pub id: i32,
+    pub root: String,
+    pub name: String,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = albums)]
+pub struct NewAlbum<'a> {
+    pub root: &'a str,
+    pub name: &'a str,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = album_images)]
+pub struct NewAlbumImage {
+    pub album_id: i32,
+    pub image_id: i32,
+    pub added_at: NaiveDateTime,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = image_tags)]
+pub struct NewImageTag<'a> {
+    pub image_id: i32,
+    pub tag: &'a str,
+}
@@ -0,0 +1,115 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+/// 支持的图片格式，通过文件头识别，不信任客户端给出的扩展名
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageType {
+    Png,
+    Jpeg,
+    Gif,
+    Bmp,
+    Webp,
+}
+
+impl ImageType {
+    /// 根据文件头的魔数识别格式
+    pub fn sniff(header: &[u8]) -> Option<ImageType> {
+        match header {
+            [0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a, ..] => Some(ImageType::Png),
+            [0xff, 0xd8, 0xff, ..] => Some(ImageType::Jpeg),
+            [b'G', b'I', b'F', b'8', b'7' | b'9', b'a', ..] => Some(ImageType::Gif),
+            [b'B', b'M', ..] if header.len() >= 14 => Some(ImageType::Bmp),
+            [b'R', b'I', b'F', b'F', ..] if header.get(8..12) == Some(b"WEBP") => {
+                Some(ImageType::Webp)
+            }
+            _ => None,
+        }
+    }
+
+    /// 读取文件开头若干字节并识别格式
+    pub fn sniff_file(path: &Path) -> std::io::Result<Option<ImageType>> {
+        let mut header = [0u8; 16];
+        let mut file = File::open(path)?;
+        let mut len = 0;
+        while len < header.len() {
+            let n = file.read(&mut header[len..])?;
+            if n == 0 {
+                break;
+            }
+            len += n;
+        }
+        Ok(ImageType::sniff(&header[..len]))
+    }
+
+    /// 由扩展名或格式名得到类型，jpg 与 jpeg 视为同一种
+    pub fn from_name(name: &str) -> Option<ImageType> {
+        match name.to_ascii_lowercase().as_str() {
+            "png" => Some(ImageType::Png),
+            "jpg" | "jpeg" => Some(ImageType::Jpeg),
+            "gif" => Some(ImageType::Gif),
+            "bmp" => Some(ImageType::Bmp),
+            "webp" => Some(ImageType::Webp),
+            _ => None,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ImageType::Png => "png",
+            ImageType::Jpeg => "jpg",
+            ImageType::Gif => "gif",
+            ImageType::Bmp => "bmp",
+            ImageType::Webp => "webp",
+        }
+    }
+
+    pub fn mime(self) -> &'static str {
+        match self {
+            ImageType::Png => "image/png",
+            ImageType::Jpeg => "image/jpeg",
+            ImageType::Gif => "image/gif",
+            ImageType::Bmp => "image/bmp",
+            ImageType::Webp => "image/webp",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sniff() {
+        assert_eq!(
+            ImageType::sniff(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"),
+            Some(ImageType::Png)
+        );
+        assert_eq!(
+            ImageType::sniff(b"\xff\xd8\xff\xe0\0\x10JFIF"),
+            Some(ImageType::Jpeg)
+        );
+        assert_eq!(
+            ImageType::sniff(b"GIF89a\x01\0\x01\0"),
+            Some(ImageType::Gif)
+        );
+        assert_eq!(
+            ImageType::sniff(b"BM\x46\0\0\0\0\0\0\0\x36\0\0\0"),
+            Some(ImageType::Bmp)
+        );
+        assert_eq!(
+            ImageType::sniff(b"RIFF\x24\0\0\0WEBPVP8 "),
+            Some(ImageType::Webp)
+        );
+        assert_eq!(ImageType::sniff(b"RIFF\x24\0\0\0WAVEfmt "), None);
+        assert_eq!(ImageType::sniff(b"<svg xmlns="), None);
+        assert_eq!(ImageType::sniff(b"BM"), None);
+    }
+
+    #[test]
+    fn test_from_name() {
+        assert_eq!(ImageType::from_name("JPG"), Some(ImageType::Jpeg));
+        assert_eq!(ImageType::from_name("jpeg"), Some(ImageType::Jpeg));
+        assert_eq!(ImageType::from_name("svg"), None);
+    }
+}
@@ -0,0 +1,210 @@
+use actix_web::{Responder, get, put, web};
+use diesel::dsl::count_star;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::album::image_ids;
+use super::api::RootQuery;
+use super::model::NewImageTag;
+use crate::base::Response;
+use crate::db::Database;
+use crate::error::AppError;
+use crate::openapi::tags;
+use crate::schema::{image_tags, images};
+use crate::storage::{Operation, UserStorage};
+
+const MAX_TAG_LEN: usize = 50;
+const MAX_TAGS: usize = 50;
+
+#[derive(Deserialize, ToSchema)]
+struct TagsRequest {
+    root: Option<String>,
+    tags: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+struct TagCount {
+    tag: String,
+    count: i64,
+}
+
+/// 去掉首尾空白和重复项，保持原有顺序
+fn normalize_tags(tags: Vec<String>) -> Result<Vec<String>, AppError> {
+    let mut result: Vec<String> = Vec::new();
+    for tag in tags {
+        let tag = tag.trim();
+        if tag.is_empty() {
+            continue;
+        }
+        if tag.chars().count() > MAX_TAG_LEN || tag.chars().any(char::is_control) {
+            return Err(AppError::field(
+                "tags",
+                format!("标签不能超过 {} 个字符: {}", MAX_TAG_LEN, tag),
+            ));
+        }
+        if !result.iter().any(|t| t == tag) {
+            result.push(tag.to_string());
+        }
+    }
+    if result.len() > MAX_TAGS {
+        return Err(AppError::field(
+            "tags",
+            format!("每张图片最多 {} 个标签", MAX_TAGS),
+        ));
+    }
+    Ok(result)
+}
+
+/// 用请求中的标签替换图片原有的全部标签
+#[utoipa::path(tag = tags::IMAGES, responses((status = 200, body = Response<Vec<String>>)))]
+#[put("/{name}/tags")]
+async fn set_tags(
+    name: web::Path<String>,
+    req: web::Json<TagsRequest>,
+    storage: UserStorage,
+    db: web::Data<Database>,
+) -> Result<impl Responder, AppError> {
+    let name = name.into_inner();
+    let req = req.into_inner();
+    let tags = normalize_tags(req.tags)?;
+    let root_id = storage
+        .image_root(req.root.as_deref(), Operation::Write)?
+        .id
+        .clone();
+    let tags = web::block(move || -> Result<Vec<String>, AppError> {
+        let mut conn = db.connect()?;
+        conn.transaction(|conn| {
+            let image_id = image_ids(conn, &root_id, std::slice::from_ref(&name))?[0];
+            diesel::delete(image_tags::table.filter(image_tags::image_id.eq(image_id)))
+                .execute(conn)?;
+            let rows: Vec<NewImageTag> = tags
+                .iter()
+                .map(|tag| NewImageTag { image_id, tag })
+                .collect();
+            diesel::insert_into(image_tags::table)
+                .values(&rows)
+                .execute(conn)?;
+            Ok::<_, AppError>(())
+        })?;
+        Ok(tags)
+    })
+    .await??;
+    Ok(Response::ok(tags))
+}
+
+/// 存储根中用到的全部标签及其图片数量
+#[utoipa::path(
+    tag = tags::IMAGES,
+    params(RootQuery),
+    responses((status = 200, body = Response<Vec<TagCount>>))
+)]
+#[get("/tags")]
+async fn list_tags(
+    query: web::Query<RootQuery>,
+    storage: UserStorage,
+    db: web::Data<Database>,
+) -> Result<impl Responder, AppError> {
+    let root_id = storage
+        .image_root(query.root.as_deref(), Operation::Read)?
+        .id
+        .clone();
+    let tags = web::block(move || -> Result<Vec<TagCount>, AppError> {
+        let mut conn = db.connect()?;
+        Ok(image_tags::table
+            .inner_join(images::table)
+            .filter(images::root.eq(&root_id))
+            .group_by(image_tags::tag)
+            .select((image_tags::tag, count_star()))
+            .order(image_tags::tag.asc())
+            .load::<(String, i64)>(&mut conn)?
+            .into_iter()
+            .map(|(tag, count)| TagCount { tag, count })
+            .collect())
+    })
+    .await??;
+    Ok(Response::ok(tags))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth_api::role::Role;
+    use crate::db::TestDb;
+    use crate::middleware::auth::CurrentUser;
+    use crate::storage::Storage;
+    use actix_web::dev::Service;
+    use actix_web::http::StatusCode;
+    use actix_web::test::{self, TestRequest};
+    use actix_web::{App, HttpMessage};
+    use chrono::Utc;
+    use serde_json::{Value, json};
+
+    #[test]
+    fn test_normalize_tags() {
+        let tags = normalize_tags(vec![" docs ".into(), "".into(), "docs".into(), "ui".into()]);
+        assert_eq!(tags.unwrap(), vec!["docs", "ui"]);
+        assert!(normalize_tags(vec!["x".repeat(MAX_TAG_LEN + 1)]).is_err());
+    }
+
+    /// 设置标签会替换原有标签，标签列表只统计当前存储根中的图片
+    #[actix_web::test]
+    async fn test_set_and_list_tags() {
+        let test_db = TestDb::new();
+        for (root, name) in [("images", "a.png"), ("images", "b.png"), ("files", "a.png")] {
+            diesel::insert_into(images::table)
+                .values((
+                    images::root.eq(root),
+                    images::name.eq(name),
+                    images::original_name.eq(name),
+                    images::size.eq(1i64),
+                    images::width.eq(1),
+                    images::height.eq(1),
+                    images::mime.eq("image/png"),
+                    images::sha256.eq(name),
+                    images::uploaded_at.eq(Utc::now().naive_utc()),
+                ))
+                .execute(&mut test_db.conn())
+                .unwrap();
+        }
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(Storage::for_test(&test_db.dir)))
+                .app_data(web::Data::new(test_db.db.clone()))
+                .wrap_fn(|req, srv| {
+                    req.extensions_mut()
+                        .insert(CurrentUser::for_test(Role::Admin));
+                    srv.call(req)
+                })
+                .service(list_tags)
+                .service(set_tags),
+        )
+        .await;
+        let set = |uri: &str, tags: Value| {
+            let req = TestRequest::put().uri(uri).set_json(tags).to_request();
+            let res = test::call_service(&app, req);
+            async move {
+                let res = res.await;
+                let status = res.status();
+                let body: Value = test::read_body_json(res).await;
+                (status, body["data"].clone())
+            }
+        };
+
+        let (status, tags) = set("/a.png/tags", json!({"tags": ["ui", " docs ", "ui"]})).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(tags, json!(["ui", "docs"]));
+        set("/a.png/tags", json!({"tags": ["docs", "logo"]})).await;
+        set("/b.png/tags", json!({"tags": ["docs"]})).await;
+        set("/a.png/tags", json!({"root": "files", "tags": ["docs"]})).await;
+        let (status, _) = set("/x.png/tags", json!({"tags": ["docs"]})).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let req = TestRequest::get().uri("/tags").to_request();
+        let body: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(
+            body["data"],
+            json!([{"tag": "docs", "count": 2}, {"tag": "logo", "count": 1}])
+        );
+    }
+}
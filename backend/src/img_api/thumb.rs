@@ -0,0 +1,264 @@
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::PngEncoder;
+use image::codecs::webp::WebPEncoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageReader};
+use serde::Deserialize;
+use std::fs;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use utoipa::ToSchema;
+
+use super::sniff::ImageType;
+use crate::error::AppError;
+
+/// 缩略图允许的最大边长，防止请求生成超大图片
+pub const MAX_DIMENSION: u32 = 4096;
+const JPEG_QUALITY: u8 = 85;
+
+/// 缩放方式
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Default, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Fit {
+    /// 等比缩放到框内，不裁剪
+    #[default]
+    Contain,
+    /// 等比缩放后居中裁剪，填满整个框
+    Cover,
+    /// 拉伸到指定尺寸
+    Fill,
+}
+
+impl Fit {
+    fn name(self) -> &'static str {
+        match self {
+            Fit::Contain => "contain",
+            Fit::Cover => "cover",
+            Fit::Fill => "fill",
+        }
+    }
+}
+
+/// 输出格式
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    Png,
+    #[serde(alias = "jpg")]
+    Jpeg,
+    Webp,
+}
+
+impl OutputFormat {
+    /// 未指定格式时沿用原图格式，GIF/BMP 转为 PNG
+    fn from_source(source: ImageType) -> OutputFormat {
+        match source {
+            ImageType::Jpeg => OutputFormat::Jpeg,
+            ImageType::Webp => OutputFormat::Webp,
+            _ => OutputFormat::Png,
+        }
+    }
+
+    pub fn image_type(self) -> ImageType {
+        match self {
+            OutputFormat::Png => ImageType::Png,
+            OutputFormat::Jpeg => ImageType::Jpeg,
+            OutputFormat::Webp => ImageType::Webp,
+        }
+    }
+}
+
+/// 一次缩放请求的参数
+#[derive(Debug, Clone, Copy)]
+pub struct ThumbSpec {
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub fit: Fit,
+    pub format: Option<OutputFormat>,
+}
+
+impl ThumbSpec {
+    pub fn validate(&self) -> Result<(), AppError> {
+        for v in [self.width, self.height].into_iter().flatten() {
+            if v == 0 || v > MAX_DIMENSION {
+                return Err(AppError::InvalidParam(format!(
+                    "宽高必须在 1~{} 之间",
+                    MAX_DIMENSION
+                )));
+            }
+        }
+        if self.fit != Fit::Contain && (self.width.is_none() || self.height.is_none()) {
+            return Err(AppError::InvalidParam(
+                "cover/fill 需要同时指定 w 和 h".into(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// 缓存文件名，由全部参数决定
+    fn cache_name(&self, format: OutputFormat) -> String {
+        let dim = |v: Option<u32>| v.map(|v| v.to_string()).unwrap_or_else(|| "auto".into());
+        format!(
+            "{}x{}-{}.{}",
+            dim(self.width),
+            dim(self.height),
+            self.fit.name(),
+            format.image_type().extension()
+        )
+    }
+}
+
+/// 某张原图的缩略图缓存目录：{cache_dir}/{root_id}/{name}/
+pub fn cache_dir_for(cache_dir: &Path, root_id: &str, name: &str) -> PathBuf {
+    cache_dir.join(root_id).join(name)
+}
+
+/// 删除某张原图的全部缓存
+pub fn invalidate(cache_dir: &Path, root_id: &str, name: &str) -> std::io::Result<()> {
+    match fs::remove_dir_all(cache_dir_for(cache_dir, root_id, name)) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+fn resize(img: DynamicImage, spec: &ThumbSpec) -> DynamicImage {
+    let (w, h) = (img.width(), img.height());
+    match (spec.fit, spec.width, spec.height) {
+        (Fit::Cover, Some(tw), Some(th)) => img.resize_to_fill(tw, th, FilterType::Lanczos3),
+        (Fit::Fill, Some(tw), Some(th)) => img.resize_exact(tw, th, FilterType::Lanczos3),
+        (_, tw, th) => {
+            // 只给一边时按比例计算另一边；不放大原图
+            let tw = tw.unwrap_or(u32::MAX).min(w);
+            let th = th.unwrap_or(u32::MAX).min(h);
+            if tw >= w && th >= h {
+                img
+            } else {
+                img.resize(tw, th, FilterType::Lanczos3)
+            }
+        }
+    }
+}
+
+fn encode(img: &DynamicImage, format: OutputFormat, out: impl Write) -> Result<(), AppError> {
+    let map_err = |e: image::ImageError| AppError::Unknown(format!("图片编码失败: {}", e));
+    match format {
+        OutputFormat::Png => img
+            .write_with_encoder(PngEncoder::new(out))
+            .map_err(map_err),
+        // JPEG 不支持透明通道
+        OutputFormat::Jpeg => img
+            .to_rgb8()
+            .write_with_encoder(JpegEncoder::new_with_quality(out, JPEG_QUALITY))
+            .map_err(map_err),
+        // image 自带的 WebP 编码器只支持无损模式，且只接受 8 位 RGB/RGBA
+        OutputFormat::Webp => {
+            let rgba = img.to_rgba8();
+            rgba.write_with_encoder(WebPEncoder::new_lossless(out))
+                .map_err(map_err)
+        }
+    }
+}
+
+/// 返回缩略图路径和格式；缓存不存在或比原图旧时重新生成
+pub fn thumbnail(
+    original: &Path,
+    cache_dir: &Path,
+    spec: &ThumbSpec,
+) -> Result<(PathBuf, ImageType), AppError> {
+    let source = ImageType::sniff_file(original)?
+        .ok_or_else(|| AppError::InvalidParam("不是支持的图片格式".into()))?;
+    let format = spec.format.unwrap_or(OutputFormat::from_source(source));
+    let cached = cache_dir.join(spec.cache_name(format));
+    let original_mtime = fs::metadata(original)?.modified()?;
+    if let Ok(meta) = fs::metadata(&cached)
+        && meta.modified()? >= original_mtime
+    {
+        return Ok((cached, format.image_type()));
+    }
+
+    let img = ImageReader::open(original)?
+        .with_guessed_format()?
+        .decode()
+        .map_err(|e| AppError::InvalidParam(format!("图片解码失败: {}", e)))?;
+    let img = resize(img, spec);
+
+    fs::create_dir_all(cache_dir)?;
+    // 先写临时文件再改名，并发请求同一尺寸时不会读到半个文件
+    let tmp = cache_dir.join(format!(".{}.tmp", uuid::Uuid::new_v4()));
+    let result = fs::File::create(&tmp)
+        .map_err(AppError::from)
+        .and_then(|f| {
+            let mut out = BufWriter::new(f);
+            encode(&img, format, &mut out)?;
+            out.flush()?;
+            Ok(())
+        })
+        .and_then(|_| fs::rename(&tmp, &cached).map_err(AppError::from));
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    result.map(|_| (cached, format.image_type()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, RgbImage};
+
+    fn spec(width: Option<u32>, height: Option<u32>, fit: Fit) -> ThumbSpec {
+        ThumbSpec {
+            width,
+            height,
+            fit,
+            format: None,
+        }
+    }
+
+    #[test]
+    fn test_resize() {
+        let img = DynamicImage::ImageRgb8(RgbImage::from_pixel(400, 200, Rgb([255, 0, 0])));
+        let out = resize(img.clone(), &spec(Some(100), None, Fit::Contain));
+        assert_eq!((out.width(), out.height()), (100, 50));
+        let out = resize(img.clone(), &spec(Some(100), Some(100), Fit::Cover));
+        assert_eq!((out.width(), out.height()), (100, 100));
+        let out = resize(img.clone(), &spec(Some(100), Some(100), Fit::Fill));
+        assert_eq!((out.width(), out.height()), (100, 100));
+        // contain 不放大
+        let out = resize(img, &spec(Some(1000), None, Fit::Contain));
+        assert_eq!((out.width(), out.height()), (400, 200));
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(spec(Some(100), None, Fit::Contain).validate().is_ok());
+        assert!(spec(Some(100), None, Fit::Cover).validate().is_err());
+        assert!(spec(Some(0), None, Fit::Contain).validate().is_err());
+        assert!(
+            spec(Some(MAX_DIMENSION + 1), None, Fit::Contain)
+                .validate()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_thumbnail_cache() {
+        let tmp = std::env::temp_dir().join(format!("rpanel-thumb-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&tmp).unwrap();
+        let original = tmp.join("a.png");
+        RgbImage::from_pixel(64, 32, Rgb([0, 128, 255]))
+            .save(&original)
+            .unwrap();
+        let cache = cache_dir_for(&tmp.join("cache"), "images", "a.png");
+        let mut s = spec(Some(16), None, Fit::Contain);
+        s.format = Some(OutputFormat::Webp);
+
+        let (path, kind) = thumbnail(&original, &cache, &s).unwrap();
+        assert_eq!(kind, ImageType::Webp);
+        assert_eq!(ImageType::sniff_file(&path).unwrap(), Some(ImageType::Webp));
+        assert_eq!(image::image_dimensions(&path).unwrap(), (16, 8));
+
+        invalidate(&tmp.join("cache"), "images", "a.png").unwrap();
+        assert!(!path.exists());
+        fs::remove_dir_all(&tmp).unwrap();
+    }
+}
@@ -1,58 +1,621 @@
 // backend 库主入口
+pub mod admin;
+mod audit_api;
+mod auth_api;
 mod base;
+pub mod config;
+mod db;
 mod error;
 mod file_api;
+mod frontend;
 mod img_api;
+mod lifecycle;
 mod middleware;
+mod openapi;
+mod schema;
+mod share_api;
+pub mod storage;
 mod system_info;
-use actix_web::middleware::Logger;
-use actix_web::{App, HttpServer};
+mod tls;
+mod user_api;
+use actix_multipart::form::MultipartFormConfig;
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceFactory, ServiceRequest, ServiceResponse};
+use actix_web::middleware::{Condition, DefaultHeaders, Logger, from_fn};
+use actix_web::{App, HttpServer, web};
+use auth_api::role::Permission;
+use config::Config;
+use db::Database;
 use env_logger::Env;
+use frontend::Frontend;
+use log::{info, warn};
+use middleware::permission::RequirePermission;
+use middleware::rate_limit::RateLimiter;
+use sd_notify::NotifyState;
+use share_api::sign::UrlSigner;
+use std::time::Duration;
+use storage::Storage;
 use thiserror::Error;
+use utoipa_actix_web::service_config::ServiceConfig;
+use utoipa_actix_web::{AppExt, scope};
+use utoipa_swagger_ui::SwaggerUi;
+/// 返回在 X-Version 响应头中的版本号
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+/// 没有匹配的路由时返回的 404 说明
+const NO_ROUTE: &str = "接口不存在";
+
 #[derive(Debug)]
 pub struct Server {
-    host: String,
-    port: u16,
+    config: Config,
 }
 
 #[derive(Error, Debug)]
 pub enum ServerError {
     #[error("invalid port: {0}")]
     InvalidPort(u16),
+    #[error("invalid config: {0}")]
+    Config(String),
+    /// 命令行管理操作失败
+    #[error("{0}")]
+    Command(String),
 }
 
 impl Server {
-    pub fn new(host: String, port: u16) -> Self {
-        Server { host, port }
+    /// 监听地址、worker 数量等都取自配置，配置应由 Config::load_with 逐层加载
+    pub fn from_config(config: Config) -> Self {
+        Server { config }
     }
 
     pub async fn run(self) -> std::io::Result<()> {
-        use crate::file_api;
-        use crate::system_info;
-        env_logger::init_from_env(Env::default().default_filter_or("info"));
-        use crate::base::Response;
-        use actix_web::web;
-        HttpServer::new(|| {
-            App::new()
-                .wrap(actix_web::middleware::DefaultHeaders::new().add(("X-Version", "0.1")))
-                .wrap(Logger::default())
-                .service(
-                    web::scope("/v1")
-                        .service(web::scope("/system_info").configure(system_info::handle))
-                        .service(web::scope("/file").configure(file_api::handle))
-                        .service(web::scope("/img").configure(img_api::handle)),
-                )
-                .default_service(web::route().to(|| async {
-                    Response::<()> {
-                        data: None,
-                        msg: "Not Found".to_string(),
-                        code: 404,
-                    }
-                }))
+        env_logger::init_from_env(Env::default().default_filter_or(&self.config.server.log_level));
+        self.config
+            .validate()
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+        let storage = Storage::from_config(&self.config.storage)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+        let storage = web::Data::new(storage);
+        let db = Database::open(&self.config.database)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+        let password = auth_api::ensure_admin(&db)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+        if let Some(password) = password {
+            // 日志会被 journald 等收集，密码只写入仅服务用户可读的文件
+            let path = auth_api::write_initial_password(&self.config.database, &password)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+            warn!(
+                "Created initial user admin, its password is in {}; delete the file after changing the password",
+                path.display()
+            );
+        }
+        // 后台任务在 HTTP 服务停止后统一通知退出
+        let (stop, stop_signal) = tokio::sync::watch::channel(false);
+        let mut tasks = Vec::new();
+        if self.config.features.audit {
+            tasks.extend(audit_api::spawn_pruner(
+                db.clone(),
+                self.config.audit.retention_days,
+                stop_signal.clone(),
+            ));
+        }
+        let db = web::Data::new(db);
+        let signer = UrlSigner::load_or_create(&self.config.share.key_file)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+        let signer = web::Data::new(signer);
+        let frontend = Frontend::from_config(&self.config)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+        let frontend = web::Data::new(frontend);
+        let limiter = RateLimiter::new(&self.config.rate_limit)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+        let limiter = web::Data::new(limiter);
+        let tls_config = if self.config.tls.enabled {
+            let resolver = tls::CertResolver::load(&self.config.tls)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+            let resolver = std::sync::Arc::new(resolver);
+            let reloader = tls::spawn_reloader(
+                resolver.clone(),
+                self.config.tls.reload_interval_secs,
+                stop_signal.clone(),
+            )
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+            tasks.push(reloader);
+            let tls_config = tls::server_config(resolver)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+            Some(tls_config)
+        } else {
+            None
+        };
+        let host = self.config.server.host.clone();
+        let port = self.config.server.port;
+        let redirect_port = self.config.tls.redirect_port;
+        let workers = self.config.server.workers;
+        let shutdown_timeout = self.config.server.shutdown_timeout_secs;
+        let config = web::Data::new(self.config);
+        let server = HttpServer::new(move || {
+            app(
+                storage.clone(),
+                config.clone(),
+                db.clone(),
+                signer.clone(),
+                frontend.clone(),
+                limiter.clone(),
+            )
+        })
+        .workers(workers)
+        .shutdown_timeout(shutdown_timeout)
+        // 退出信号由 lifecycle 处理，以便先通知 systemd
+        .disable_signals();
+        // 由 systemd 套接字激活时使用传入的套接字，第二个套接字用于 HTTP 重定向
+        let mut inherited = lifecycle::inherited_listeners()?.into_iter();
+        let main_listener = inherited.next();
+        let redirect_listener = inherited.next();
+        let server = match (tls_config, main_listener) {
+            (Some(tls_config), Some(listener)) => {
+                let server = server.listen_rustls_0_23(listener, tls_config)?;
+                match redirect_listener {
+                    Some(listener) if redirect_port.is_some() => server.listen(listener)?,
+                    _ => server,
+                }
+            }
+            (Some(tls_config), None) => {
+                let server = server.bind_rustls_0_23((host.clone(), port), tls_config)?;
+                match redirect_port {
+                    Some(redirect_port) => server.bind((host, redirect_port))?,
+                    None => server,
+                }
+            }
+            (None, Some(listener)) => server.listen(listener)?,
+            (None, None) => server.bind((host, port))?,
+        };
+        let server = server.run();
+        let signals = lifecycle::spawn_signal_handler(server.handle())?;
+        tasks.extend(lifecycle::spawn_watchdog(stop_signal));
+        lifecycle::notify(NotifyState::Ready);
+        let result = server.await;
+        signals.abort();
+
+        info!("HTTP server stopped, waiting for background tasks");
+        let grace = Duration::from_secs(shutdown_timeout);
+        file_api::stop_jobs(grace).await;
+        lifecycle::stop_tasks(stop, tasks, grace).await;
+        info!("Shutdown complete");
+        result
+    }
+}
+
+/// 完整的应用，包括全部中间件和路由；接口文档由注册的路由生成
+fn app(
+    storage: web::Data<Storage>,
+    config: web::Data<Config>,
+    db: web::Data<Database>,
+    signer: web::Data<UrlSigner>,
+    frontend: web::Data<Frontend>,
+    limiter: web::Data<RateLimiter>,
+) -> App<
+    impl ServiceFactory<
+        ServiceRequest,
+        Config = (),
+        Response = ServiceResponse<impl MessageBody>,
+        Error = actix_web::Error,
+        InitError = (),
+    >,
+> {
+    let (app, api) = App::new()
+        .into_utoipa_app()
+        .openapi(openapi::base())
+        .app_data(storage)
+        .app_data(config.clone())
+        .app_data(db)
+        .app_data(signer)
+        .app_data(frontend)
+        .app_data(limiter)
+        .app_data(base::json_config())
+        .app_data(base::query_config())
+        .app_data(base::path_config())
+        // 接口文档和登录接口不要求已登录，需在 /v1 之前注册
+        .configure(|cfg| {
+            if config.features.docs {
+                cfg.route("/v1/openapi.json", web::get().to(openapi::openapi_json));
+            }
+        })
+        .service(
+            scope("/v1/auth")
+                .wrap(Condition::new(
+                    config.features.audit,
+                    from_fn(middleware::audit::audit),
+                ))
+                .wrap(Condition::new(
+                    config.features.rate_limit,
+                    from_fn(middleware::rate_limit::limit_user),
+                ))
+                .wrap(from_fn(middleware::auth::load_session))
+                .configure(auth_api::handle),
+        )
+        .service(
+            scope("/v1")
+                .wrap(Condition::new(
+                    config.features.audit,
+                    from_fn(middleware::audit::audit),
+                ))
+                .wrap(Condition::new(
+                    config.features.rate_limit,
+                    from_fn(middleware::rate_limit::limit_user),
+                ))
+                .wrap(from_fn(middleware::auth::require_login))
+                .configure(|cfg| v1_routes(cfg, &config)),
+        )
+        .configure(|cfg| {
+            if config.features.shares {
+                cfg.service(scope("/s").configure(share_api::public));
+            }
         })
-        .workers(2)
-        .bind((self.host, self.port))?
-        .run()
-        .await
+        .split_for_parts();
+    app.app_data(web::Data::new(openapi::finish(api, &config.auth)))
+        .configure(|cfg| {
+            if config.features.docs {
+                cfg.service(
+                    SwaggerUi::new("/docs/{_:.*}")
+                        .config(utoipa_swagger_ui::Config::from("/v1/openapi.json")),
+                );
+            }
+        })
+        .wrap(Condition::new(
+            config.features.version_header,
+            DefaultHeaders::new().add(("X-Version", VERSION)),
+        ))
+        .wrap(Condition::new(
+            config.features.rate_limit,
+            from_fn(middleware::rate_limit::limit_ip),
+        ))
+        .wrap(from_fn(middleware::request_id::assign))
+        // 在默认格式后加上请求 ID
+        .wrap(Logger::new(
+            r#"%a "%r" %s %b "%{Referer}i" "%{User-Agent}i" %T %{x-request-id}o"#,
+        ))
+        // HTTP 端口只负责重定向，放在最外层
+        .wrap(Condition::new(
+            config.tls.enabled && config.tls.redirect_port.is_some(),
+            from_fn(middleware::https::redirect_https),
+        ))
+        // 其它路径交给前端页面
+        .default_service(web::route().to(frontend::serve))
+}
+
+/// /v1 下需要登录的接口，按功能开关注册
+fn v1_routes(cfg: &mut ServiceConfig, config: &Config) {
+    cfg.service(
+        scope("/system_info")
+            .wrap(RequirePermission::all(Permission::SystemRead))
+            .configure(system_info::handle),
+    );
+    if config.features.files {
+        cfg.service(
+            scope("/file")
+                .wrap(RequirePermission::new(
+                    Permission::FilesRead,
+                    Permission::FilesWrite,
+                ))
+                .configure(file_api::handle),
+        );
+    }
+    if config.features.images {
+        cfg.service(
+            scope("/img")
+                .wrap(RequirePermission::new(
+                    Permission::ImagesRead,
+                    Permission::ImagesWrite,
+                ))
+                .app_data(
+                    MultipartFormConfig::default()
+                        .total_limit(config.image.max_request_bytes as usize),
+                )
+                .configure(img_api::handle),
+        );
+    }
+    if config.features.shares {
+        cfg.service(
+            scope("/share")
+                .wrap(RequirePermission::all(Permission::SharesManage))
+                .configure(share_api::handle),
+        );
+    }
+    cfg.service(
+        scope("/users")
+            .wrap(RequirePermission::all(Permission::UsersManage))
+            .configure(user_api::handle),
+    );
+    if config.features.audit {
+        cfg.service(
+            scope("/audit")
+                .wrap(RequirePermission::all(Permission::AuditRead))
+                .configure(audit_api::handle),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth_api::session::create_session;
+    use crate::base::Response;
+    use crate::db::TestDb;
+    use crate::error::{AppError, code};
+    use crate::middleware::request_id::REQUEST_ID;
+    use actix_web::cookie::Cookie;
+    use actix_web::dev::Service;
+    use actix_web::http::{Method, StatusCode};
+    use actix_web::test::{self, TestRequest};
+    use serde_json::{Value, json};
+
+    /// 存储根、数据库和分享密钥都放在临时目录中的默认配置
+    fn test_config(test_db: &TestDb) -> Config {
+        let mut config = Config::default();
+        config.database = test_db.config.clone();
+        for root in &mut config.storage.roots {
+            root.path = test_db.dir.join(&root.id);
+        }
+        config.share.key_file = test_db.dir.join("share.key");
+        // 不使用打包的前端，测试结果不受是否构建了前端影响
+        let web_dir = test_db.dir.join("web");
+        std::fs::create_dir_all(&web_dir).unwrap();
+        config.web.dir = Some(web_dir);
+        // 测试中大量请求来自同一地址，不限流
+        config.features.rate_limit = false;
+        config
+    }
+
+    fn test_app(
+        config: Config,
+        db: Database,
+    ) -> App<
+        impl ServiceFactory<
+            ServiceRequest,
+            Config = (),
+            Response = ServiceResponse<impl MessageBody>,
+            Error = actix_web::Error,
+            InitError = (),
+        >,
+    > {
+        let storage = Storage::from_config(&config.storage).unwrap();
+        let signer = UrlSigner::load_or_create(&config.share.key_file).unwrap();
+        let frontend = Frontend::from_config(&config).unwrap();
+        let limiter = RateLimiter::new(&config.rate_limit).unwrap();
+        app(
+            web::Data::new(storage),
+            web::Data::new(config),
+            web::Data::new(db),
+            web::Data::new(signer),
+            web::Data::new(frontend),
+            web::Data::new(limiter),
+        )
+    }
+
+    /// 中间件直接返回的错误由服务器转换为响应，这里做同样的转换；
+    /// 每个响应都应带有请求 ID，且与响应体中的 request_id 相同
+    async fn read(
+        res: Result<ServiceResponse<impl MessageBody + 'static>, actix_web::Error>,
+    ) -> (StatusCode, Response<Value>) {
+        let res = match res {
+            Ok(res) => res.into_parts().1.map_into_boxed_body(),
+            Err(e) => e.error_response(),
+        };
+        let status = res.status();
+        let id = res
+            .headers()
+            .get(&REQUEST_ID)
+            .map(|v| v.to_str().unwrap().to_string());
+        let body = actix_web::body::to_bytes(res.into_body()).await.unwrap();
+        let body: Response<Value> = serde_json::from_slice(&body).unwrap();
+        assert!(id.is_some());
+        assert_eq!(body.request_id, id);
+        (status, body)
+    }
+
+    /// 所有接口的成功和失败响应都使用同一格式，失败时 code 与 AppError 变体一一对应
+    #[actix_web::test]
+    async fn test_envelope_contract() {
+        let test_db = TestDb::new();
+        let config = test_config(&test_db);
+        let cookie_name = config.auth.cookie_name.clone();
+        let session = |username: &str, role: &str| {
+            let id = test_db.create_user(username, role);
+            let (token, _) = create_session(&mut test_db.conn(), id, 60, None, None).unwrap();
+            Cookie::new(cookie_name.clone(), token)
+        };
+        let admin = session("admin", "admin");
+        let viewer = session("alice", "viewer");
+        let other = session("bob", "viewer");
+        let app = test::init_service(test_app(config, test_db.db.clone())).await;
+        let call = |req: TestRequest| test::try_call_service(&app, req.to_request());
+
+        let (status, body) =
+            read(call(TestRequest::get().uri("/v1/auth/me").cookie(admin.clone())).await).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body.code, code::SUCCESS);
+        assert_eq!(body.msg, "Success");
+        assert_eq!(body.data.unwrap()["username"], "admin");
+        assert!(body.errors.is_empty());
+
+        // 没有返回值的接口 data 为 null
+        let req = TestRequest::post().uri("/v1/auth/logout").cookie(other);
+        let (status, body) = read(call(req).await).await;
+        assert_eq!((status, body.code), (StatusCode::OK, code::SUCCESS));
+        assert_eq!(body.data, None);
+
+        let cases = [
+            (
+                TestRequest::get().uri("/v1/users"),
+                StatusCode::UNAUTHORIZED,
+                code::UNAUTHORIZED,
+            ),
+            (
+                TestRequest::get().uri("/v1/auth/me"),
+                StatusCode::UNAUTHORIZED,
+                code::UNAUTHORIZED,
+            ),
+            (
+                TestRequest::get().uri("/v1/users").cookie(viewer),
+                StatusCode::FORBIDDEN,
+                code::FORBIDDEN,
+            ),
+            (
+                TestRequest::get().uri("/no/such/api"),
+                StatusCode::NOT_FOUND,
+                code::NOT_FOUND,
+            ),
+            (
+                TestRequest::delete()
+                    .uri("/v1/users/999")
+                    .cookie(admin.clone()),
+                StatusCode::NOT_FOUND,
+                code::NOT_FOUND,
+            ),
+            (
+                TestRequest::post()
+                    .uri("/v1/users")
+                    .cookie(admin.clone())
+                    .set_json(
+                        json!({"username": "admin", "password": "password1", "role": "viewer"}),
+                    ),
+                StatusCode::CONFLICT,
+                code::CONFLICT,
+            ),
+            (
+                TestRequest::post()
+                    .uri("/v1/auth/tokens")
+                    .cookie(admin.clone())
+                    .insert_header(("content-type", "application/json"))
+                    .set_payload("{"),
+                StatusCode::BAD_REQUEST,
+                code::INVALID_PARAM,
+            ),
+            (
+                TestRequest::get()
+                    .uri("/v1/audit?page=x")
+                    .cookie(admin.clone()),
+                StatusCode::BAD_REQUEST,
+                code::INVALID_PARAM,
+            ),
+        ];
+        for (req, expected_status, expected_code) in cases {
+            let (status, body) = read(call(req).await).await;
+            assert_eq!(
+                (status, body.code),
+                (expected_status, expected_code),
+                "{}",
+                body.msg
+            );
+            assert_eq!(body.data, None);
+        }
+
+        // 校验失败时列出全部有问题的字段
+        let req = TestRequest::post()
+            .uri("/v1/auth/tokens")
+            .cookie(admin.clone())
+            .set_json(json!({"name": " ", "scopes": [], "expires_in": 0}));
+        let (status, body) = read(call(req).await).await;
+        assert_eq!(
+            (status, body.code),
+            (StatusCode::BAD_REQUEST, code::VALIDATION)
+        );
+        let fields: Vec<&str> = body.errors.iter().map(|e| e.field.as_str()).collect();
+        assert_eq!(fields, ["name", "scopes", "expires_in"]);
+
+        // 缺少字段时指出字段名
+        let req = TestRequest::post()
+            .uri("/v1/auth/tokens")
+            .cookie(admin)
+            .set_json(json!({"scopes": ["files_read"]}));
+        let (_, body) = read(call(req).await).await;
+        assert_eq!(body.code, code::VALIDATION);
+        assert_eq!(body.errors[0].field, "name");
+    }
+
+    /// 文档中的每个接口都能路由到 handler，且路径参数都有说明；接口改名或删除而文档未同步时失败。
+    /// 反方向由编译器保证：各模块通过 utoipa 的 ServiceConfig 注册，没有 #[utoipa::path] 的 handler 无法注册
+    #[actix_web::test]
+    async fn test_openapi_matches_routes() {
+        let test_db = TestDb::new();
+        let config = test_config(&test_db);
+        let cookie_name = config.auth.cookie_name.clone();
+        let admin = test_db.create_user("admin", "admin");
+        let app = test::init_service(test_app(config, test_db.db.clone())).await;
+
+        let req = TestRequest::get().uri("/v1/openapi.json").to_request();
+        let spec: Value = test::call_and_read_body_json(&app, req).await;
+        for schema in ["Response_Vec_FileInfo", "Response_CpuInfo", "ErrorResponse"] {
+            assert!(
+                spec["components"]["schemas"].get(schema).is_some(),
+                "{}",
+                schema
+            );
+        }
+        let unrouted = AppError::NotFound(NO_ROUTE.into()).to_string();
+        let mut operations = 0;
+        for (path, item) in spec["paths"].as_object().unwrap() {
+            for (method, op) in item.as_object().unwrap() {
+                let name = format!("{} {}", method.to_uppercase(), path);
+                assert!(op["tags"][0].is_string(), "{} 缺少分组", name);
+                assert!(op["responses"]["200"].is_object(), "{} 缺少成功响应", name);
+                assert!(
+                    op["responses"]["default"].is_object(),
+                    "{} 缺少错误响应",
+                    name
+                );
+
+                let declared: Vec<&str> = op["parameters"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter(|p| p["in"] == "path")
+                    .map(|p| p["name"].as_str().unwrap())
+                    .collect();
+                let mut uri = path.clone();
+                for segment in path.split('/').filter(|s| s.starts_with('{')) {
+                    let param = segment.trim_matches(|c| c == '{' || c == '}');
+                    assert!(declared.contains(&param), "{} 缺少路径参数 {}", name, param);
+                    uri = uri.replace(segment, "0");
+                }
+                assert_eq!(declared.len(), path.matches('{').count(), "{}", name);
+
+                // 每个请求使用新的会话，logout 等接口不影响后续请求；
+                // 参数不完整时由 handler 返回 400，未注册的路由才会落到默认的 404
+                let (token, _) =
+                    create_session(&mut test_db.conn(), admin, 60, None, None).unwrap();
+                let req = TestRequest::default()
+                    .method(Method::from_bytes(method.to_uppercase().as_bytes()).unwrap())
+                    .uri(&uri)
+                    .cookie(Cookie::new(cookie_name.clone(), token))
+                    .to_request();
+                let res = match app.call(req).await {
+                    Ok(res) => res.into_parts().1.map_into_boxed_body(),
+                    Err(e) => e.error_response(),
+                };
+                assert_ne!(res.status(), StatusCode::METHOD_NOT_ALLOWED, "{}", name);
+                if res.status() == StatusCode::NOT_FOUND {
+                    let body = actix_web::body::to_bytes(res.into_body()).await.unwrap();
+                    let body: Response<Value> = serde_json::from_slice(&body).unwrap();
+                    assert_ne!(body.msg, unrouted, "{} 没有对应的路由", name);
+                }
+                operations += 1;
+            }
+        }
+        assert!(operations > 50, "{}", operations);
+
+        let req = TestRequest::get().uri("/docs/").to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+        // 关闭 docs 后文档接口不存在
+        let mut config = test_config(&test_db);
+        config.features.docs = false;
+        let app = test::init_service(test_app(config, test_db.db.clone())).await;
+        let (token, _) = create_session(&mut test_db.conn(), admin, 60, None, None).unwrap();
+        for uri in ["/v1/openapi.json", "/docs/"] {
+            let req = TestRequest::get()
+                .uri(uri)
+                .cookie(Cookie::new(cookie_name.clone(), token.clone()))
+                .to_request();
+            let (status, _) = read(test::try_call_service(&app, req).await).await;
+            assert_eq!(status, StatusCode::NOT_FOUND, "{}", uri);
+        }
     }
 }
@@ -0,0 +1,100 @@
+// 服务生命周期：systemd 通知、看门狗、套接字激活和退出信号
+use actix_web::dev::ServerHandle;
+use log::{info, warn};
+use sd_notify::NotifyState;
+use std::net::TcpListener;
+use std::os::fd::FromRawFd;
+use std::time::Duration;
+use tokio::signal::unix::{SignalKind, signal};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+/// 后台任务的停止通知，值变为 true 或发送端被丢弃时任务应退出
+pub type StopSignal = watch::Receiver<bool>;
+
+/// 向 systemd 报告状态，未由 systemd 启动时（没有 NOTIFY_SOCKET）什么也不做
+pub fn notify(state: NotifyState) {
+    if let Err(e) = sd_notify::notify(false, &[state]) {
+        warn!("Failed to notify service manager: {}", e);
+    }
+}
+
+/// 由 systemd 套接字激活传入的监听套接字，按 .socket 单元中 ListenStream 的顺序排列
+pub fn inherited_listeners() -> std::io::Result<Vec<TcpListener>> {
+    let listeners: Vec<TcpListener> = sd_notify::listen_fds()?
+        // SAFETY: listen_fds 只在 LISTEN_PID 与本进程一致时返回描述符，这些描述符由 systemd 传入，
+        // 不被进程中其他代码持有，只在这里转换一次
+        .map(|fd| unsafe { TcpListener::from_raw_fd(fd) })
+        .collect();
+    for listener in &listeners {
+        listener.set_nonblocking(true)?;
+    }
+    Ok(listeners)
+}
+
+/// 启用了 WatchdogSec 时，按超时时间的一半定期发送 WATCHDOG=1
+pub fn spawn_watchdog(mut stop: StopSignal) -> Option<JoinHandle<()>> {
+    let mut usec = 0;
+    if !sd_notify::watchdog_enabled(false, &mut usec) {
+        return None;
+    }
+    let period = Duration::from_micros(usec / 2);
+    info!("Watchdog enabled, pinging every {:?}", period);
+    Some(tokio::spawn(async move {
+        let mut interval = tokio::time::interval(period);
+        loop {
+            tokio::select! {
+                _ = interval.tick() => notify(NotifyState::Watchdog),
+                _ = stop.changed() => break,
+            }
+        }
+    }))
+}
+
+/// 收到 SIGTERM / SIGINT 时停止接受新连接，等待处理中的请求完成；SIGQUIT 立即退出
+pub fn spawn_signal_handler(handle: ServerHandle) -> std::io::Result<JoinHandle<()>> {
+    let mut term = signal(SignalKind::terminate())?;
+    let mut int = signal(SignalKind::interrupt())?;
+    let mut quit = signal(SignalKind::quit())?;
+    Ok(tokio::spawn(async move {
+        let (name, graceful) = tokio::select! {
+            _ = term.recv() => ("SIGTERM", true),
+            _ = int.recv() => ("SIGINT", true),
+            _ = quit.recv() => ("SIGQUIT", false),
+        };
+        info!("Received {}, shutting down", name);
+        notify(NotifyState::Stopping);
+        handle.stop(graceful).await;
+    }))
+}
+
+/// 通知后台任务停止并等待其退出，超过 timeout 的任务不再等待
+pub async fn stop_tasks(stop: watch::Sender<bool>, tasks: Vec<JoinHandle<()>>, timeout: Duration) {
+    let _ = stop.send(true);
+    let all = futures_util::future::join_all(tasks);
+    if tokio::time::timeout(timeout, all).await.is_err() {
+        warn!("Background tasks did not stop within {:?}", timeout);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[actix_web::test]
+    async fn test_stop_tasks() {
+        let (tx, mut rx) = watch::channel(false);
+        let task = tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(3600));
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {}
+                    _ = rx.changed() => break,
+                }
+            }
+        });
+        let started = std::time::Instant::now();
+        stop_tasks(tx, vec![task], Duration::from_secs(5)).await;
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+}
@@ -0,0 +1,232 @@
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::{Method, header};
+use actix_web::middleware::Next;
+use actix_web::{Error, HttpMessage, web};
+use chrono::Utc;
+use futures_util::StreamExt;
+use log::warn;
+use serde_json::{Map, Value};
+
+use super::auth::CurrentUser;
+use crate::audit_api::model::NewAuditLog;
+use crate::audit_api::record;
+use crate::config::Config;
+use crate::db::Database;
+
+/// 字段名包含这些词时记录为 ***
+const SENSITIVE_KEYS: &[&str] = &["password", "token", "secret", "code", "challenge"];
+/// 超过该长度的字符串只记录长度，避免把文本文件内容写进审计日志
+const MAX_STRING_LEN: usize = 256;
+
+/// 脱敏并截断请求参数
+fn sanitize(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (key, v) in map.iter_mut() {
+                let key = key.to_lowercase();
+                if SENSITIVE_KEYS.iter().any(|k| key.contains(k)) {
+                    *v = Value::String("***".into());
+                } else {
+                    sanitize(v);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(sanitize),
+        Value::String(s) if s.len() > MAX_STRING_LEN => {
+            *s = format!("<{} bytes>", s.len());
+        }
+        _ => {}
+    }
+}
+
+/// 读取不超过 limit 字节的 JSON 请求体，读取后放回请求供 handler 使用
+async fn capture_body(req: &mut ServiceRequest, limit: usize) -> Option<Value> {
+    let is_json = req
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("application/json"));
+    let length = req
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok()?.parse::<usize>().ok())?;
+    if !is_json || length > limit {
+        return None;
+    }
+    let mut payload = req.take_payload();
+    let mut body = web::BytesMut::with_capacity(length);
+    while let Some(chunk) = payload.next().await {
+        match chunk {
+            Ok(chunk) => body.extend_from_slice(&chunk),
+            Err(_) => break,
+        }
+    }
+    let body = body.freeze();
+    let value = serde_json::from_slice(&body).ok();
+    req.set_payload(body.into());
+    value
+}
+
+/// 查询参数和请求体合并为一个 JSON 对象，都没有时为 None
+fn params(query: &str, body: Option<Value>) -> Option<String> {
+    let mut map = Map::new();
+    if !query.is_empty() {
+        let pairs: Map<String, Value> = web::Query::<Vec<(String, String)>>::from_query(query)
+            .map(|q| {
+                q.into_inner()
+                    .into_iter()
+                    .map(|(k, v)| (k, Value::String(v)))
+                    .collect()
+            })
+            .unwrap_or_default();
+        map.insert("query".into(), Value::Object(pairs));
+    }
+    if let Some(body) = body {
+        map.insert("body".into(), body);
+    }
+    if map.is_empty() {
+        return None;
+    }
+    let mut value = Value::Object(map);
+    sanitize(&mut value);
+    Some(value.to_string())
+}
+
+/// 把路径中与路由参数相同的段替换为 {参数名}，得到与请求方法对应的路由模板
+///
+/// HttpRequest::match_pattern 不区分请求方法，同一路径下有多个方法的路由时会返回错误的模板
+fn route_template(path: &str, params: &[(String, String)]) -> String {
+    let mut template = path.to_string();
+    for (name, value) in params.iter().rev() {
+        if value.is_empty() {
+            continue;
+        }
+        let placeholder = format!("{{{}}}", name);
+        if value.contains('/') {
+            if let Some(prefix) = template.strip_suffix(value.as_str()) {
+                template = format!("{}{}", prefix, placeholder);
+            }
+            continue;
+        }
+        let mut segments: Vec<&str> = template.split('/').collect();
+        if let Some(i) = segments.iter().rposition(|s| s == value) {
+            segments[i] = &placeholder;
+            template = segments.join("/");
+        }
+    }
+    template
+}
+
+/// 记录所有修改类请求（GET/HEAD/OPTIONS 以外）的操作人、来源、参数和结果
+///
+/// 需放在认证中间件之内才能取得操作人；写入失败只记录日志，不影响请求本身
+pub async fn audit(
+    mut req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    if matches!(*req.method(), Method::GET | Method::HEAD | Method::OPTIONS) {
+        return next.call(req).await;
+    }
+    let Some(db) = req.app_data::<web::Data<Database>>().cloned() else {
+        return next.call(req).await;
+    };
+    let limit = req
+        .app_data::<web::Data<Config>>()
+        .map(|c| c.audit.max_body_bytes)
+        .unwrap_or_default();
+    let body = capture_body(&mut req, limit).await;
+    let user = req
+        .extensions()
+        .get::<CurrentUser>()
+        .map(|u| (u.id, u.username.clone()));
+    let mut entry = NewAuditLog {
+        created_at: Utc::now().naive_utc(),
+        user_id: user.as_ref().map(|u| u.0),
+        username: user.map(|u| u.1),
+        ip: req.peer_addr().map(|a| a.ip().to_string()),
+        method: req.method().to_string(),
+        action: String::new(),
+        target: req.path().to_string(),
+        params: params(req.query_string(), body),
+        status: 0,
+        success: false,
+        error: None,
+    };
+
+    let result = next.call(req).await;
+    // 路由完成后响应中的请求才有全部路径参数；中间件直接返回错误时只能记录原始路径
+    let (status, error, route_params) = match &result {
+        Ok(res) => (
+            res.status(),
+            res.response().error().map(|e| e.to_string()),
+            res.request()
+                .match_info()
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        ),
+        Err(e) => (
+            e.as_response_error().status_code(),
+            Some(e.to_string()),
+            Vec::new(),
+        ),
+    };
+    entry.action = route_template(&entry.target, &route_params);
+    entry.status = status.as_u16() as i32;
+    entry.success = status.is_success();
+    entry.error = error;
+    if let Err(e) = db.run(move |conn| record(conn, &entry)).await {
+        warn!("Failed to write audit log: {}", e);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_params() {
+        let body = serde_json::json!({
+            "username": "alice",
+            "new_password": "hunter22",
+            "content": "x".repeat(MAX_STRING_LEN + 1),
+            "grants": [{"root": "files", "access": "read"}],
+        });
+        let params: Value =
+            serde_json::from_str(&params("root=files", Some(body)).unwrap()).unwrap();
+        assert_eq!(params["query"]["root"], "files");
+        assert_eq!(params["body"]["username"], "alice");
+        assert_eq!(params["body"]["new_password"], "***");
+        assert_eq!(
+            params["body"]["content"],
+            format!("<{} bytes>", MAX_STRING_LEN + 1)
+        );
+        assert_eq!(params["body"]["grants"][0]["root"], "files");
+        assert_eq!(super::params("", None), None);
+    }
+
+    #[test]
+    fn test_route_template() {
+        let p = |pairs: &[(&str, &str)]| -> Vec<(String, String)> {
+            pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect()
+        };
+        assert_eq!(
+            route_template("/v1/img/a.png", &p(&[("name", "a.png")])),
+            "/v1/img/{name}"
+        );
+        assert_eq!(
+            route_template("/v1/users/1/grants", &p(&[("id", "1")])),
+            "/v1/users/{id}/grants"
+        );
+        assert_eq!(
+            route_template("/v1/img/thumb/a/b.png", &p(&[("path", "a/b.png")])),
+            "/v1/img/thumb/{path}"
+        );
+        assert_eq!(route_template("/v1/img/delete", &[]), "/v1/img/delete");
+    }
+}
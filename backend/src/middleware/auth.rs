@@ -0,0 +1,253 @@
+use actix_web::body::MessageBody;
+use actix_web::dev::{Payload, ServiceRequest, ServiceResponse};
+use actix_web::http::header;
+use actix_web::middleware::Next;
+use actix_web::{Error, FromRequest, HttpMessage, HttpRequest, web};
+use chrono::Utc;
+use diesel::prelude::*;
+use std::collections::HashMap;
+use std::future::{Ready, ready};
+
+use crate::auth_api::model::{RootGrant, User};
+use crate::auth_api::role::{Access, Permission, Role};
+use crate::auth_api::session::session_user;
+use crate::auth_api::token::token_user;
+use crate::config::Config;
+use crate::db::Database;
+use crate::error::AppError;
+use crate::schema::root_grants;
+use crate::storage::Operation;
+
+/// 当前登录用户，由认证中间件放入请求扩展，handler 可直接作为参数提取
+#[derive(Debug, Clone)]
+pub struct CurrentUser {
+    pub id: i32,
+    pub username: String,
+    pub role: Role,
+    /// 存储根 id -> 授权，管理员不受限制
+    pub grants: HashMap<String, Access>,
+    /// 通过 API 令牌认证时为令牌的权限范围，会话登录时为 None
+    pub scopes: Option<Vec<Permission>>,
+    /// 管理员要求启用两步验证但用户尚未启用
+    pub totp_pending: bool,
+}
+
+impl CurrentUser {
+    /// 读取用户的角色和存储根授权
+    pub fn load(conn: &mut SqliteConnection, user: User) -> Result<CurrentUser, AppError> {
+        let grants = root_grants::table
+            .filter(root_grants::user_id.eq(user.id))
+            .select(RootGrant::as_select())
+            .load(conn)?
+            .into_iter()
+            .filter_map(|g| Some((g.root, g.access.parse().ok()?)))
+            .collect();
+        Ok(CurrentUser {
+            id: user.id,
+            username: user.username,
+            role: user.role.parse()?,
+            grants,
+            scopes: None,
+            totp_pending: user.totp_required && !user.totp_enabled,
+        })
+    }
+
+    /// 角色和令牌范围都包含该权限时才允许
+    pub fn require(&self, permission: Permission) -> Result<(), AppError> {
+        if !self.role.has(permission) {
+            return Err(AppError::Forbidden(format!("需要 {:?} 权限", permission)));
+        }
+        if let Some(scopes) = &self.scopes
+            && !scopes.contains(&permission)
+        {
+            return Err(AppError::Forbidden(format!(
+                "令牌范围不包含 {:?} 权限",
+                permission
+            )));
+        }
+        Ok(())
+    }
+
+    /// 修改密码、创建令牌等操作只允许通过登录会话执行
+    pub fn require_session(&self) -> Result<(), AppError> {
+        if self.scopes.is_some() {
+            return Err(AppError::Forbidden("API 令牌不能执行此操作".into()));
+        }
+        Ok(())
+    }
+
+    /// 检查用户是否被授权在存储根上执行 op
+    pub fn check_root(&self, root_id: &str, op: Operation) -> Result<(), AppError> {
+        if self.role == Role::Admin {
+            return Ok(());
+        }
+        match self.grants.get(root_id) {
+            Some(access) if access.allows(op) => Ok(()),
+            _ => Err(AppError::Forbidden(format!(
+                "未授权在存储根 {} 上执行 {:?} 操作",
+                root_id, op
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+impl CurrentUser {
+    /// 通过会话登录、没有存储根授权的测试用户
+    pub fn for_test(role: Role) -> CurrentUser {
+        CurrentUser {
+            id: 1,
+            username: role.as_str().into(),
+            role,
+            grants: HashMap::new(),
+            scopes: None,
+            totp_pending: false,
+        }
+    }
+}
+
+impl FromRequest for CurrentUser {
+    type Error = AppError;
+    type Future = Ready<Result<Self, AppError>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(
+            req.extensions()
+                .get::<CurrentUser>()
+                .cloned()
+                .ok_or(AppError::Unauthorized),
+        )
+    }
+}
+
+enum Credential {
+    Bearer(String),
+    Session(String),
+}
+
+/// 优先使用 Authorization: Bearer 中的 API 令牌，其次是会话 Cookie
+fn credential(req: &ServiceRequest, config: &Config) -> Option<Credential> {
+    if let Some(value) = req.headers().get(header::AUTHORIZATION) {
+        let token = value
+            .to_str()
+            .ok()
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .unwrap_or_default();
+        return Some(Credential::Bearer(token.trim().to_string()));
+    }
+    req.cookie(&config.auth.cookie_name)
+        .map(|c| Credential::Session(c.value().to_string()))
+}
+
+/// 按 API 令牌或会话 Cookie 识别用户；没有凭据或凭据无效时返回 None
+async fn authenticate(req: &ServiceRequest) -> Result<Option<CurrentUser>, AppError> {
+    let config = req
+        .app_data::<web::Data<Config>>()
+        .ok_or_else(|| AppError::Unknown("缺少配置".into()))?;
+    let Some(credential) = credential(req, config) else {
+        return Ok(None);
+    };
+    let db = req
+        .app_data::<web::Data<Database>>()
+        .cloned()
+        .ok_or_else(|| AppError::Unknown("缺少数据库".into()))?;
+    web::block(move || {
+        let mut conn = db.connect()?;
+        let now = Utc::now().naive_utc();
+        match credential {
+            Credential::Bearer(token) => match token_user(&mut conn, &token, now)? {
+                Some((user, scopes)) => {
+                    let mut user = CurrentUser::load(&mut conn, user)?;
+                    user.scopes = Some(scopes);
+                    Ok(Some(user))
+                }
+                None => Ok(None),
+            },
+            Credential::Session(token) => match session_user(&mut conn, &token, now)? {
+                Some(user) => Ok(Some(CurrentUser::load(&mut conn, user)?)),
+                None => Ok(None),
+            },
+        }
+    })
+    .await?
+}
+
+/// 识别登录状态但不拦截请求，用于登录接口所在的 scope
+pub async fn load_session(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    if let Some(user) = authenticate(&req).await? {
+        req.extensions_mut().insert(user);
+    }
+    next.call(req).await
+}
+
+/// 未登录时直接返回 401；被要求启用两步验证而尚未启用时返回 403，只能访问 /v1/auth 下的接口完成设置
+pub async fn require_login(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let user = authenticate(&req).await?.ok_or(AppError::Unauthorized)?;
+    if user.totp_pending {
+        return Err(AppError::Forbidden("需要先启用两步验证".into()).into());
+    }
+    req.extensions_mut().insert(user);
+    next.call(req).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth_api::session::create_session;
+    use crate::db::TestDb;
+    use actix_web::cookie::Cookie;
+    use actix_web::middleware::from_fn;
+    use actix_web::{App, HttpResponse, test};
+
+    async fn whoami(user: CurrentUser) -> HttpResponse {
+        HttpResponse::Ok().body(user.username)
+    }
+
+    #[actix_web::test]
+    async fn test_require_login() {
+        let test_db = TestDb::new();
+        let id = test_db.create_user("alice", "viewer");
+        let (token, _) = create_session(&mut test_db.conn(), id, 60, None, None).unwrap();
+
+        let config = Config::default();
+        let cookie_name = config.auth.cookie_name.clone();
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(config))
+                .app_data(web::Data::new(test_db.db.clone()))
+                .service(
+                    web::scope("/v1")
+                        .wrap(from_fn(require_login))
+                        .route("/me", web::get().to(whoami)),
+                ),
+        )
+        .await;
+
+        // 中间件返回的错误由服务器转换为响应，这里直接检查错误对应的状态码
+        let status = |res: Result<ServiceResponse, Error>| match res {
+            Ok(res) => res.status(),
+            Err(e) => e.as_response_error().status_code(),
+        };
+        let req = test::TestRequest::get().uri("/v1/me").to_request();
+        assert_eq!(status(test::try_call_service(&app, req).await), 401);
+
+        let req = test::TestRequest::get()
+            .uri("/v1/me")
+            .cookie(Cookie::new(cookie_name.clone(), "bogus"))
+            .to_request();
+        assert_eq!(status(test::try_call_service(&app, req).await), 401);
+
+        let req = test::TestRequest::get()
+            .uri("/v1/me")
+            .cookie(Cookie::new(cookie_name, token))
+            .to_request();
+        let body = test::call_and_read_body(&app, req).await;
+        assert_eq!(body, "alice");
+    }
+}
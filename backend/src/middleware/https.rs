@@ -0,0 +1,68 @@
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header;
+use actix_web::middleware::Next;
+use actix_web::{Error, HttpResponse, web};
+
+use crate::config::Config;
+
+/// 去掉 Host 中的端口，换成 HTTPS 端口，443 时省略
+fn https_location(host: &str, port: u16, path: &str) -> String {
+    let name = match host.strip_prefix('[') {
+        // IPv6 地址，如 [::1]:8080
+        Some(rest) => rest
+            .split_once(']')
+            .map_or(host, |(addr, _)| &host[..addr.len() + 2]),
+        None => host.split_once(':').map_or(host, |(name, _)| name),
+    };
+    if port == 443 {
+        format!("https://{}{}", name, path)
+    } else {
+        format!("https://{}:{}{}", name, port, path)
+    }
+}
+
+/// 把 HTTP 连接上的请求永久重定向到 HTTPS 端口，HTTPS 连接上的请求直接放行
+pub async fn redirect_https(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, Error> {
+    if req.app_config().secure() {
+        return next.call(req).await.map(|res| res.map_into_left_body());
+    }
+    let port = req
+        .app_data::<web::Data<Config>>()
+        .map(|c| c.server.port)
+        .unwrap_or(443);
+    let path = req
+        .uri()
+        .path_and_query()
+        .map_or("/", |p| p.as_str())
+        .to_string();
+    let location = https_location(req.connection_info().host(), port, &path);
+    let res = HttpResponse::PermanentRedirect()
+        .insert_header((header::LOCATION, location))
+        .finish();
+    Ok(req.into_response(res).map_into_right_body())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_https_location() {
+        assert_eq!(
+            https_location("panel.example.com", 443, "/v1/auth/me"),
+            "https://panel.example.com/v1/auth/me"
+        );
+        assert_eq!(
+            https_location("panel.example.com:8080", 8443, "/a?b=1"),
+            "https://panel.example.com:8443/a?b=1"
+        );
+        assert_eq!(
+            https_location("[::1]:8080", 8443, "/"),
+            "https://[::1]:8443/"
+        );
+    }
+}
@@ -1 +1,6 @@
-
+pub mod audit;
+pub mod auth;
+pub mod https;
+pub mod permission;
+pub mod rate_limit;
+pub mod request_id;
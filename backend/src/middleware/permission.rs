@@ -0,0 +1,136 @@
+use actix_web::dev::{Service, ServiceRequest, ServiceResponse, Transform, forward_ready};
+use actix_web::http::Method;
+use actix_web::{Error, HttpMessage};
+use futures_util::future::LocalBoxFuture;
+use std::future::{Ready, ready};
+
+use super::auth::CurrentUser;
+use crate::auth_api::role::Permission;
+use crate::error::AppError;
+
+/// 声明 scope 所需的权限：GET/HEAD 请求需要 read，其它方法需要 write
+///
+/// 必须放在认证中间件之内，未登录时返回 401，权限不足时返回 403
+#[derive(Debug, Clone, Copy)]
+pub struct RequirePermission {
+    read: Permission,
+    write: Permission,
+}
+
+impl RequirePermission {
+    pub fn new(read: Permission, write: Permission) -> Self {
+        RequirePermission { read, write }
+    }
+
+    /// 所有方法都需要同一权限
+    pub fn all(permission: Permission) -> Self {
+        RequirePermission::new(permission, permission)
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for RequirePermission
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = RequirePermissionMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequirePermissionMiddleware {
+            service,
+            require: *self,
+        }))
+    }
+}
+
+pub struct RequirePermissionMiddleware<S> {
+    service: S,
+    require: RequirePermission,
+}
+
+impl<S, B> Service<ServiceRequest> for RequirePermissionMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let permission = if matches!(*req.method(), Method::GET | Method::HEAD) {
+            self.require.read
+        } else {
+            self.require.write
+        };
+        let allowed = match req.extensions().get::<CurrentUser>() {
+            Some(user) => user.require(permission),
+            None => Err(AppError::Unauthorized),
+        };
+        if let Err(e) = allowed {
+            return Box::pin(ready(Err(e.into())));
+        }
+        let fut = self.service.call(req);
+        Box::pin(fut)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth_api::role::Role;
+    use actix_web::{App, HttpResponse, test, web};
+    use std::collections::HashMap;
+
+    #[actix_web::test]
+    async fn test_require_permission() {
+        let app = test::init_service(
+            App::new().service(
+                web::scope("/img")
+                    .wrap(RequirePermission::new(
+                        Permission::ImagesRead,
+                        Permission::ImagesWrite,
+                    ))
+                    .wrap_fn(|req, srv| {
+                        // 带 X-User 头时模拟已登录的 viewer
+                        if req.headers().contains_key("X-User") {
+                            req.extensions_mut().insert(CurrentUser {
+                                id: 1,
+                                username: "bob".into(),
+                                role: Role::Viewer,
+                                grants: HashMap::new(),
+                                scopes: None,
+                                totp_pending: false,
+                            });
+                        }
+                        srv.call(req)
+                    })
+                    .route("", web::get().to(HttpResponse::Ok))
+                    .route("", web::post().to(HttpResponse::Ok)),
+            ),
+        )
+        .await;
+        let status = |res: Result<ServiceResponse, Error>| match res {
+            Ok(res) => res.status(),
+            Err(e) => e.as_response_error().status_code(),
+        };
+        let req = test::TestRequest::get().uri("/img").to_request();
+        assert_eq!(status(test::try_call_service(&app, req).await), 401);
+        let req = test::TestRequest::get()
+            .uri("/img")
+            .insert_header(("X-User", "1"))
+            .to_request();
+        assert_eq!(status(test::try_call_service(&app, req).await), 200);
+        let req = test::TestRequest::post()
+            .uri("/img")
+            .insert_header(("X-User", "1"))
+            .to_request();
+        assert_eq!(status(test::try_call_service(&app, req).await), 403);
+    }
+}
@@ -0,0 +1,443 @@
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::{Method, StatusCode};
+use actix_web::middleware::Next;
+use actix_web::{Error, HttpMessage, web};
+use log::warn;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use super::auth::CurrentUser;
+use crate::ServerError;
+use crate::config::{BucketConfig, RateLimitConfig};
+use crate::error::AppError;
+
+/// 受登录限制的接口
+const LOGIN_PATHS: [&str; 2] = ["/v1/auth/login", "/v1/auth/login/totp"];
+/// 记录数超过该值时清理已恢复满的令牌桶和过期的失败记录
+const PRUNE_THRESHOLD: usize = 10_000;
+
+/// IP 地址或网段，如 192.168.1.10、10.0.0.0/8、fd00::/8
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IpNet {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl IpNet {
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.addr, ip.to_canonical()) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix as u32).unwrap_or(0);
+                u32::from(net) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix as u32).unwrap_or(0);
+                u128::from(net) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for IpNet {
+    type Err = ServerError;
+
+    /// ::ffff:a.b.c.d 形式的地址按 IPv4 处理
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ServerError::Config(format!("invalid address or network: {}", s));
+        let (addr, prefix) = match s.split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix)),
+            None => (s, None),
+        };
+        let parsed: IpAddr = addr.trim().parse().map_err(|_| invalid())?;
+        let mapped = parsed.is_ipv6() && parsed.to_canonical().is_ipv4();
+        let addr = parsed.to_canonical();
+        let max = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(prefix) => {
+                let prefix: u8 = prefix.trim().parse().map_err(|_| invalid())?;
+                // ::ffff:0:0/96 之类的前缀按 IPv6 长度书写
+                let prefix = if mapped {
+                    prefix.checked_sub(96).ok_or_else(invalid)?
+                } else {
+                    prefix
+                };
+                if prefix > max {
+                    return Err(invalid());
+                }
+                prefix
+            }
+            None => max,
+        };
+        Ok(IpNet { addr, prefix })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Key {
+    Ip(IpAddr),
+    Login(IpAddr),
+    User(i32),
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+impl Bucket {
+    fn full(config: BucketConfig, now: Instant) -> Self {
+        Bucket {
+            tokens: config.burst as f64,
+            updated_at: now,
+        }
+    }
+
+    fn refill(&mut self, config: BucketConfig, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated_at).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * config.per_sec).min(config.burst as f64);
+        self.updated_at = now;
+    }
+
+    /// 取一个令牌；没有令牌时返回需要等待的秒数，至少 1 秒
+    fn take(&mut self, config: BucketConfig, now: Instant) -> Result<(), u64> {
+        self.refill(config, now);
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            return Ok(());
+        }
+        let wait = (1.0 - self.tokens) / config.per_sec;
+        Err((wait.ceil() as u64).max(1))
+    }
+}
+
+/// 同一 IP 在统计窗口内的登录失败次数
+#[derive(Debug)]
+struct Failures {
+    count: u32,
+    since: Instant,
+    locked_until: Option<Instant>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    buckets: HashMap<Key, Bucket>,
+    failures: HashMap<IpAddr, Failures>,
+}
+
+/// 令牌桶和登录失败记录都只保存在内存中，重启后清空
+#[derive(Debug)]
+pub struct RateLimiter {
+    config: RateLimitConfig,
+    allow_list: Vec<IpNet>,
+    state: Mutex<State>,
+}
+
+fn secs_until(deadline: Instant, now: Instant) -> u64 {
+    deadline
+        .saturating_duration_since(now)
+        .as_secs_f64()
+        .ceil()
+        .max(1.0) as u64
+}
+
+impl RateLimiter {
+    pub fn new(config: &RateLimitConfig) -> Result<Self, ServerError> {
+        let allow_list = config
+            .allow_list
+            .iter()
+            .map(|s| s.parse())
+            .collect::<Result<_, _>>()?;
+        Ok(RateLimiter {
+            config: config.clone(),
+            allow_list,
+            state: Mutex::new(State::default()),
+        })
+    }
+
+    fn allowed(&self, ip: IpAddr) -> bool {
+        self.allow_list.iter().any(|net| net.contains(ip))
+    }
+
+    fn lockout(&self) -> Duration {
+        Duration::from_secs(self.config.lockout_secs)
+    }
+
+    fn bucket_config(&self, key: &Key) -> BucketConfig {
+        match key {
+            Key::Ip(_) => self.config.ip,
+            Key::Login(_) => self.config.auth,
+            Key::User(_) => self.config.user,
+        }
+    }
+
+    fn take(&self, key: Key, now: Instant) -> Result<(), u64> {
+        let config = self.bucket_config(&key);
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if state.buckets.len() >= PRUNE_THRESHOLD {
+            self.prune(&mut state, now);
+        }
+        state
+            .buckets
+            .entry(key)
+            .or_insert_with(|| Bucket::full(config, now))
+            .take(config, now)
+    }
+
+    /// 恢复满的令牌桶与新建的没有区别，可以直接丢弃
+    fn prune(&self, state: &mut State, now: Instant) {
+        state.buckets.retain(|key, bucket| {
+            let config = self.bucket_config(key);
+            bucket.refill(config, now);
+            bucket.tokens < config.burst as f64
+        });
+        let lockout = self.lockout();
+        state.failures.retain(|_, f| match f.locked_until {
+            Some(until) => until > now,
+            None => now.saturating_duration_since(f.since) < lockout,
+        });
+    }
+
+    /// 登录前检查：IP 被锁定或请求过于频繁时返回需要等待的秒数
+    fn check_login(&self, ip: IpAddr, now: Instant) -> Result<(), u64> {
+        {
+            let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+            if let Some(until) = state.failures.get(&ip).and_then(|f| f.locked_until)
+                && until > now
+            {
+                return Err(secs_until(until, now));
+            }
+        }
+        self.take(Key::Login(ip), now)
+    }
+
+    /// 统计窗口内失败次数达到上限时锁定该 IP
+    fn login_failed(&self, ip: IpAddr, now: Instant) {
+        let max = self.config.max_login_failures;
+        if max == 0 {
+            return;
+        }
+        let lockout = self.lockout();
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let failures = state.failures.entry(ip).or_insert(Failures {
+            count: 0,
+            since: now,
+            locked_until: None,
+        });
+        if failures.locked_until.is_some()
+            || now.saturating_duration_since(failures.since) >= lockout
+        {
+            *failures = Failures {
+                count: 0,
+                since: now,
+                locked_until: None,
+            };
+        }
+        failures.count += 1;
+        if failures.count >= max {
+            failures.locked_until = Some(now + lockout);
+            warn!(
+                "Locked out {} for {}s after {} failed logins",
+                ip, self.config.lockout_secs, failures.count
+            );
+        }
+    }
+
+    fn login_succeeded(&self, ip: IpAddr) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.failures.remove(&ip);
+    }
+}
+
+fn limiter(req: &ServiceRequest) -> Result<web::Data<RateLimiter>, AppError> {
+    req.app_data::<web::Data<RateLimiter>>()
+        .cloned()
+        .ok_or_else(|| AppError::Unknown("缺少限流器".into()))
+}
+
+/// 按对端 IP 限制全部请求；登录接口另按更严格的令牌桶限制，
+/// 登录失败过多时在一段时间内拒绝该 IP 的登录请求。白名单中的地址不受限制
+pub async fn limit_ip(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let limiter = limiter(&req)?;
+    let Some(ip) = req.peer_addr().map(|a| a.ip().to_canonical()) else {
+        return next.call(req).await;
+    };
+    if limiter.allowed(ip) {
+        return next.call(req).await;
+    }
+    let now = Instant::now();
+    limiter
+        .take(Key::Ip(ip), now)
+        .map_err(AppError::TooManyRequests)?;
+    let login = req.method() == Method::POST && LOGIN_PATHS.contains(&req.path());
+    if !login {
+        return next.call(req).await;
+    }
+    limiter
+        .check_login(ip, now)
+        .map_err(AppError::TooManyRequests)?;
+    let res = next.call(req).await?;
+    match res.status() {
+        StatusCode::UNAUTHORIZED => limiter.login_failed(ip, Instant::now()),
+        status if status.is_success() => limiter.login_succeeded(ip),
+        _ => {}
+    }
+    Ok(res)
+}
+
+/// 按已登录用户限制请求，需放在识别登录状态的中间件之内；未登录的请求只受 IP 限制
+pub async fn limit_user(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let user = req.extensions().get::<CurrentUser>().map(|u| u.id);
+    if let Some(id) = user {
+        let limiter = limiter(&req)?;
+        let exempt = req.peer_addr().is_some_and(|a| limiter.allowed(a.ip()));
+        if !exempt {
+            limiter
+                .take(Key::User(id), Instant::now())
+                .map_err(AppError::TooManyRequests)?;
+        }
+    }
+    next.call(req).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::dev::Service;
+    use actix_web::http::header;
+    use actix_web::middleware::from_fn;
+    use actix_web::test::{TestRequest, init_service};
+    use actix_web::{App, HttpResponse};
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_ip_net() {
+        let net: IpNet = "10.0.0.0/8".parse().unwrap();
+        assert!(net.contains(ip("10.1.2.3")));
+        assert!(net.contains(ip("::ffff:10.1.2.3")));
+        assert!(!net.contains(ip("11.0.0.1")));
+        assert!(!net.contains(ip("::1")));
+
+        let host: IpNet = "::ffff:127.0.0.1".parse().unwrap();
+        assert!(host.contains(ip("127.0.0.1")));
+        assert!(!host.contains(ip("127.0.0.2")));
+        let any: IpNet = "0.0.0.0/0".parse().unwrap();
+        assert!(any.contains(ip("8.8.8.8")));
+        let v6: IpNet = "fd00::/8".parse().unwrap();
+        assert!(v6.contains(ip("fd12::1")));
+        assert!(!v6.contains(ip("fe80::1")));
+
+        for s in [
+            "10.0.0.0/33",
+            "::/129",
+            "localhost",
+            "10.0.0.0/",
+            "::ffff:0:0/90",
+        ] {
+            assert!(s.parse::<IpNet>().is_err(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn test_bucket() {
+        let config = BucketConfig {
+            burst: 2,
+            per_sec: 0.5,
+        };
+        let now = Instant::now();
+        let mut bucket = Bucket::full(config, now);
+        assert_eq!(bucket.take(config, now), Ok(()));
+        assert_eq!(bucket.take(config, now), Ok(()));
+        assert_eq!(bucket.take(config, now), Err(2));
+        assert_eq!(bucket.take(config, now + Duration::from_secs(1)), Err(1));
+        assert_eq!(bucket.take(config, now + Duration::from_secs(2)), Ok(()));
+        // 恢复的令牌不超过 burst
+        let later = now + Duration::from_secs(3600);
+        assert_eq!(bucket.take(config, later), Ok(()));
+        assert_eq!(bucket.take(config, later), Ok(()));
+        assert!(bucket.take(config, later).is_err());
+    }
+
+    #[test]
+    fn test_lockout() {
+        let config = RateLimitConfig {
+            max_login_failures: 3,
+            lockout_secs: 60,
+            ..Default::default()
+        };
+        let limiter = RateLimiter::new(&config).unwrap();
+        let client = ip("192.0.2.1");
+        let now = Instant::now();
+        for _ in 0..2 {
+            limiter.login_failed(client, now);
+        }
+        assert!(limiter.check_login(client, now).is_ok());
+        // 成功登录后重新计数
+        limiter.login_succeeded(client);
+        for _ in 0..2 {
+            limiter.login_failed(client, now);
+        }
+        assert!(limiter.check_login(client, now).is_ok());
+        limiter.login_failed(client, now);
+        assert_eq!(limiter.check_login(client, now), Err(60));
+        assert!(limiter.check_login(ip("192.0.2.2"), now).is_ok());
+        let later = now + Duration::from_secs(61);
+        assert!(limiter.check_login(client, later).is_ok());
+    }
+
+    #[actix_web::test]
+    async fn test_limit_ip() {
+        let config = RateLimitConfig {
+            ip: BucketConfig {
+                burst: 3,
+                per_sec: 0.01,
+            },
+            allow_list: vec!["127.0.0.1".into()],
+            ..Default::default()
+        };
+        let limiter = RateLimiter::new(&config).unwrap();
+        let app = init_service(
+            App::new()
+                .app_data(web::Data::new(limiter))
+                .wrap(from_fn(limit_ip))
+                .default_service(web::to(HttpResponse::Ok)),
+        )
+        .await;
+        let call = |addr: &str| {
+            let req = TestRequest::get()
+                .peer_addr(addr.parse().unwrap())
+                .to_request();
+            app.call(req)
+        };
+
+        for _ in 0..3 {
+            assert!(call("192.0.2.1:4000").await.is_ok());
+        }
+        let err = call("192.0.2.1:4001").await.err().unwrap();
+        let res = err.error_response();
+        assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(res.headers().get(header::RETRY_AFTER).unwrap(), "100");
+        let body = actix_web::body::to_bytes(res.into_body()).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["code"], crate::error::code::TOO_MANY_REQUESTS);
+
+        assert!(call("192.0.2.2:4000").await.is_ok());
+        for _ in 0..5 {
+            assert!(call("127.0.0.1:4000").await.is_ok());
+        }
+    }
+}
@@ -0,0 +1,156 @@
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::StatusCode;
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::middleware::Next;
+use actix_web::{Error, HttpResponse, ResponseError};
+
+pub const REQUEST_ID: HeaderName = HeaderName::from_static("x-request-id");
+/// 沿用客户端传入的请求 ID 时允许的最大长度
+const MAX_LEN: usize = 64;
+
+tokio::task_local! {
+    static CURRENT: String;
+}
+
+/// 当前请求的 ID，不在请求处理过程中时为 None
+pub fn current() -> Option<String> {
+    CURRENT.try_with(|id| id.clone()).ok()
+}
+
+/// 反向代理传入的 ID 只含字母、数字和 -_. 且不超过 64 个字符时沿用，否则生成新的
+fn request_id(req: &ServiceRequest) -> String {
+    req.headers()
+        .get(&REQUEST_ID)
+        .and_then(|v| v.to_str().ok())
+        .filter(|v| {
+            !v.is_empty()
+                && v.len() <= MAX_LEN
+                && v.chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        })
+        .map(str::to_string)
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string())
+}
+
+fn set_header(res: &mut HttpResponse<impl MessageBody>, id: &str) {
+    if let Ok(value) = HeaderValue::from_str(id) {
+        res.headers_mut().insert(REQUEST_ID, value);
+    }
+}
+
+/// 内层中间件直接返回的错误在离开本中间件后才生成响应，那时已取不到当前请求 ID，
+/// 包一层以便生成响应时带上请求 ID
+#[derive(Debug)]
+struct WithRequestId {
+    error: Error,
+    id: String,
+}
+
+impl std::fmt::Display for WithRequestId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.error.fmt(f)
+    }
+}
+
+impl ResponseError for WithRequestId {
+    fn status_code(&self) -> StatusCode {
+        self.error.as_response_error().status_code()
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let mut res = CURRENT.sync_scope(self.id.clone(), || self.error.error_response());
+        set_header(&mut res, &self.id);
+        res
+    }
+}
+
+/// 为每个请求分配 ID，处理请求期间可通过 [`current`] 取得，并写入响应头 X-Request-Id
+pub async fn assign(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let id = request_id(&req);
+    match CURRENT.scope(id.clone(), next.call(req)).await {
+        Ok(mut res) => {
+            set_header(res.response_mut(), &id);
+            Ok(res)
+        }
+        Err(error) => Err(WithRequestId { error, id }.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::dev::Service;
+    use actix_web::middleware::from_fn;
+    use actix_web::{App, test, web};
+
+    use crate::base::Response;
+    use crate::error::AppError;
+
+    #[actix_web::test]
+    async fn test_request_id() {
+        let app = test::init_service(
+            App::new()
+                .wrap(from_fn(assign))
+                .route(
+                    "/ok",
+                    web::get().to(|| async { Response::ok(current().unwrap()) }),
+                )
+                // 中间件直接返回错误时响应体和响应头中也有请求 ID
+                .service(
+                    web::scope("/denied")
+                        .wrap(from_fn(|_: ServiceRequest, _: Next<_>| async {
+                            Err::<ServiceResponse, Error>(AppError::Unauthorized.into())
+                        }))
+                        .route("", web::get().to(HttpResponse::Ok)),
+                ),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/ok")
+            .insert_header((REQUEST_ID, "abc-123"))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.headers().get(&REQUEST_ID).unwrap(), "abc-123");
+        let body: Response<String> = test::read_body_json(res).await;
+        assert_eq!(body.data.as_deref(), Some("abc-123"));
+        assert_eq!(body.request_id.as_deref(), Some("abc-123"));
+
+        // 含非法字符时重新生成
+        let req = test::TestRequest::get()
+            .uri("/ok")
+            .insert_header((REQUEST_ID, "a b"))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        let id = res
+            .headers()
+            .get(&REQUEST_ID)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_string();
+        assert_eq!(id.len(), 36);
+
+        let req = test::TestRequest::get().uri("/denied").to_request();
+        let res = app.call(req).await;
+        let res = match res {
+            Ok(res) => res.into_parts().1.map_into_boxed_body(),
+            Err(e) => e.error_response(),
+        };
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        let id = res
+            .headers()
+            .get(&REQUEST_ID)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_string();
+        let body = actix_web::body::to_bytes(res.into_body()).await.unwrap();
+        let body: Response<()> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body.request_id, Some(id));
+    }
+}
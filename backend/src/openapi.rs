@@ -0,0 +1,103 @@
+// OpenAPI 文档：路径和请求、响应类型在注册路由时收集，这里补充全局信息、认证方式和统一的错误响应
+use actix_web::{HttpResponse, web};
+use utoipa::openapi::path::Operation;
+use utoipa::openapi::security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::openapi::{ContentBuilder, OpenApi, Ref, ResponseBuilder};
+use utoipa::{OpenApi as _, ToSchema};
+
+use crate::config::AuthConfig;
+use crate::error::FieldError;
+
+/// 接口分组
+pub mod tags {
+    pub const AUTH: &str = "auth";
+    pub const USERS: &str = "users";
+    pub const SYSTEM: &str = "system";
+    pub const FILES: &str = "files";
+    pub const IMAGES: &str = "images";
+    pub const SHARES: &str = "shares";
+    pub const AUDIT: &str = "audit";
+}
+
+/// 请求失败时的响应体
+#[derive(ToSchema)]
+#[allow(dead_code)]
+struct ErrorResponse {
+    /// 应用错误码，前三位与 HTTP 状态码相同：40000 无效参数、40001 字段校验失败、40100 未登录、
+    /// 40300 权限不足、40400 资源不存在、40900 资源冲突、41000 链接已失效、41300 内容过大、
+    /// 42900 请求过于频繁、50000 未知错误、50001 IO 错误
+    code: u16,
+    msg: String,
+    /// 始终为 null
+    data: Option<String>,
+    /// 字段校验失败（40001）时列出有问题的字段
+    errors: Vec<FieldError>,
+    request_id: String,
+}
+
+#[derive(utoipa::OpenApi)]
+#[openapi(
+    info(
+        title = "rpanel",
+        description = "成功和失败的响应都使用 {code, msg, data} 格式，成功时 code 为 0。\
+            需要登录的接口可以使用登录后的会话 Cookie，或在 Authorization 头中携带 API 令牌"
+    ),
+    security(("session" = []), ("token" = [])),
+    components(schemas(ErrorResponse, FieldError))
+)]
+struct ApiDoc;
+
+/// 全局信息和认证方式，路由注册时收集的路径会合并进来
+pub fn base() -> OpenApi {
+    ApiDoc::openapi()
+}
+
+fn add_error_response(operation: &mut Operation) {
+    let response = ResponseBuilder::new()
+        .description("失败，HTTP 状态码表示错误类别")
+        .content(
+            "application/json",
+            ContentBuilder::new()
+                .schema(Some(Ref::from_schema_name("ErrorResponse")))
+                .build(),
+        )
+        .build();
+    operation
+        .responses
+        .responses
+        .entry("default".into())
+        .or_insert(response.into());
+}
+
+/// 补充认证方式，并为每个接口加上统一的错误响应
+pub fn finish(mut api: OpenApi, auth: &AuthConfig) -> OpenApi {
+    let components = api.components.get_or_insert_with(Default::default);
+    components.add_security_scheme(
+        "session",
+        SecurityScheme::ApiKey(ApiKey::Cookie(ApiKeyValue::new(&auth.cookie_name))),
+    );
+    components.add_security_scheme(
+        "token",
+        SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+    );
+    for item in api.paths.paths.values_mut() {
+        for operation in [
+            &mut item.get,
+            &mut item.put,
+            &mut item.post,
+            &mut item.delete,
+            &mut item.patch,
+            &mut item.head,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            add_error_response(operation);
+        }
+    }
+    api
+}
+
+pub async fn openapi_json(api: web::Data<OpenApi>) -> HttpResponse {
+    HttpResponse::Ok().json(api.as_ref())
+}
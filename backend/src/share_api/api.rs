@@ -0,0 +1,512 @@
+use actix_files::NamedFile;
+use actix_web::http::header::{
+    self, ContentDisposition, DispositionParam, DispositionType, HeaderValue,
+};
+use actix_web::{HttpRequest, HttpResponse, Responder, delete, get, post, web};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use utoipa::{IntoParams, ToSchema};
+
+use super::model::Share;
+use super::sign::UrlSigner;
+use crate::base::Response;
+use crate::config::{Config, ShareConfig};
+use crate::db::Database;
+use crate::error::{AppError, Validator};
+use crate::img_api::sniff::ImageType;
+use crate::openapi::tags;
+use crate::schema::shares;
+use crate::storage::{Operation, Storage, UserStorage};
+
+#[derive(Deserialize, ToSchema)]
+#[schema(as = CreateShareRequest)]
+struct CreateRequest {
+    /// 不填使用图床存储根
+    root: Option<String>,
+    path: String,
+    /// 有效期（秒），不填使用配置中的默认值
+    expires_in: Option<u64>,
+    max_downloads: Option<i32>,
+}
+
+/// 分享记录及其签名链接
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+struct ShareLink {
+    #[serde(flatten)]
+    share: Share,
+    url: String,
+}
+
+fn from_timestamp(secs: i64) -> Result<NaiveDateTime, AppError> {
+    DateTime::from_timestamp(secs, 0)
+        .map(|t| t.naive_utc())
+        .ok_or_else(|| AppError::InvalidParam("时间超出范围".into()))
+}
+
+fn link(share: Share, signer: &UrlSigner, config: &ShareConfig) -> ShareLink {
+    let expires = share.expires_at.and_utc().timestamp();
+    let url = format!(
+        "{}/s/{}?expires={}&sig={}",
+        config
+            .base_url
+            .as_deref()
+            .unwrap_or("")
+            .trim_end_matches('/'),
+        share.id,
+        expires,
+        signer.sign(&share.id, expires)
+    );
+    ShareLink { share, url }
+}
+
+#[utoipa::path(tag = tags::SHARES, responses((status = 200, body = Response<ShareLink>)))]
+#[post("")]
+async fn create_share(
+    req: web::Json<CreateRequest>,
+    storage: UserStorage,
+    config: web::Data<Config>,
+    signer: web::Data<UrlSigner>,
+    db: web::Data<Database>,
+) -> Result<impl Responder, AppError> {
+    let req = req.into_inner();
+    let share_config = &config.share;
+    let ttl = req.expires_in.unwrap_or(share_config.default_ttl_secs);
+    Validator::new()
+        .check(
+            (1..=share_config.max_ttl_secs).contains(&ttl),
+            "expires_in",
+            format!("必须在 1~{} 秒之间", share_config.max_ttl_secs),
+        )
+        .check(
+            req.max_downloads.is_none_or(|n| n >= 1),
+            "max_downloads",
+            "必须大于 0",
+        )
+        .finish()?;
+    let root = storage.image_root(req.root.as_deref(), Operation::Read)?;
+    let path = root.resolve(&req.path)?;
+    if !path.is_file() {
+        return Err(AppError::NotFound(format!("文件不存在: {}", req.path)));
+    }
+    let now = Utc::now().naive_utc();
+    let share = Share {
+        id: uuid::Uuid::new_v4().to_string(),
+        root: root.id.clone(),
+        path: root.relative(&path),
+        created_at: now,
+        // 链接中只带秒级时间戳，入库时同样截到秒
+        expires_at: from_timestamp(now.and_utc().timestamp() + ttl as i64)?,
+        max_downloads: req.max_downloads,
+        downloads: 0,
+        revoked_at: None,
+    };
+    let share = web::block(move || -> Result<Share, AppError> {
+        diesel::insert_into(shares::table)
+            .values(&share)
+            .execute(&mut db.connect()?)?;
+        Ok(share)
+    })
+    .await??;
+    Ok(Response::ok(link(share, &signer, share_config)))
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct ListQuery {
+    root: Option<String>,
+    /// 为 true 时只列出仍可下载的链接
+    #[serde(default)]
+    active: bool,
+}
+
+#[utoipa::path(
+    tag = tags::SHARES,
+    params(ListQuery),
+    responses((status = 200, body = Response<Vec<ShareLink>>))
+)]
+#[get("")]
+async fn list_shares(
+    query: web::Query<ListQuery>,
+    storage: UserStorage,
+    config: web::Data<Config>,
+    signer: web::Data<UrlSigner>,
+    db: web::Data<Database>,
+) -> Result<impl Responder, AppError> {
+    let query = query.into_inner();
+    let root_id = match &query.root {
+        Some(id) => Some(storage.root(id, Operation::Read)?.id.clone()),
+        None => None,
+    };
+    let list = web::block(move || -> Result<Vec<Share>, AppError> {
+        let mut q = shares::table.into_boxed();
+        if let Some(root_id) = root_id {
+            q = q.filter(shares::root.eq(root_id));
+        }
+        let list: Vec<Share> = q
+            .order(shares::created_at.desc())
+            .select(Share::as_select())
+            .load(&mut db.connect()?)?;
+        let now = Utc::now().naive_utc();
+        Ok(list
+            .into_iter()
+            .filter(|s| !query.active || s.unavailable(now).is_none())
+            .collect())
+    })
+    .await??;
+    // 只返回用户有读取权限的存储根上的分享
+    let list: Vec<Share> = list
+        .into_iter()
+        .filter(|s| storage.allows(&s.root, Operation::Read))
+        .collect();
+    let links: Vec<ShareLink> = list
+        .into_iter()
+        .map(|s| link(s, &signer, &config.share))
+        .collect();
+    Ok(Response::ok(links))
+}
+
+/// 撤销链接，记录保留以便查看下载次数
+#[utoipa::path(tag = tags::SHARES, responses((status = 200, body = Response<Share>)))]
+#[delete("/{id}")]
+async fn revoke_share(
+    id: web::Path<String>,
+    storage: UserStorage,
+    db: web::Data<Database>,
+) -> Result<impl Responder, AppError> {
+    let id = id.into_inner();
+    let share = web::block(move || -> Result<Share, AppError> {
+        let mut conn = db.connect()?;
+        let not_found = || AppError::NotFound(format!("分享不存在: {}", id));
+        let mut share: Share = shares::table
+            .filter(shares::id.eq(&id))
+            .select(Share::as_select())
+            .first(&mut conn)
+            .optional()?
+            .ok_or_else(not_found)?;
+        // 与列表一致，无权读取的存储根上的分享视为不存在
+        if !storage.allows(&share.root, Operation::Read) {
+            return Err(not_found());
+        }
+        if share.revoked_at.is_none() {
+            let now = Utc::now().naive_utc();
+            diesel::update(shares::table.filter(shares::id.eq(&id)))
+                .filter(shares::revoked_at.is_null())
+                .set(shares::revoked_at.eq(now))
+                .execute(&mut conn)?;
+            share.revoked_at = Some(now);
+        }
+        Ok(share)
+    })
+    .await??;
+    Ok(Response::ok(share))
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct SignedQuery {
+    expires: i64,
+    sig: String,
+}
+
+/// 不带 Range 或从第 0 字节开始的请求才算一次下载，断点续传和分段下载的后续请求不计数
+fn counts_download(req: &HttpRequest) -> bool {
+    let Some(range) = req.headers().get(header::RANGE) else {
+        return true;
+    };
+    range
+        .to_str()
+        .ok()
+        .and_then(|r| r.trim().strip_prefix("bytes="))
+        .and_then(|r| r.split(',').next())
+        .is_some_and(|r| r.trim().starts_with("0-"))
+}
+
+/// 公开的下载地址，不需要登录；校验签名、有效期和下载次数后返回文件内容
+#[utoipa::path(
+    tag = tags::SHARES,
+    params(SignedQuery),
+    security(()),
+    responses((status = 200, description = "文件内容", content_type = "application/octet-stream"))
+)]
+#[get("/{id}")]
+async fn download(
+    req: HttpRequest,
+    id: web::Path<String>,
+    query: web::Query<SignedQuery>,
+    storage: web::Data<Storage>,
+    signer: web::Data<UrlSigner>,
+    db: web::Data<Database>,
+) -> Result<HttpResponse, AppError> {
+    let id = id.into_inner();
+    if !signer.verify(&id, query.expires, &query.sig) {
+        return Err(AppError::Forbidden("签名无效".into()));
+    }
+    let expires = query.expires;
+    let counted = counts_download(&req);
+    let path = web::block(move || -> Result<PathBuf, AppError> {
+        let mut conn = db.connect()?;
+        // 立即加写锁，并发下载时不会超出次数限制
+        conn.immediate_transaction(|conn| {
+            let share: Share = shares::table
+                .filter(shares::id.eq(&id))
+                .select(Share::as_select())
+                .first(conn)
+                .optional()?
+                .ok_or_else(|| AppError::NotFound("分享不存在".into()))?;
+            if share.expires_at.and_utc().timestamp() != expires {
+                return Err(AppError::Forbidden("签名无效".into()));
+            }
+            if let Some(reason) = share.unavailable(Utc::now().naive_utc()) {
+                return Err(AppError::Gone(reason.into()));
+            }
+            // 文件不存在时不消耗下载次数
+            let root = storage.root(&share.root, Operation::Read)?;
+            let path = root.resolve(&share.path)?;
+            if !path.is_file() {
+                return Err(AppError::NotFound("文件不存在".into()));
+            }
+            if counted {
+                diesel::update(shares::table.filter(shares::id.eq(&id)))
+                    .set(shares::downloads.eq(shares::downloads + 1))
+                    .execute(conn)?;
+            }
+            Ok(path)
+        })
+    })
+    .await??;
+
+    let file_name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let mut file = NamedFile::open_async(&path).await?;
+    // 图片按识别出的格式内联显示；其它文件一律作为附件下载，避免浏览器执行分享出去的 HTML
+    file = match ImageType::sniff_file(&path)? {
+        Some(image_type) => file
+            .set_content_type(image_type.mime().parse().expect("valid mime"))
+            .set_content_disposition(ContentDisposition {
+                disposition: DispositionType::Inline,
+                parameters: vec![DispositionParam::Filename(file_name)],
+            }),
+        None => file.set_content_disposition(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename(file_name)],
+        }),
+    };
+    let mut response = file.into_response(&req);
+    response.headers_mut().insert(
+        header::X_CONTENT_TYPE_OPTIONS,
+        HeaderValue::from_static("nosniff"),
+    );
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::TestDb;
+    use crate::middleware::auth::CurrentUser;
+    use actix_web::HttpMessage;
+    use actix_web::http::StatusCode;
+    use actix_web::test::{self, TestRequest};
+    use actix_web::{App, dev::Service};
+    use chrono::Duration;
+
+    fn share(now: NaiveDateTime) -> Share {
+        Share {
+            id: "x".into(),
+            root: "files".into(),
+            path: "a.log".into(),
+            created_at: now,
+            expires_at: now + Duration::hours(1),
+            max_downloads: Some(2),
+            downloads: 0,
+            revoked_at: None,
+        }
+    }
+
+    #[test]
+    fn test_unavailable() {
+        let now = Utc::now().naive_utc();
+        let mut s = share(now);
+        assert_eq!(s.unavailable(now), None);
+        assert_eq!(s.unavailable(now + Duration::hours(2)), Some("已过期"));
+        s.downloads = 2;
+        assert_eq!(s.unavailable(now), Some("下载次数已用完"));
+        s.downloads = 0;
+        s.revoked_at = Some(now);
+        assert_eq!(s.unavailable(now), Some("已撤销"));
+    }
+
+    /// 没有 files 授权的操作员不能撤销 files 上的分享，图床上的分享不受存储根授权限制
+    #[actix_web::test]
+    async fn test_revoke() {
+        let test_db = TestDb::new();
+        let now = Utc::now().naive_utc();
+        for (id, root) in [("f", "files"), ("i", "images")] {
+            let s = Share {
+                id: id.into(),
+                root: root.into(),
+                ..share(now)
+            };
+            diesel::insert_into(shares::table)
+                .values(&s)
+                .execute(&mut test_db.conn())
+                .unwrap();
+        }
+        let revoked = |id: &str| -> bool {
+            shares::table
+                .filter(shares::id.eq(id))
+                .select(shares::revoked_at)
+                .first::<Option<NaiveDateTime>>(&mut test_db.conn())
+                .unwrap()
+                .is_some()
+        };
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(Storage::for_test(&test_db.dir)))
+                .app_data(web::Data::new(test_db.db.clone()))
+                .wrap_fn(|req, srv| {
+                    // X-Role 头指定模拟登录用户的角色
+                    let role = req.headers().get("X-Role").unwrap().to_str().unwrap();
+                    req.extensions_mut()
+                        .insert(CurrentUser::for_test(role.parse().unwrap()));
+                    srv.call(req)
+                })
+                .service(revoke_share),
+        )
+        .await;
+        let revoke = |id: &str, role: &str| {
+            let req = TestRequest::delete()
+                .uri(&format!("/{}", id))
+                .insert_header(("X-Role", role))
+                .to_request();
+            let res = app.call(req);
+            async move {
+                match res.await {
+                    Ok(res) => res.status(),
+                    Err(e) => e.error_response().status(),
+                }
+            }
+        };
+
+        assert_eq!(revoke("f", "operator").await, StatusCode::NOT_FOUND);
+        assert!(!revoked("f"));
+        assert_eq!(revoke("missing", "operator").await, StatusCode::NOT_FOUND);
+        assert_eq!(revoke("i", "operator").await, StatusCode::OK);
+        assert!(revoked("i"));
+        assert_eq!(revoke("f", "admin").await, StatusCode::OK);
+        assert!(revoked("f"));
+        // 重复撤销保持原来的撤销时间
+        assert_eq!(revoke("f", "admin").await, StatusCode::OK);
+    }
+
+    #[actix_web::test]
+    async fn test_download() {
+        let test_db = TestDb::new();
+        let storage = Storage::for_test(&test_db.dir);
+        std::fs::write(test_db.dir.join("files/a.log"), "0123456789").unwrap();
+        let signer = web::Data::new(UrlSigner::new(vec![1; 32]));
+        let now = Utc::now().naive_utc();
+        let add = |id: &str, f: &dyn Fn(&mut Share)| {
+            let mut s = share(now);
+            s.id = id.into();
+            f(&mut s);
+            diesel::insert_into(shares::table)
+                .values(&s)
+                .execute(&mut test_db.conn())
+                .unwrap();
+            s.expires_at.and_utc().timestamp()
+        };
+        let ok = add("ok", &|_| {});
+        let expired = add("expired", &|s| s.expires_at = now - Duration::hours(1));
+        let revoked = add("revoked", &|s| s.revoked_at = Some(now));
+        let used = add("used", &|s| s.downloads = 2);
+        let missing = add("missing", &|s| s.path = "b.log".into());
+        let downloads = |id: &str| -> i32 {
+            shares::table
+                .filter(shares::id.eq(id))
+                .select(shares::downloads)
+                .first(&mut test_db.conn())
+                .unwrap()
+        };
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(storage))
+                .app_data(signer.clone())
+                .app_data(web::Data::new(test_db.db.clone()))
+                .service(download),
+        )
+        .await;
+        // 按分享 id 和过期时间签名；sig 为 None 时使用错误的签名
+        let get = |id: &str, expires: i64, sig: Option<&str>, range: Option<&str>| {
+            let sig = sig.map_or_else(|| signer.sign(id, expires), str::to_string);
+            let mut req =
+                TestRequest::get().uri(&format!("/{}?expires={}&sig={}", id, expires, sig));
+            if let Some(range) = range {
+                req = req.insert_header((header::RANGE, range));
+            }
+            let res = app.call(req.to_request());
+            async move {
+                match res.await {
+                    Ok(res) => res.status(),
+                    Err(e) => e.error_response().status(),
+                }
+            }
+        };
+        assert_eq!(
+            get("ok", ok, Some("bad"), None).await,
+            StatusCode::FORBIDDEN
+        );
+        // 签名有效但与分享的过期时间不符
+        assert_eq!(get("ok", ok + 1, None, None).await, StatusCode::FORBIDDEN);
+        assert_eq!(downloads("ok"), 0);
+
+        for (id, expires) in [("expired", expired), ("revoked", revoked), ("used", used)] {
+            assert_eq!(get(id, expires, None, None).await, StatusCode::GONE);
+        }
+        assert_eq!(downloads("used"), 2);
+
+        // 文件不存在时不计数
+        let status = get("missing", missing, None, None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(downloads("missing"), 0);
+
+        assert_eq!(get("ok", ok, None, None).await, StatusCode::OK);
+        assert_eq!(downloads("ok"), 1);
+        // 续传不计数，从头开始的分段请求计数
+        let status = get("ok", ok, None, Some("bytes=5-")).await;
+        assert_eq!(status, StatusCode::PARTIAL_CONTENT);
+        assert_eq!(downloads("ok"), 1);
+        let status = get("ok", ok, None, Some("bytes=0-4")).await;
+        assert_eq!(status, StatusCode::PARTIAL_CONTENT);
+        assert_eq!(downloads("ok"), 2);
+        // 次数用完后续传也被拒绝
+        let status = get("ok", ok, None, Some("bytes=5-")).await;
+        assert_eq!(status, StatusCode::GONE);
+        assert_eq!(downloads("ok"), 2);
+    }
+
+    #[test]
+    fn test_link() {
+        let now = Utc::now().naive_utc();
+        let signer = UrlSigner::new(vec![1; 32]);
+        let config = ShareConfig {
+            base_url: Some("https://panel.example.com/".into()),
+            ..ShareConfig::default()
+        };
+        let link = link(share(now), &signer, &config);
+        let expires = now.and_utc().timestamp() + 3600;
+        assert_eq!(
+            link.url,
+            format!(
+                "https://panel.example.com/s/x?expires={}&sig={}",
+                expires,
+                signer.sign("x", expires)
+            )
+        );
+    }
+}
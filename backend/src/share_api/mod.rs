@@ -0,0 +1,16 @@
+mod api;
+mod model;
+pub mod sign;
+use utoipa_actix_web::service_config::ServiceConfig;
+
+/// /v1/share 下的管理接口
+pub fn handle(cfg: &mut ServiceConfig) {
+    cfg.service(api::create_share);
+    cfg.service(api::list_shares);
+    cfg.service(api::revoke_share);
+}
+
+/// /s 下的公开下载地址
+pub fn public(cfg: &mut ServiceConfig) {
+    cfg.service(api::download);
+}
@@ -0,0 +1,37 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::schema::shares;
+
+/// 一条分享链接，id 为 uuid
+#[derive(Debug, Clone, Queryable, Selectable, Insertable, Serialize, Deserialize, ToSchema)]
+#[diesel(table_name = shares, check_for_backend(diesel::sqlite::Sqlite))]
+pub struct Share {
+    pub id: String,
+    pub root: String,
+    /// 相对于存储根的路径
+    pub path: String,
+    pub created_at: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
+    /// 允许下载的次数，不填表示不限
+    pub max_downloads: Option<i32>,
+    pub downloads: i32,
+    pub revoked_at: Option<NaiveDateTime>,
+}
+
+impl Share {
+    /// 已撤销、已过期或次数已用完时返回原因
+    pub fn unavailable(&self, now: NaiveDateTime) -> Option<&'static str> {
+        if self.revoked_at.is_some() {
+            Some("已撤销")
+        } else if now >= self.expires_at {
+            Some("已过期")
+        } else if self.max_downloads.is_some_and(|max| self.downloads >= max) {
+            Some("下载次数已用完")
+        } else {
+            None
+        }
+    }
+}
@@ -0,0 +1,104 @@
+use hmac::{Hmac, Mac};
+use log::info;
+use rand::RngCore;
+use sha2::Sha256;
+use std::fs;
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::Path;
+
+use crate::ServerError;
+
+const KEY_LEN: usize = 32;
+
+/// 用 HMAC-SHA256 对分享链接签名，密钥只保存在服务端
+pub struct UrlSigner {
+    key: Vec<u8>,
+}
+
+impl std::fmt::Debug for UrlSigner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UrlSigner").finish_non_exhaustive()
+    }
+}
+
+impl UrlSigner {
+    pub fn new(key: Vec<u8>) -> UrlSigner {
+        UrlSigner { key }
+    }
+
+    /// 读取密钥文件，不存在时生成随机密钥并以 0600 权限写入
+    pub fn load_or_create(path: &Path) -> Result<UrlSigner, ServerError> {
+        let err = |e: std::io::Error| ServerError::Config(format!("{}: {}", path.display(), e));
+        if path.exists() {
+            let key = hex::decode(fs::read_to_string(path).map_err(err)?.trim())
+                .map_err(|e| ServerError::Config(format!("{}: {}", path.display(), e)))?;
+            if key.len() < KEY_LEN {
+                return Err(ServerError::Config(format!(
+                    "{}: share key must be at least {} bytes",
+                    path.display(),
+                    KEY_LEN
+                )));
+            }
+            return Ok(UrlSigner::new(key));
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(err)?;
+        }
+        let mut key = vec![0u8; KEY_LEN];
+        rand::rngs::OsRng.fill_bytes(&mut key);
+        fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(path)
+            .and_then(|mut f| f.write_all(hex::encode(&key).as_bytes()))
+            .map_err(err)?;
+        info!("Generated share key {:?}", path);
+        Ok(UrlSigner::new(key))
+    }
+
+    fn mac(&self, id: &str, expires: i64) -> Hmac<Sha256> {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(&self.key).expect("HMAC accepts keys of any length");
+        mac.update(format!("{}:{}", id, expires).as_bytes());
+        mac
+    }
+
+    pub fn sign(&self, id: &str, expires: i64) -> String {
+        hex::encode(self.mac(id, expires).finalize().into_bytes())
+    }
+
+    /// 常数时间比较，签名格式错误时同样返回 false
+    pub fn verify(&self, id: &str, expires: i64, sig: &str) -> bool {
+        match hex::decode(sig) {
+            Ok(sig) => self.mac(id, expires).verify_slice(&sig).is_ok(),
+            Err(_) => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign_verify() {
+        let signer = UrlSigner::new(vec![7; KEY_LEN]);
+        let sig = signer.sign("abc", 1700000000);
+        assert!(signer.verify("abc", 1700000000, &sig));
+        assert!(!signer.verify("abc", 1700000001, &sig));
+        assert!(!signer.verify("abd", 1700000000, &sig));
+        assert!(!signer.verify("abc", 1700000000, "not-hex"));
+        assert!(!UrlSigner::new(vec![8; KEY_LEN]).verify("abc", 1700000000, &sig));
+    }
+
+    #[test]
+    fn test_key_file() {
+        let path = std::env::temp_dir().join(format!("rpanel-key-{}", uuid::Uuid::new_v4()));
+        let a = UrlSigner::load_or_create(&path).unwrap();
+        let b = UrlSigner::load_or_create(&path).unwrap();
+        assert_eq!(a.sign("x", 1), b.sign("x", 1));
+        fs::remove_file(&path).unwrap();
+    }
+}
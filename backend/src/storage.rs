@@ -0,0 +1,448 @@
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Component, Path, PathBuf};
+
+use crate::ServerError;
+use crate::config::StorageConfig;
+use crate::error::AppError;
+use crate::middleware::auth::CurrentUser;
+use actix_web::dev::Payload;
+use actix_web::{FromRequest, HttpMessage, HttpRequest, web};
+use std::future::{Ready, ready};
+use utoipa::ToSchema;
+
+/// 存储根上允许执行的操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Operation {
+    /// 列目录、读文件、预览压缩包
+    Read,
+    /// 新建、保存、上传
+    Write,
+    Delete,
+    /// 创建和解压压缩包
+    Archive,
+    /// chmod / chown
+    Permissions,
+    /// 递归搜索和目录占用统计
+    Search,
+}
+
+impl Operation {
+    pub const ALL: [Operation; 6] = [
+        Operation::Read,
+        Operation::Write,
+        Operation::Delete,
+        Operation::Archive,
+        Operation::Permissions,
+        Operation::Search,
+    ];
+
+    /// 不修改文件系统的操作，只读存储根也允许
+    pub fn is_read(self) -> bool {
+        matches!(self, Operation::Read | Operation::Search)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct StorageRoot {
+    pub id: String,
+    #[schema(value_type = String)]
+    pub path: PathBuf,
+    pub read_only: bool,
+    /// 实际生效的操作（已去掉只读根上的写操作）
+    pub operations: Vec<Operation>,
+}
+
+impl StorageRoot {
+    pub fn allows(&self, op: Operation) -> bool {
+        self.operations.contains(&op)
+    }
+
+    /// 将客户端传入的相对路径解析到存储根下，拒绝 `..` 以及经符号链接越出根目录的路径
+    pub fn resolve(&self, rel: &str) -> Result<PathBuf, AppError> {
+        resolve(&self.path, rel)
+    }
+
+    /// 绝对路径转为相对于存储根的路径，用于返回给客户端
+    pub fn relative(&self, path: &Path) -> String {
+        path.strip_prefix(&self.path)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .to_string()
+    }
+}
+
+/// 按文本拼接路径，不访问文件系统
+fn join(root: &Path, rel: &str) -> Result<PathBuf, AppError> {
+    let mut path = root.to_path_buf();
+    for comp in Path::new(rel).components() {
+        match comp {
+            Component::Normal(part) => path.push(part),
+            Component::RootDir | Component::CurDir => {}
+            Component::ParentDir | Component::Prefix(_) => {
+                return Err(AppError::InvalidParam(format!("非法路径: {}", rel)));
+            }
+        }
+    }
+    Ok(path)
+}
+
+/// 检查 path 的实际位置在 root 之内：存在的部分解析符号链接后比较，
+/// 尚不存在的目标检查其最近的已存在上级目录；悬空的符号链接写入时会在链接指向处创建文件，一律拒绝
+pub fn check_within(root: &Path, path: &Path, rel: &str) -> Result<(), AppError> {
+    let root = root.canonicalize()?;
+    let mut existing = path;
+    let real = loop {
+        match existing.canonicalize() {
+            Ok(real) => break real,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                if existing.symlink_metadata().is_ok() {
+                    return Err(AppError::Forbidden(format!("路径不在存储根内: {}", rel)));
+                }
+                existing = existing.parent().ok_or(e)?;
+            }
+            Err(e) => return Err(e.into()),
+        }
+    };
+    if !real.starts_with(&root) {
+        return Err(AppError::Forbidden(format!("路径不在存储根内: {}", rel)));
+    }
+    Ok(())
+}
+
+/// 返回拼接后的路径而不是解析后的实际路径，对根内的符号链接本身操作（删除、重命名）时不会作用到链接目标
+pub fn resolve(root: &Path, rel: &str) -> Result<PathBuf, AppError> {
+    let path = join(root, rel)?;
+    check_within(root, &path, rel)?;
+    Ok(path)
+}
+
+/// 配置中定义的全部存储根，以 `web::Data<Storage>` 共享给各个 handler
+#[derive(Debug)]
+pub struct Storage {
+    roots: Vec<StorageRoot>,
+    image_root: String,
+}
+
+impl Storage {
+    /// 校验配置；可写的根目录不存在时自动创建
+    pub fn from_config(config: &StorageConfig) -> Result<Storage, ServerError> {
+        let mut ids = HashSet::new();
+        let mut roots = Vec::new();
+        for root in &config.roots {
+            if root.id.is_empty()
+                || !root
+                    .id
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+            {
+                return Err(ServerError::Config(format!(
+                    "invalid storage root id: {:?}",
+                    root.id
+                )));
+            }
+            if !ids.insert(root.id.as_str()) {
+                return Err(ServerError::Config(format!(
+                    "duplicate storage root id: {}",
+                    root.id
+                )));
+            }
+            if !root.path.exists() {
+                if root.read_only {
+                    return Err(ServerError::Config(format!(
+                        "storage root {} does not exist: {}",
+                        root.id,
+                        root.path.display()
+                    )));
+                }
+                std::fs::create_dir_all(&root.path)
+                    .map_err(|e| ServerError::Config(format!("{}: {}", root.path.display(), e)))?;
+                info!("Created storage root {}: {:?}", root.id, root.path);
+            }
+            let path = root
+                .path
+                .canonicalize()
+                .map_err(|e| ServerError::Config(format!("{}: {}", root.path.display(), e)))?;
+            if !path.is_dir() {
+                return Err(ServerError::Config(format!(
+                    "storage root {} is not a directory: {}",
+                    root.id,
+                    path.display()
+                )));
+            }
+            let operations = root
+                .operations
+                .clone()
+                .unwrap_or_else(|| Operation::ALL.to_vec())
+                .into_iter()
+                .filter(|op| !root.read_only || op.is_read())
+                .collect();
+            roots.push(StorageRoot {
+                id: root.id.clone(),
+                path,
+                read_only: root.read_only,
+                operations,
+            });
+        }
+        if !config.image_root.is_empty() && !ids.contains(config.image_root.as_str()) {
+            return Err(ServerError::Config(format!(
+                "image_root refers to unknown storage root: {}",
+                config.image_root
+            )));
+        }
+        Ok(Storage {
+            roots,
+            image_root: config.image_root.clone(),
+        })
+    }
+
+    pub fn roots(&self) -> &[StorageRoot] {
+        &self.roots
+    }
+
+    /// 配置中的图床存储根 id，未配置时为空
+    pub fn image_root_id(&self) -> &str {
+        &self.image_root
+    }
+
+    /// 按 id 查找存储根，并检查是否允许执行 op
+    pub fn root(&self, id: &str, op: Operation) -> Result<&StorageRoot, AppError> {
+        let root = self
+            .roots
+            .iter()
+            .find(|r| r.id == id)
+            .ok_or_else(|| AppError::NotFound(format!("存储根不存在: {}", id)))?;
+        if !root.allows(op) {
+            return Err(AppError::Forbidden(format!(
+                "存储根 {} 不允许 {:?} 操作",
+                id, op
+            )));
+        }
+        Ok(root)
+    }
+
+    /// 图床的存储根，未指定 id 时使用配置中的 image_root
+    pub fn image_root(&self, id: Option<&str>, op: Operation) -> Result<&StorageRoot, AppError> {
+        match id {
+            Some(id) => self.root(id, op),
+            None if self.image_root.is_empty() => {
+                Err(AppError::NotFound("未配置图床存储根".into()))
+            }
+            None => self.root(&self.image_root, op),
+        }
+    }
+}
+
+/// 当前用户视角下的存储根：在存储根自身的操作限制之外，再按用户的存储根授权检查
+///
+/// 作为 handler 参数提取，需在认证中间件之内使用
+pub struct UserStorage {
+    storage: web::Data<Storage>,
+    user: CurrentUser,
+}
+
+impl FromRequest for UserStorage {
+    type Error = AppError;
+    type Future = Ready<Result<Self, AppError>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let storage = req
+            .app_data::<web::Data<Storage>>()
+            .cloned()
+            .ok_or_else(|| AppError::Unknown("缺少存储配置".into()));
+        let user = req
+            .extensions()
+            .get::<CurrentUser>()
+            .cloned()
+            .ok_or(AppError::Unauthorized);
+        ready(storage.and_then(|storage| {
+            Ok(UserStorage {
+                storage,
+                user: user?,
+            })
+        }))
+    }
+}
+
+impl UserStorage {
+    pub fn root(&self, id: &str, op: Operation) -> Result<&StorageRoot, AppError> {
+        self.user.check_root(id, op)?;
+        self.storage.root(id, op)
+    }
+
+    /// 配置的图床存储根只受图片权限控制；通过 ?root= 指定其它存储根时同样检查存储根授权
+    pub fn image_root(&self, id: Option<&str>, op: Operation) -> Result<&StorageRoot, AppError> {
+        let root = self.storage.image_root(id, op)?;
+        if root.id != self.storage.image_root_id() {
+            self.user.check_root(&root.id, op)?;
+        }
+        Ok(root)
+    }
+
+    /// 用户是否可以在存储根上执行 op，规则与 image_root 相同
+    pub fn allows(&self, root_id: &str, op: Operation) -> bool {
+        root_id == self.storage.image_root_id() || self.user.check_root(root_id, op).is_ok()
+    }
+
+    /// 用户可以访问的存储根，operations 只保留被授权的操作
+    pub fn roots(&self) -> Vec<StorageRoot> {
+        self.storage
+            .roots()
+            .iter()
+            .filter_map(|root| {
+                let operations: Vec<Operation> = root
+                    .operations
+                    .iter()
+                    .copied()
+                    .filter(|op| self.user.check_root(&root.id, *op).is_ok())
+                    .collect();
+                (!operations.is_empty()).then(|| StorageRoot {
+                    operations,
+                    ..root.clone()
+                })
+            })
+            .collect()
+    }
+}
+
+/// 测试用的存储根，位于独立临时目录下的 root 子目录中，允许全部操作；
+/// 临时目录中 root 以外的位置可用来放置存储根外的文件，drop 时整个目录删除
+#[cfg(test)]
+pub struct TestRoot {
+    pub root: StorageRoot,
+    pub dir: PathBuf,
+}
+
+#[cfg(test)]
+impl TestRoot {
+    pub fn new(id: &str) -> TestRoot {
+        let dir = std::env::temp_dir().join(format!("rpanel-root-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("root")).unwrap();
+        let dir = dir.canonicalize().unwrap();
+        let root = StorageRoot {
+            id: id.into(),
+            path: dir.join("root"),
+            read_only: false,
+            operations: Operation::ALL.to_vec(),
+        };
+        TestRoot { root, dir }
+    }
+}
+
+#[cfg(test)]
+impl std::ops::Deref for TestRoot {
+    type Target = StorageRoot;
+
+    fn deref(&self) -> &StorageRoot {
+        &self.root
+    }
+}
+
+#[cfg(test)]
+impl Storage {
+    /// 默认配置中的 files 和 images 存储根，放在 dir 下
+    pub fn for_test(dir: &Path) -> Storage {
+        let mut config = StorageConfig::default();
+        for root in &mut config.roots {
+            root.path = dir.join(&root.id);
+        }
+        Storage::from_config(&config).unwrap()
+    }
+}
+
+#[cfg(test)]
+impl Drop for TestRoot {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::RootConfig;
+
+    #[test]
+    fn test_join() {
+        let root = Path::new("/home");
+        assert_eq!(join(root, "a/b.txt").unwrap(), Path::new("/home/a/b.txt"));
+        assert_eq!(join(root, "/a/./b").unwrap(), Path::new("/home/a/b"));
+        assert_eq!(join(root, "").unwrap(), Path::new("/home"));
+        assert!(join(root, "../etc/passwd").is_err());
+        assert!(join(root, "a/../../etc").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_resolve_symlink() {
+        use std::os::unix::fs::symlink;
+        let test_root = TestRoot::new("test");
+        let root = test_root.path.clone();
+        let outside = test_root.dir.join("outside");
+        std::fs::create_dir_all(root.join("sub")).unwrap();
+        std::fs::create_dir_all(&outside).unwrap();
+        std::fs::write(outside.join("secret.txt"), "x").unwrap();
+        symlink(&outside, root.join("escape")).unwrap();
+        symlink(root.join("sub"), root.join("inner")).unwrap();
+        symlink(outside.join("missing.txt"), root.join("dangling")).unwrap();
+
+        assert_eq!(
+            resolve(&root, "sub/new.txt").unwrap(),
+            root.join("sub/new.txt")
+        );
+        assert_eq!(resolve(&root, "inner/a/b").unwrap(), root.join("inner/a/b"));
+        for rel in [
+            "escape",
+            "escape/secret.txt",
+            "escape/new/file.txt",
+            "dangling",
+        ] {
+            assert!(
+                matches!(resolve(&root, rel), Err(AppError::Forbidden(_))),
+                "{}",
+                rel
+            );
+        }
+    }
+
+    #[test]
+    fn test_read_only_root() {
+        let config = StorageConfig {
+            roots: vec![RootConfig {
+                id: "tmp".into(),
+                path: std::env::temp_dir(),
+                read_only: true,
+                operations: None,
+            }],
+            image_root: String::new(),
+        };
+        let storage = Storage::from_config(&config).unwrap();
+        assert!(storage.root("tmp", Operation::Read).is_ok());
+        assert!(storage.root("tmp", Operation::Search).is_ok());
+        assert!(matches!(
+            storage.root("tmp", Operation::Write),
+            Err(AppError::Forbidden(_))
+        ));
+        assert!(matches!(
+            storage.root("other", Operation::Read),
+            Err(AppError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_reject_duplicate_ids() {
+        let root = RootConfig {
+            id: "tmp".into(),
+            path: std::env::temp_dir(),
+            read_only: true,
+            operations: None,
+        };
+        let config = StorageConfig {
+            roots: vec![root.clone(), root],
+            image_root: String::new(),
+        };
+        assert!(Storage::from_config(&config).is_err());
+    }
+}
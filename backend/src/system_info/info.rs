@@ -1,36 +1,40 @@
 use crate::base::Response;
+use crate::openapi::tags;
 use actix_web::{Responder, get};
 use serde::Serialize;
 use system_info::cpu;
-#[derive(Serialize, serde::Deserialize)]
+use utoipa::ToSchema;
+#[derive(Serialize, serde::Deserialize, ToSchema)]
 pub struct CpuInfo {
     pub cores: usize,
     pub usage: f32, // 百分比 0.0~100.0
 }
 
-#[derive(Serialize, serde::Deserialize)]
+#[derive(Serialize, serde::Deserialize, ToSchema)]
 struct MemInfo {
     pub total_kb: u64,
     pub used_kb: u64,
     pub usage_ratio: f32,
 }
 
-#[derive(Serialize, serde::Deserialize)]
+#[derive(Serialize, serde::Deserialize, ToSchema)]
 struct SwapInfo {
     pub total_kb: u64,
     pub used_kb: u64,
     pub usage_ratio: f32,
 }
 
+#[utoipa::path(tag = tags::SYSTEM, responses((status = 200, body = Response<CpuInfo>)))]
 #[get("/cpu")]
 pub async fn cpu_info() -> impl Responder {
     let info = CpuInfo {
         cores: cpu::count(),
         usage: cpu::usage() as f32 / 1000.0,
     };
-    Response::new(Some(info), "Success".into(), 0)
+    Response::ok(info)
 }
 
+#[utoipa::path(tag = tags::SYSTEM, responses((status = 200, body = Response<MemInfo>)))]
 #[get("/mem")]
 pub async fn mem_info() -> impl Responder {
     let info = system_info::mem::get_mem_info();
@@ -39,13 +43,10 @@ pub async fn mem_info() -> impl Responder {
         used_kb: info.used_kb,
         usage_ratio: info.usage_ratio as f32,
     };
-    Response {
-        data: Some(response),
-        msg: "Success".into(),
-        code: 0,
-    }
+    Response::ok(response)
 }
 
+#[utoipa::path(tag = tags::SYSTEM, responses((status = 200, body = Response<SwapInfo>)))]
 #[get("/swap")]
 pub async fn swap_info() -> impl Responder {
     let info = system_info::swap::get_swap_info();
@@ -54,9 +55,5 @@ pub async fn swap_info() -> impl Responder {
         used_kb: info.used_kb,
         usage_ratio: info.usage_ratio as f32,
     };
-    Response {
-        data: Some(info),
-        msg: "Success".into(),
-        code: 0,
-    }
+    Response::ok(info)
 }
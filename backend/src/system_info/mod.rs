@@ -1,6 +1,6 @@
 mod info;
 
-use actix_web::web::ServiceConfig;
+use utoipa_actix_web::service_config::ServiceConfig;
 
 pub fn handle(cfg: &mut ServiceConfig) {
     cfg.service(info::cpu_info);
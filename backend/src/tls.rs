@@ -0,0 +1,199 @@
+// HTTPS 证书加载与热更新
+use log::{info, warn};
+use rustls::crypto::ring::{default_provider, sign::any_supported_type};
+use rustls::server::{ClientHello, ResolvesServerCert};
+use rustls::sign::CertifiedKey;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, SystemTime};
+use tokio::signal::unix::{SignalKind, signal};
+use tokio::task::JoinHandle;
+use tokio::time::{Instant, Interval};
+
+use crate::ServerError;
+use crate::config::TlsConfig;
+use crate::lifecycle::StopSignal;
+
+/// 读取 PEM 证书链和私钥，并确认私钥与证书匹配
+fn load_certified_key(cert_file: &Path, key_file: &Path) -> Result<CertifiedKey, ServerError> {
+    let err = |path: &Path, e: &dyn std::fmt::Display| {
+        ServerError::Config(format!("{}: {}", path.display(), e))
+    };
+    let open = |path: &Path| {
+        File::open(path)
+            .map(BufReader::new)
+            .map_err(|e| err(path, &e))
+    };
+    let certs = rustls_pemfile::certs(&mut open(cert_file)?)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| err(cert_file, &e))?;
+    if certs.is_empty() {
+        return Err(err(cert_file, &"no certificate found"));
+    }
+    let key = rustls_pemfile::private_key(&mut open(key_file)?)
+        .map_err(|e| err(key_file, &e))?
+        .ok_or_else(|| err(key_file, &"no private key found"))?;
+    let key = any_supported_type(&key).map_err(|e| err(key_file, &e))?;
+    let certified = CertifiedKey::new(certs, key);
+    certified
+        .keys_match()
+        .map_err(|e| err(key_file, &format!("does not match certificate: {}", e)))?;
+    Ok(certified)
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// 每次握手时取当前证书，重新加载后新连接使用新证书，已建立的连接不受影响
+#[derive(Debug)]
+pub struct CertResolver {
+    cert_file: PathBuf,
+    key_file: PathBuf,
+    current: RwLock<Arc<CertifiedKey>>,
+    /// 上次加载时证书和私钥文件的修改时间
+    loaded_at: Mutex<(Option<SystemTime>, Option<SystemTime>)>,
+}
+
+impl CertResolver {
+    pub fn load(config: &TlsConfig) -> Result<CertResolver, ServerError> {
+        let mtimes = (modified(&config.cert_file), modified(&config.key_file));
+        let key = load_certified_key(&config.cert_file, &config.key_file)?;
+        Ok(CertResolver {
+            cert_file: config.cert_file.clone(),
+            key_file: config.key_file.clone(),
+            current: RwLock::new(Arc::new(key)),
+            loaded_at: Mutex::new(mtimes),
+        })
+    }
+
+    /// 重新读取证书；读取失败时保留原证书。force 为 false 时只在文件修改时间变化后读取
+    ///
+    /// 返回是否换上了新证书
+    pub fn reload(&self, force: bool) -> Result<bool, ServerError> {
+        let mtimes = (modified(&self.cert_file), modified(&self.key_file));
+        let mut loaded_at = self.loaded_at.lock().unwrap_or_else(|e| e.into_inner());
+        if !force && *loaded_at == mtimes {
+            return Ok(false);
+        }
+        // 证书和私钥分两次写入时，中间状态不匹配会加载失败，下次检查时再重试
+        let key = load_certified_key(&self.cert_file, &self.key_file)?;
+        *self.current.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(key);
+        *loaded_at = mtimes;
+        Ok(true)
+    }
+}
+
+impl ResolvesServerCert for CertResolver {
+    fn resolve(&self, _client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        Some(
+            self.current
+                .read()
+                .unwrap_or_else(|e| e.into_inner())
+                .clone(),
+        )
+    }
+}
+
+/// 使用 ring 实现的 rustls 配置，证书由 resolver 提供
+pub fn server_config(resolver: Arc<CertResolver>) -> Result<rustls::ServerConfig, ServerError> {
+    Ok(
+        rustls::ServerConfig::builder_with_provider(Arc::new(default_provider()))
+            .with_safe_default_protocol_versions()
+            .map_err(|e| ServerError::Config(format!("tls: {}", e)))?
+            .with_no_client_auth()
+            .with_cert_resolver(resolver),
+    )
+}
+
+fn log_reload(result: Result<bool, ServerError>) {
+    match result {
+        Ok(true) => info!("Reloaded TLS certificate"),
+        Ok(false) => {}
+        Err(e) => warn!(
+            "Failed to reload TLS certificate, keeping the old one: {}",
+            e
+        ),
+    }
+}
+
+/// interval 为 None 时永远不会返回
+async fn tick(interval: &mut Option<Interval>) {
+    match interval {
+        Some(interval) => {
+            interval.tick().await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
+/// 收到 SIGHUP 时重新加载证书，interval_secs 大于 0 时还会定期检查文件是否更新
+///
+/// 需在 tokio 运行时中调用，收到停止通知后退出
+pub fn spawn_reloader(
+    resolver: Arc<CertResolver>,
+    interval_secs: u64,
+    mut stop: StopSignal,
+) -> Result<JoinHandle<()>, ServerError> {
+    let mut hangup = signal(SignalKind::hangup())
+        .map_err(|e| ServerError::Config(format!("listen for SIGHUP: {}", e)))?;
+    let mut interval = (interval_secs > 0).then(|| {
+        let period = Duration::from_secs(interval_secs);
+        tokio::time::interval_at(Instant::now() + period, period)
+    });
+    Ok(tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                _ = hangup.recv() => {
+                    info!("Received SIGHUP, reloading TLS certificate");
+                    log_reload(resolver.reload(true));
+                }
+                _ = tick(&mut interval) => log_reload(resolver.reload(false)),
+                _ = stop.changed() => break,
+            }
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_pair(dir: &Path, name: &str) -> (PathBuf, PathBuf) {
+        let cert = rcgen::generate_simple_self_signed(vec![name.to_string()]).unwrap();
+        let cert_file = dir.join("cert.pem");
+        let key_file = dir.join("key.pem");
+        std::fs::write(&cert_file, cert.cert.pem()).unwrap();
+        std::fs::write(&key_file, cert.key_pair.serialize_pem()).unwrap();
+        (cert_file, key_file)
+    }
+
+    #[test]
+    fn test_reload() {
+        let dir = std::env::temp_dir().join(format!("rpanel-tls-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (cert_file, key_file) = write_pair(&dir, "a.example.com");
+        let config = TlsConfig {
+            enabled: true,
+            cert_file: cert_file.clone(),
+            key_file: key_file.clone(),
+            ..TlsConfig::default()
+        };
+        let resolver = CertResolver::load(&config).unwrap();
+        let first = resolver.current.read().unwrap().cert[0].clone();
+        assert!(!resolver.reload(false).unwrap());
+
+        // 私钥与证书不匹配时保留原证书
+        let other = rcgen::generate_simple_self_signed(vec!["b.example.com".into()]).unwrap();
+        std::fs::write(&key_file, other.key_pair.serialize_pem()).unwrap();
+        assert!(resolver.reload(true).is_err());
+        assert_eq!(resolver.current.read().unwrap().cert[0], first);
+
+        write_pair(&dir, "b.example.com");
+        assert!(resolver.reload(true).unwrap());
+        assert_ne!(resolver.current.read().unwrap().cert[0], first);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
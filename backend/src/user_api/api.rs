@@ -0,0 +1,338 @@
+use actix_web::{Responder, delete, get, post, put, web};
+use chrono::Utc;
+use diesel::prelude::*;
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use utoipa::ToSchema;
+
+use crate::auth_api::model::{NewUser, RootGrant, User};
+use crate::auth_api::password::{check_password_policy, hash_password};
+use crate::auth_api::role::{Access, Permission, Role};
+use crate::auth_api::totp;
+use crate::base::Response;
+use crate::db::Database;
+use crate::error::AppError;
+use crate::middleware::auth::CurrentUser;
+use crate::openapi::tags;
+use crate::schema::{root_grants, sessions, users};
+use crate::storage::Storage;
+
+const MAX_USERNAME_LEN: usize = 32;
+
+/// 用户及其存储根授权
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+struct UserInfo {
+    #[serde(flatten)]
+    user: User,
+    grants: Vec<RootGrant>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+struct RoleInfo {
+    role: Role,
+    permissions: Vec<Permission>,
+}
+
+#[derive(Deserialize, ToSchema)]
+#[schema(as = CreateUserRequest)]
+struct CreateRequest {
+    username: String,
+    password: String,
+    role: Role,
+    /// 要求用户启用两步验证后才能使用面板
+    #[serde(default)]
+    totp_required: bool,
+}
+
+/// 只修改请求中给出的字段
+#[derive(Deserialize, ToSchema)]
+struct UpdateRequest {
+    role: Option<Role>,
+    disabled: Option<bool>,
+    password: Option<String>,
+    totp_required: Option<bool>,
+    /// 清除用户的两步验证，用于用户丢失验证器且没有恢复码时
+    #[serde(default)]
+    reset_totp: bool,
+}
+
+#[derive(Deserialize, ToSchema)]
+struct GrantItem {
+    root: String,
+    access: Access,
+}
+
+#[derive(Deserialize, ToSchema)]
+struct GrantsRequest {
+    grants: Vec<GrantItem>,
+}
+
+pub(crate) fn check_username(name: &str) -> Result<&str, AppError> {
+    let name = name.trim();
+    if name.is_empty()
+        || name.len() > MAX_USERNAME_LEN
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+    {
+        return Err(AppError::field(
+            "username",
+            format!(
+                "只能包含字母、数字和 _-.，且不超过 {} 个字符",
+                MAX_USERNAME_LEN
+            ),
+        ));
+    }
+    Ok(name)
+}
+
+fn find_user(conn: &mut SqliteConnection, id: i32) -> Result<User, AppError> {
+    users::table
+        .find(id)
+        .select(User::as_select())
+        .first(conn)
+        .optional()?
+        .ok_or_else(|| AppError::NotFound(format!("用户不存在: {}", id)))
+}
+
+fn user_info(conn: &mut SqliteConnection, user: User) -> Result<UserInfo, AppError> {
+    let grants = root_grants::table
+        .filter(root_grants::user_id.eq(user.id))
+        .order(root_grants::root.asc())
+        .select(RootGrant::as_select())
+        .load(conn)?;
+    Ok(UserInfo { user, grants })
+}
+
+/// 全部角色及其权限
+#[utoipa::path(tag = tags::USERS, responses((status = 200, body = Response<Vec<RoleInfo>>)))]
+#[get("/roles")]
+async fn list_roles() -> Result<impl Responder, AppError> {
+    let roles: Vec<RoleInfo> = Role::ALL
+        .into_iter()
+        .map(|role| RoleInfo {
+            role,
+            permissions: role.permissions().to_vec(),
+        })
+        .collect();
+    Ok(Response::ok(roles))
+}
+
+#[utoipa::path(tag = tags::USERS, responses((status = 200, body = Response<Vec<UserInfo>>)))]
+#[get("")]
+async fn list_users(db: web::Data<Database>) -> Result<impl Responder, AppError> {
+    let result = web::block(move || -> Result<Vec<UserInfo>, AppError> {
+        let mut conn = db.connect()?;
+        let list: Vec<User> = users::table
+            .order(users::id.asc())
+            .select(User::as_select())
+            .load(&mut conn)?;
+        let mut grants: HashMap<i32, Vec<RootGrant>> = HashMap::new();
+        for grant in root_grants::table
+            .order(root_grants::root.asc())
+            .select(RootGrant::as_select())
+            .load(&mut conn)?
+        {
+            grants.entry(grant.user_id).or_default().push(grant);
+        }
+        Ok(list
+            .into_iter()
+            .map(|user| UserInfo {
+                grants: grants.remove(&user.id).unwrap_or_default(),
+                user,
+            })
+            .collect())
+    })
+    .await??;
+    Ok(Response::ok(result))
+}
+
+#[utoipa::path(tag = tags::USERS, responses((status = 200, body = Response<UserInfo>)))]
+#[post("")]
+async fn create_user(
+    req: web::Json<CreateRequest>,
+    db: web::Data<Database>,
+) -> Result<impl Responder, AppError> {
+    let req = req.into_inner();
+    let username = check_username(&req.username)?.to_string();
+    check_password_policy("password", &req.password)?;
+    let hash = hash_password(&req.password)?;
+    let user = web::block(move || -> Result<UserInfo, AppError> {
+        let mut conn = db.connect()?;
+        let now = Utc::now().naive_utc();
+        diesel::insert_into(users::table)
+            .values(&NewUser {
+                username: &username,
+                password_hash: &hash,
+                role: req.role.as_str(),
+                created_at: now,
+                updated_at: now,
+            })
+            .execute(&mut conn)
+            .map_err(|e| match e {
+                DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, _) => {
+                    AppError::Conflict(format!("用户已存在: {}", username))
+                }
+                e => e.into(),
+            })?;
+        if req.totp_required {
+            diesel::update(users::table.filter(users::username.eq(&username)))
+                .set(users::totp_required.eq(true))
+                .execute(&mut conn)?;
+        }
+        let user = users::table
+            .filter(users::username.eq(&username))
+            .select(User::as_select())
+            .first(&mut conn)?;
+        user_info(&mut conn, user)
+    })
+    .await??;
+    Ok(Response::ok(user))
+}
+
+/// 禁用用户或重置密码时注销该用户的全部会话；管理员不能修改自己的角色和禁用状态
+///
+/// totp_required 设置两步验证策略，reset_totp 清除用户已绑定的验证器
+#[utoipa::path(tag = tags::USERS, responses((status = 200, body = Response<UserInfo>)))]
+#[put("/{id}")]
+async fn update_user(
+    id: web::Path<i32>,
+    req: web::Json<UpdateRequest>,
+    current: CurrentUser,
+    db: web::Data<Database>,
+) -> Result<impl Responder, AppError> {
+    let id = id.into_inner();
+    let req = req.into_inner();
+    if id == current.id
+        && (req.role.is_some_and(|r| r != current.role) || req.disabled == Some(true))
+    {
+        return Err(AppError::Forbidden("不能修改自己的角色或禁用自己".into()));
+    }
+    let hash = match &req.password {
+        Some(password) => {
+            check_password_policy("password", password)?;
+            Some(hash_password(password)?)
+        }
+        None => None,
+    };
+    let user = web::block(move || -> Result<UserInfo, AppError> {
+        let mut conn = db.connect()?;
+        conn.transaction(|conn| {
+            find_user(conn, id)?;
+            let now = Utc::now().naive_utc();
+            let target = users::table.find(id);
+            if let Some(role) = req.role {
+                diesel::update(target)
+                    .set(users::role.eq(role.as_str()))
+                    .execute(conn)?;
+            }
+            if let Some(disabled) = req.disabled {
+                diesel::update(target)
+                    .set(users::disabled.eq(disabled))
+                    .execute(conn)?;
+            }
+            if let Some(hash) = &hash {
+                diesel::update(target)
+                    .set(users::password_hash.eq(hash))
+                    .execute(conn)?;
+            }
+            if let Some(required) = req.totp_required {
+                diesel::update(target)
+                    .set(users::totp_required.eq(required))
+                    .execute(conn)?;
+            }
+            if req.reset_totp {
+                totp::reset(conn, id)?;
+            }
+            diesel::update(target)
+                .set(users::updated_at.eq(now))
+                .execute(conn)?;
+            if req.disabled == Some(true) || hash.is_some() {
+                diesel::delete(sessions::table.filter(sessions::user_id.eq(id))).execute(conn)?;
+            }
+            let user = find_user(conn, id)?;
+            user_info(conn, user)
+        })
+    })
+    .await??;
+    Ok(Response::ok(user))
+}
+
+/// 删除用户，会话和授权随之删除
+#[utoipa::path(tag = tags::USERS, responses((status = 200, body = Response<User>)))]
+#[delete("/{id}")]
+async fn delete_user(
+    id: web::Path<i32>,
+    current: CurrentUser,
+    db: web::Data<Database>,
+) -> Result<impl Responder, AppError> {
+    let id = id.into_inner();
+    if id == current.id {
+        return Err(AppError::Forbidden("不能删除自己".into()));
+    }
+    let user = web::block(move || -> Result<User, AppError> {
+        let mut conn = db.connect()?;
+        let user = find_user(&mut conn, id)?;
+        diesel::delete(users::table.find(id)).execute(&mut conn)?;
+        Ok(user)
+    })
+    .await??;
+    Ok(Response::ok(user))
+}
+
+/// 用请求中的授权替换用户原有的全部存储根授权
+#[utoipa::path(tag = tags::USERS, responses((status = 200, body = Response<UserInfo>)))]
+#[put("/{id}/grants")]
+async fn set_grants(
+    id: web::Path<i32>,
+    req: web::Json<GrantsRequest>,
+    storage: web::Data<Storage>,
+    db: web::Data<Database>,
+) -> Result<impl Responder, AppError> {
+    let id = id.into_inner();
+    let mut grants: HashMap<String, Access> = HashMap::new();
+    for item in req.into_inner().grants {
+        if !storage.roots().iter().any(|r| r.id == item.root) {
+            return Err(AppError::InvalidParam(format!(
+                "存储根不存在: {}",
+                item.root
+            )));
+        }
+        grants.insert(item.root, item.access);
+    }
+    let user = web::block(move || -> Result<UserInfo, AppError> {
+        let mut conn = db.connect()?;
+        conn.transaction(|conn| {
+            let user = find_user(conn, id)?;
+            diesel::delete(root_grants::table.filter(root_grants::user_id.eq(id))).execute(conn)?;
+            let rows: Vec<RootGrant> = grants
+                .into_iter()
+                .map(|(root, access)| RootGrant {
+                    user_id: id,
+                    root,
+                    access: access.as_str().to_string(),
+                })
+                .collect();
+            diesel::insert_into(root_grants::table)
+                .values(&rows)
+                .execute(conn)?;
+            user_info(conn, user)
+        })
+    })
+    .await??;
+    Ok(Response::ok(user))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_username() {
+        assert_eq!(check_username(" bob ").unwrap(), "bob");
+        assert!(check_username("").is_err());
+        assert!(check_username("a b").is_err());
+        assert!(check_username(&"a".repeat(MAX_USERNAME_LEN + 1)).is_err());
+    }
+}
@@ -0,0 +1,13 @@
+mod api;
+pub(crate) use api::check_username;
+use utoipa_actix_web::service_config::ServiceConfig;
+
+/// /v1/users 下的用户和授权管理接口，只有 admin 可以访问
+pub fn handle(cfg: &mut ServiceConfig) {
+    cfg.service(api::list_roles);
+    cfg.service(api::list_users);
+    cfg.service(api::create_user);
+    cfg.service(api::update_user);
+    cfg.service(api::delete_user);
+    cfg.service(api::set_grants);
+}
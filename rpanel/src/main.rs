@@ -1,9 +1,432 @@
-use backend::Server;
+mod output;
+mod unit;
+
+use std::io::BufRead;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use backend::config::Config;
+use backend::{Server, ServerError, admin};
+use clap::{Args, Parser, Subcommand};
+use output::Format;
+
+/// 配置优先级：命令行参数 > 环境变量（RPANEL_SERVER__PORT 等）> 配置文件 > 默认值
+///
+/// 不带子命令时等同于 serve
+#[derive(Parser, Debug)]
+#[command(
+    version,
+    about = "rpanel 服务端",
+    args_conflicts_with_subcommands = true
+)]
+struct Cli {
+    #[command(flatten)]
+    global: GlobalArgs,
+    #[command(flatten)]
+    serve: ServeArgs,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+/// 所有子命令共用的配置参数
+#[derive(Args, Debug)]
+struct GlobalArgs {
+    /// 配置文件路径，默认取 RPANEL_CONFIG 或 ./rpanel.toml
+    #[arg(short, long, global = true)]
+    config: Option<PathBuf>,
+    /// 数据库路径
+    #[arg(long, global = true)]
+    database: Option<String>,
+    /// 覆盖任意配置项，如 --set features.audit=false，可重复
+    #[arg(long = "set", value_name = "KEY=VALUE", value_parser = parse_override, global = true)]
+    overrides: Vec<(String, String)>,
+}
+
+#[derive(Args, Debug, Default)]
+struct ServeArgs {
+    /// 监听地址
+    #[arg(long)]
+    host: Option<String>,
+    /// 监听端口
+    #[arg(short, long)]
+    port: Option<u16>,
+    /// worker 线程数
+    #[arg(long)]
+    workers: Option<usize>,
+    /// 日志级别，同 RUST_LOG 语法
+    #[arg(long)]
+    log_level: Option<String>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// 启动服务
+    Serve(ServeArgs),
+    /// 写入默认配置文件，创建存储目录和数据库，并创建初始 admin 账号
+    Init {
+        /// 配置文件已存在时覆盖
+        #[arg(long)]
+        force: bool,
+    },
+    /// 管理用户
+    #[command(subcommand)]
+    User(UserCommand),
+    /// 执行数据库迁移
+    Migrate,
+    /// 校验配置，成功时输出生效的配置
+    CheckConfig,
+    /// 输出系统信息快照
+    Info {
+        #[arg(long, value_enum, default_value_t)]
+        format: Format,
+    },
+    /// 生成 systemd 单元文件
+    SystemdUnit {
+        /// 运行服务的用户
+        #[arg(long)]
+        user: Option<String>,
+        /// 同时生成 rpanel.socket，由 systemd 监听端口后传给服务
+        #[arg(long)]
+        socket: bool,
+        /// 写入该目录，如 /etc/systemd/system；不指定时输出到标准输出
+        #[arg(long)]
+        output_dir: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum UserCommand {
+    /// 创建用户，不指定密码时生成随机密码
+    Add {
+        username: String,
+        /// viewer / operator / admin
+        #[arg(long, default_value = "viewer")]
+        role: String,
+        /// 从标准输入读取一行作为密码
+        #[arg(long)]
+        password_stdin: bool,
+    },
+    /// 重置密码并注销该用户的全部会话，不指定密码时生成随机密码
+    Passwd {
+        username: String,
+        /// 从标准输入读取一行作为密码
+        #[arg(long)]
+        password_stdin: bool,
+    },
+    /// 列出全部用户
+    List {
+        #[arg(long, value_enum, default_value_t)]
+        format: Format,
+    },
+}
+
+fn parse_override(s: &str) -> Result<(String, String), String> {
+    let (key, value) = s
+        .split_once('=')
+        .ok_or_else(|| format!("需要 KEY=VALUE 格式: {}", s))?;
+    Ok((key.trim().to_string(), value.to_string()))
+}
+
+fn toml_string(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+impl GlobalArgs {
+    /// 专用参数转换为点分配置项，排在 --set 之后以保证优先
+    fn overrides(&self, serve: &ServeArgs) -> Vec<(String, String)> {
+        let mut overrides = self.overrides.clone();
+        // 字符串值按 TOML 字符串传入，避免被解析成其他类型
+        let flags = [
+            ("server.host", serve.host.as_deref().map(toml_string)),
+            ("server.port", serve.port.map(|v| v.to_string())),
+            ("server.workers", serve.workers.map(|v| v.to_string())),
+            (
+                "server.log_level",
+                serve.log_level.as_deref().map(toml_string),
+            ),
+            ("database.url", self.database.as_deref().map(toml_string)),
+        ];
+        for (key, value) in flags {
+            if let Some(value) = value {
+                overrides.push((key.to_string(), value));
+            }
+        }
+        overrides
+    }
+
+    fn load(&self, serve: &ServeArgs) -> Result<Config, ServerError> {
+        Config::load_with(self.config.as_deref(), &self.overrides(serve))
+    }
+}
+
+/// 配置文件路径，与 Config::load_with 的查找顺序一致
+fn config_path(arg: Option<&Path>) -> PathBuf {
+    arg.map(Path::to_path_buf)
+        .or_else(|| std::env::var_os("RPANEL_CONFIG").map(PathBuf::from))
+        .unwrap_or_else(|| PathBuf::from("rpanel.toml"))
+}
+
+fn read_password(from_stdin: bool) -> Result<Option<String>, ServerError> {
+    if !from_stdin {
+        return Ok(None);
+    }
+    let mut line = String::new();
+    std::io::stdin()
+        .lock()
+        .read_line(&mut line)
+        .map_err(|e| ServerError::Command(format!("read password: {}", e)))?;
+    Ok(Some(line.trim_end_matches(['\r', '\n']).to_string()))
+}
+
+async fn run(cli: Cli) -> Result<(), ServerError> {
+    let global = cli.global;
+    match cli.command {
+        None => serve(&global, &cli.serve).await,
+        Some(Command::Serve(args)) => serve(&global, &args).await,
+        Some(Command::Init { force }) => {
+            let path = config_path(global.config.as_deref());
+            admin::write_default_config(&path, force)?;
+            println!("已写入配置文件 {}", path.display());
+            let config = Config::load_with(Some(&path), &global.overrides(&ServeArgs::default()))?;
+            match admin::init(&config)? {
+                Some(password) => {
+                    println!("已创建 admin 账号，初始密码 {}，请登录后修改", password)
+                }
+                None => println!("数据库中已有用户，未创建 admin 账号"),
+            }
+            Ok(())
+        }
+        Some(Command::User(command)) => {
+            let config = global.load(&ServeArgs::default())?;
+            user(&config, command)
+        }
+        Some(Command::Migrate) => {
+            let config = global.load(&ServeArgs::default())?;
+            let applied = admin::migrate(&config.database)?;
+            if applied.is_empty() {
+                println!("数据库已是最新");
+            }
+            for version in applied {
+                println!("已执行迁移 {}", version);
+            }
+            Ok(())
+        }
+        Some(Command::CheckConfig) => {
+            let config = global.load(&ServeArgs::default())?;
+            admin::check_config(&config)?;
+            print!("{}", config.to_toml()?);
+            Ok(())
+        }
+        Some(Command::Info { format }) => {
+            output::info(&admin::system_snapshot(), format);
+            Ok(())
+        }
+        Some(Command::SystemdUnit {
+            user,
+            socket,
+            output_dir,
+        }) => {
+            let config = global.load(&ServeArgs::default())?;
+            systemd_unit(&global, &config, user, socket, output_dir)
+        }
+    }
+}
+
+fn systemd_unit(
+    global: &GlobalArgs,
+    config: &Config,
+    user: Option<String>,
+    socket: bool,
+    output_dir: Option<PathBuf>,
+) -> Result<(), ServerError> {
+    let io_err = |e: std::io::Error| ServerError::Command(e.to_string());
+    let working_dir = std::env::current_dir().map_err(io_err)?;
+    // 只有配置文件存在时才写入 --config，路径转为绝对路径
+    let config_file = Some(config_path(global.config.as_deref()))
+        .filter(|p| p.exists())
+        .map(|p| p.canonicalize())
+        .transpose()
+        .map_err(io_err)?;
+    let options = unit::UnitOptions {
+        exe: std::env::current_exe().map_err(io_err)?,
+        config_file,
+        working_dir,
+        user,
+        socket,
+    };
+    let mut files = vec![("rpanel.service", unit::service(config, &options))];
+    if socket {
+        files.push(("rpanel.socket", unit::socket(config)));
+    }
+    for (name, content) in files {
+        match &output_dir {
+            Some(dir) => {
+                let path = dir.join(name);
+                std::fs::write(&path, content)
+                    .map_err(|e| ServerError::Command(format!("{}: {}", path.display(), e)))?;
+                println!("已写入 {}", path.display());
+            }
+            None => print!("# {}\n{}\n", name, content),
+        }
+    }
+    Ok(())
+}
+
+async fn serve(global: &GlobalArgs, args: &ServeArgs) -> Result<(), ServerError> {
+    let config = global.load(args)?;
+    Server::from_config(config)
+        .run()
+        .await
+        .map_err(|e| ServerError::Command(format!("服务启动失败: {}", e)))
+}
+
+fn user(config: &Config, command: UserCommand) -> Result<(), ServerError> {
+    match command {
+        UserCommand::Add {
+            username,
+            role,
+            password_stdin,
+        } => {
+            let password = read_password(password_stdin)?;
+            let (user, generated) =
+                admin::add_user(&config.database, &username, &role, password.as_deref())?;
+            println!("已创建用户 {} ({})", user.username, user.role);
+            if let Some(password) = generated {
+                println!("初始密码 {}", password);
+            }
+        }
+        UserCommand::Passwd {
+            username,
+            password_stdin,
+        } => {
+            let password = read_password(password_stdin)?;
+            let generated = admin::set_password(&config.database, &username, password.as_deref())?;
+            println!("已重置 {} 的密码", username);
+            if let Some(password) = generated {
+                println!("新密码 {}", password);
+            }
+        }
+        UserCommand::List { format } => {
+            output::users(&admin::list_users(&config.database)?, format);
+        }
+    }
+    Ok(())
+}
 
 #[tokio::main]
-async fn main() {
-    let server = Server::new("127.0.0.1".to_string(), 8080);
-    if let Err(e) = server.run().await {
-        eprintln!("服务启动失败: {}", e);
+async fn main() -> ExitCode {
+    match run(Cli::parse()).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Cli, clap::Error> {
+        Cli::try_parse_from(std::iter::once("rpanel").chain(args.iter().copied()))
+    }
+
+    #[test]
+    fn test_parse_commands() {
+        // 不带子命令时直接接受 serve 的参数
+        let cli = parse(&["--port", "9000", "-c", "/etc/rpanel.toml"]).unwrap();
+        assert!(cli.command.is_none());
+        assert_eq!(cli.serve.port, Some(9000));
+        assert_eq!(cli.global.config, Some("/etc/rpanel.toml".into()));
+
+        // 全局参数可以写在子命令之后
+        let cli = parse(&["serve", "--host", "::", "--database", "a.db"]).unwrap();
+        match cli.command {
+            Some(Command::Serve(args)) => assert_eq!(args.host.as_deref(), Some("::")),
+            other => panic!("{:?}", other),
+        }
+        assert_eq!(cli.global.database.as_deref(), Some("a.db"));
+
+        let cli = parse(&[
+            "user",
+            "add",
+            "bob",
+            "--role",
+            "operator",
+            "--password-stdin",
+        ])
+        .unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Command::User(UserCommand::Add { username, role, password_stdin: true }))
+                if username == "bob" && role == "operator"
+        ));
+        let cli = parse(&["systemd-unit", "--socket", "--user", "www"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Command::SystemdUnit { user: Some(user), socket: true, output_dir: None })
+                if user == "www"
+        ));
+        assert!(matches!(
+            parse(&["info", "--format", "json"]).unwrap().command,
+            Some(Command::Info {
+                format: Format::Json
+            })
+        ));
+
+        // serve 的参数不能和其它子命令混用
+        assert!(parse(&["--port", "9000", "migrate"]).is_err());
+        assert!(parse(&["--set", "server.port"]).is_err());
+        assert!(parse(&["user", "add"]).is_err());
+        assert!(parse(&["unknown"]).is_err());
+    }
+
+    #[test]
+    fn test_overrides() {
+        let cli = parse(&[
+            "--set",
+            "features.audit=false",
+            "--set",
+            "server.port=1",
+            "--database",
+            r#"C:\data\"rpanel".db"#,
+            "--host",
+            "::",
+            "--port",
+            "9000",
+            "--log-level",
+            "debug",
+        ])
+        .unwrap();
+        assert_eq!(
+            cli.global.overrides(&cli.serve),
+            vec![
+                ("features.audit".to_string(), "false".to_string()),
+                ("server.port".to_string(), "1".to_string()),
+                ("server.host".to_string(), r#""::""#.to_string()),
+                ("server.port".to_string(), "9000".to_string()),
+                ("server.log_level".to_string(), r#""debug""#.to_string()),
+                (
+                    "database.url".to_string(),
+                    r#""C:\\data\\\"rpanel\".db""#.to_string()
+                ),
+            ]
+        );
+        assert!(
+            GlobalArgs {
+                config: None,
+                database: None,
+                overrides: Vec::new(),
+            }
+            .overrides(&ServeArgs::default())
+            .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_toml_string() {
+        assert_eq!(toml_string("plain"), r#""plain""#);
+        assert_eq!(toml_string(r#"a"b\c"#), r#""a\"b\\c""#);
     }
 }
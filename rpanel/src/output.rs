@@ -0,0 +1,102 @@
+// 子命令的表格和 JSON 输出
+use backend::admin::{SystemSnapshot, UserSummary};
+use clap::ValueEnum;
+use serde_json::Value;
+
+#[derive(Debug, Clone, Copy, Default, ValueEnum)]
+pub enum Format {
+    #[default]
+    Table,
+    Json,
+}
+
+fn print_json(value: Value) {
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&value).unwrap_or_default()
+    );
+}
+
+/// 按列宽左对齐输出，第一行为表头
+fn print_table(headers: &[&str], rows: &[Vec<String>]) {
+    let mut widths: Vec<usize> = headers.iter().map(|h| h.len()).collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let line = |cells: Vec<&str>| {
+        let text: Vec<String> = cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect();
+        println!("{}", text.join("  ").trim_end());
+    };
+    line(headers.to_vec());
+    for row in rows {
+        line(row.iter().map(String::as_str).collect());
+    }
+}
+
+pub fn users(users: &[UserSummary], format: Format) {
+    match format {
+        Format::Json => print_json(serde_json::to_value(users).unwrap_or_default()),
+        Format::Table => {
+            let rows: Vec<Vec<String>> = users
+                .iter()
+                .map(|u| {
+                    vec![
+                        u.id.to_string(),
+                        u.username.clone(),
+                        u.role.clone(),
+                        u.disabled.to_string(),
+                        u.totp_enabled.to_string(),
+                        u.created_at.format("%Y-%m-%d %H:%M:%S").to_string(),
+                    ]
+                })
+                .collect();
+            print_table(
+                &["ID", "USERNAME", "ROLE", "DISABLED", "2FA", "CREATED"],
+                &rows,
+            );
+        }
+    }
+}
+
+fn mb(kb: u64) -> String {
+    format!("{:.1} MiB", kb as f64 / 1024.0)
+}
+
+pub fn info(info: &SystemSnapshot, format: Format) {
+    match format {
+        Format::Json => print_json(serde_json::to_value(info).unwrap_or_default()),
+        Format::Table => {
+            let rows = [
+                ("version", info.version.to_string()),
+                ("cpu cores", info.cpu_cores.to_string()),
+                ("cpu usage", format!("{:.1}%", info.cpu_usage_ratio * 100.0)),
+                (
+                    "memory",
+                    format!(
+                        "{} / {} ({:.1}%)",
+                        mb(info.mem_used_kb),
+                        mb(info.mem_total_kb),
+                        info.mem_usage_ratio * 100.0
+                    ),
+                ),
+                (
+                    "swap",
+                    format!(
+                        "{} / {} ({:.1}%)",
+                        mb(info.swap_used_kb),
+                        mb(info.swap_total_kb),
+                        info.swap_usage_ratio * 100.0
+                    ),
+                ),
+            ]
+            .map(|(k, v)| vec![k.to_string(), v]);
+            print_table(&["ITEM", "VALUE"], &rows);
+        }
+    }
+}
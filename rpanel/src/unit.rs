@@ -0,0 +1,137 @@
+// 生成 systemd 单元文件
+use backend::config::Config;
+use std::path::{Path, PathBuf};
+
+/// 生成单元文件需要的参数
+pub struct UnitOptions {
+    pub exe: PathBuf,
+    pub config_file: Option<PathBuf>,
+    pub working_dir: PathBuf,
+    pub user: Option<String>,
+    pub socket: bool,
+}
+
+/// 单元文件中路径含空格时需要加引号
+fn quote(path: &Path) -> String {
+    let s = path.display().to_string();
+    if s.contains(char::is_whitespace) {
+        format!("\"{}\"", s.replace('"', "\\\""))
+    } else {
+        s
+    }
+}
+
+/// rpanel.service，Type=notify 配合启动完成通知和看门狗
+pub fn service(config: &Config, options: &UnitOptions) -> String {
+    let mut exec = quote(&options.exe);
+    if let Some(file) = &options.config_file {
+        exec.push_str(&format!(" --config {}", quote(file)));
+    }
+    exec.push_str(" serve");
+    let mut unit = String::from("[Unit]\nDescription=rpanel server\nAfter=network.target\n");
+    if options.socket {
+        unit.push_str("Requires=rpanel.socket\n");
+    }
+    unit.push_str(&format!(
+        "\n[Service]\nType=notify\nExecStart={}\nWorkingDirectory={}\n",
+        exec,
+        quote(&options.working_dir)
+    ));
+    // 只有启用 HTTPS 时才处理 SIGHUP（重新加载证书），否则 SIGHUP 会结束进程
+    if config.tls.enabled {
+        unit.push_str("ExecReload=/bin/kill -HUP $MAINPID\n");
+    }
+    if let Some(user) = &options.user {
+        unit.push_str(&format!("User={}\n", user));
+    }
+    // 留出时间等待处理中的请求和后台任务
+    unit.push_str(&format!(
+        "TimeoutStopSec={}\nWatchdogSec=30\nRestart=on-failure\nNoNewPrivileges=true\n",
+        config.server.shutdown_timeout_secs + 10
+    ));
+    unit.push_str("\n[Install]\nWantedBy=multi-user.target\n");
+    unit
+}
+
+/// rpanel.socket，启用 HTTPS 重定向时额外监听重定向端口
+pub fn socket(config: &Config) -> String {
+    // IPv6 地址需要加方括号
+    let listen = |port: u16| {
+        if config.server.host.contains(':') {
+            format!("ListenStream=[{}]:{}\n", config.server.host, port)
+        } else {
+            format!("ListenStream={}:{}\n", config.server.host, port)
+        }
+    };
+    let mut unit = String::from("[Unit]\nDescription=rpanel server socket\n\n[Socket]\n");
+    unit.push_str(&listen(config.server.port));
+    if config.tls.enabled
+        && let Some(port) = config.tls.redirect_port
+    {
+        unit.push_str(&listen(port));
+    }
+    unit.push_str("\n[Install]\nWantedBy=sockets.target\n");
+    unit
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options() -> UnitOptions {
+        UnitOptions {
+            exe: "/opt/rpanel/bin/rpanel".into(),
+            config_file: None,
+            working_dir: "/var/lib/rpanel".into(),
+            user: None,
+            socket: false,
+        }
+    }
+
+    #[test]
+    fn test_service() {
+        let config = Config::default();
+        assert_eq!(
+            service(&config, &options()),
+            "[Unit]\nDescription=rpanel server\nAfter=network.target\n\
+             \n[Service]\nType=notify\nExecStart=/opt/rpanel/bin/rpanel serve\n\
+             WorkingDirectory=/var/lib/rpanel\n\
+             TimeoutStopSec=40\nWatchdogSec=30\nRestart=on-failure\nNoNewPrivileges=true\n\
+             \n[Install]\nWantedBy=multi-user.target\n"
+        );
+
+        let mut config = Config::default();
+        config.tls.enabled = true;
+        let options = UnitOptions {
+            exe: "/opt/my apps/rpanel".into(),
+            config_file: Some("/etc/rpanel/rpanel.toml".into()),
+            user: Some("www".into()),
+            socket: true,
+            ..options()
+        };
+        let unit = service(&config, &options);
+        assert!(unit.contains("Requires=rpanel.socket\n"));
+        assert!(unit.contains(
+            "ExecStart=\"/opt/my apps/rpanel\" --config /etc/rpanel/rpanel.toml serve\n"
+        ));
+        assert!(unit.contains("ExecReload=/bin/kill -HUP $MAINPID\n"));
+        assert!(unit.contains("User=www\n"));
+    }
+
+    #[test]
+    fn test_socket() {
+        let mut config = Config::default();
+        assert_eq!(
+            socket(&config),
+            "[Unit]\nDescription=rpanel server socket\n\n[Socket]\n\
+             ListenStream=127.0.0.1:8080\n\n[Install]\nWantedBy=sockets.target\n"
+        );
+        // 未启用 HTTPS 时不监听重定向端口
+        config.server.host = "::".into();
+        config.tls.redirect_port = Some(80);
+        assert!(!socket(&config).contains(":80\n"));
+        config.tls.enabled = true;
+        let unit = socket(&config);
+        assert!(unit.contains("ListenStream=[::]:8080\nListenStream=[::]:80\n"));
+    }
+}